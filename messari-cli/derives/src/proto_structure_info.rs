@@ -10,14 +10,28 @@ use std::borrow::BorrowMut;
 #[derive(PartialEq, Clone, Debug)]
 pub struct MessageInfo {
     pub type_name: String,
+    /// Type name including the package (eg. google.protobuf.Timestamp)
+    pub full_type_name: String,
+    /// Whether this is the generated entry type of a proto map<> field
+    pub is_map_entry: bool,
     pub field_specification: FieldSpecification,
     pub fields: Vec<FieldInfo>,
     pub oneof_groups: Vec<Vec<u64>>
 }
 
 impl MessageInfo {
-    pub fn new(proto_descriptors: &Vec<FileDescriptorProto>, proto_type_name: &str, field_specification: FieldSpecification, parent_message_descriptor: Option<&DescriptorProto>) -> Self {
-        let message = get_proto_type(proto_descriptors, proto_type_name, parent_message_descriptor);
+    /// Builds the info for the message with the given fully qualified type name (eg. proto:messari.erc20.v1.Transfers)
+    pub fn new(proto_descriptors: &Vec<FileDescriptorProto>, proto_type_name: &str, field_specification: FieldSpecification) -> Result<Self, String> {
+        Self::new_with_ancestors(proto_descriptors, proto_type_name, field_specification, &[])
+    }
+
+    /// Ancestor type names are the full type names of all of the messages this message is nested in, so that fields
+    /// referencing any of them (directly or indirectly recursive messages) can be stopped from being expanded forever
+    fn new_with_ancestors(proto_descriptors: &Vec<FileDescriptorProto>, proto_type_name: &str, field_specification: FieldSpecification, ancestor_type_names: &[String]) -> Result<Self, String> {
+        let full_type_name = get_full_type_name(proto_type_name).to_string();
+        let message = get_proto_type(proto_descriptors, &full_type_name)?;
+
+        let ancestor_type_names = ancestor_type_names.iter().cloned().chain([full_type_name.clone()]).collect::<Vec<_>>();
 
         let mut oneof_group_mappings: HashMap<u64, HashSet<u64>> = HashMap::new();
         let mut fields = Vec::new();
@@ -35,7 +49,8 @@ impl MessageInfo {
             }
             fields.push(FieldInfo {
                 field_name: field.name().to_string(),
-                field_type: field.get_field_type(proto_descriptors, &field_specification, message),
+                field_type: field.get_field_type(proto_descriptors, &field_specification, &ancestor_type_names)
+                    .map_err(|error| format!("Unable to get the type of field: {} in message: {}! {}", field.name(), full_type_name, error))?,
                 field_specification,
                 field_number,
             });
@@ -53,17 +68,21 @@ impl MessageInfo {
         let oneof_fields = oneof_groups.clone().into_iter().flat_map(|x| x).collect::<Vec<_>>();
         for field in fields.iter_mut() {
             if oneof_fields.contains(&field.field_number) {
-                assert_eq!(field.field_specification, FieldSpecification::Required, "All oneof fields should be required! TODO: Flesh out this error");
+                if field.field_specification != FieldSpecification::Required {
+                    return Err(format!("Oneof field: {} in message: {} is {:?}! All oneof fields should be required.", field.field_name, full_type_name, field.field_specification));
+                }
                 field.set_as_optional_field(); // Once the oneof is taken to account each field inside will become optional so this needs to be taken into account
             }
         }
 
-        MessageInfo {
-            type_name: message.name().to_string(), // TODO: Should probably make sure this isn't a "full path type" and instead just the actual type name as specified in the proto
+        Ok(MessageInfo {
+            type_name: message.name().to_string(),
+            full_type_name,
+            is_map_entry: message.options.as_ref().map(|options| options.map_entry()).unwrap_or(false),
             field_specification,
             fields,
             oneof_groups,
-        }
+        })
     }
 
     pub fn assert_block_number_field_not_manually_specified(&self) {
@@ -72,6 +91,21 @@ impl MessageInfo {
         }
     }
 
+    /// Same as above but for all of the column names the sink is going to add in (eg. the block metadata columns)
+    pub fn assert_fields_not_manually_specified(&self, column_names: &[&str]) {
+        if let Err(error) = self.check_fields_not_manually_specified(column_names) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Same as above but returns an error instead of panicking
+    pub fn check_fields_not_manually_specified(&self, column_names: &[&str]) -> Result<(), String> {
+        match self.fields.iter().find(|field| column_names.contains(&field.field_name.as_str())) {
+            Some(field) => Err(format!("Can't specify \"{}\" for a top hierachy field of: {} as we are already manually adding this in field in the sink - please remove this!", field.field_name, self.type_name)),
+            None => Ok(()),
+        }
+    }
+
     pub fn is_collection_of_items(&self) -> bool {
         if self.fields.len()==1 {
             let inner_field = self.fields[0].borrow();
@@ -110,12 +144,12 @@ pub struct EnumInfo {
 }
 
 impl EnumInfo {
-    pub fn new(proto_descriptors: &Vec<FileDescriptorProto>, proto_type_name: &str, parent_message_descriptor: &DescriptorProto) -> Self {
-        let enum_type = get_enum_type(proto_descriptors, proto_type_name, parent_message_descriptor);
+    pub fn new(proto_descriptors: &Vec<FileDescriptorProto>, proto_type_name: &str) -> Result<Self, String> {
+        let enum_type = get_enum_type(proto_descriptors, proto_type_name)?;
 
-        EnumInfo {
+        Ok(EnumInfo {
             enum_mappings: enum_type.value.iter().map(|enum_value| (enum_value.number.unwrap() as u64, enum_value.name().to_string())).collect(),
-        }
+        })
     }
 
     pub fn from_fields(mut starting_tag: u8, field_names: Vec<String>) -> Self {
@@ -160,7 +194,7 @@ impl FieldInfo {
     pub fn get_struct_info(self) -> (MessageInfo, String) {
         match self.field_type {
             FieldType::Message(message_info) => (message_info, self.field_name),
-            _ => panic!("No message info found! TODO: Flesh out this error some more")
+            _ => panic!("Field: {} is a {:?} field, not a message field!", self.field_name, self.field_type)
         }
     }
 
@@ -201,59 +235,55 @@ pub enum FieldType {
     Sint64
 }
 
-pub fn get_proto_type<'a>(proto_files: &'a Vec<FileDescriptorProto>, proto_type: &str, parent_message_descriptor: Option<&'a DescriptorProto>) -> &'a DescriptorProto {
-    let message_type = proto_type.split('.').last().unwrap().to_string();
-
-    // First check to see if type is internally declared before checking all proto file descriptors
-    if let Some(parent_message_descriptor) = parent_message_descriptor {
-        for proto_message in parent_message_descriptor.nested_type.iter() {
-            if proto_message.name.as_ref().unwrap() == &message_type {
-                return proto_message;
-            }
-        }
-    }
+/// Strips the module output prefix and the leading '.' of proto descriptor type names (eg. proto:messari.erc20.v1.Transfer
+/// or .messari.erc20.v1.Transfer -> messari.erc20.v1.Transfer)
+pub fn get_full_type_name(proto_type: &str) -> &str {
+    proto_type.trim_start_matches("proto:").trim_start_matches('.')
+}
 
-    // If not internally declared it should be declared in the root of one of the attached file descriptors
-    for proto in proto_files.iter() {
-        if proto_type.contains(proto.package.as_ref().unwrap()) {
-            for proto_message in proto.message_type.iter() {
-                if proto_message.name.as_ref().unwrap() == &message_type {
-                    return proto_message;
-                }
-            }
-        }
-    }
+/// Finds the message declared under the fully qualified type name, including messages nested inside other messages
+/// (eg. messari.erc20.v1.Transfer.Log)
+pub fn get_proto_type<'a>(proto_files: &'a Vec<FileDescriptorProto>, proto_type: &str) -> Result<&'a DescriptorProto, String> {
+    let full_type_name = get_full_type_name(proto_type);
 
-    panic!("TODO: Something like: Unable to find proto type!!");
+    proto_files.iter().filter_map(|proto| get_type_scope(proto, full_type_name))
+        .find_map(|(messages, _, type_name)| messages.iter().find(|proto_message| proto_message.name() == type_name))
+        .ok_or_else(|| format!("Unable to find proto message: {} in any of the proto files!", full_type_name))
 }
 
-pub fn get_enum_type<'a>(proto_files: &'a Vec<FileDescriptorProto>, proto_type: &str, parent_message_descriptor: &'a DescriptorProto) -> &'a EnumDescriptorProto {
-    let message_type = proto_type.split('.').last().unwrap().to_string();
+/// Same as above but for enums
+pub fn get_enum_type<'a>(proto_files: &'a Vec<FileDescriptorProto>, proto_type: &str) -> Result<&'a EnumDescriptorProto, String> {
+    let full_type_name = get_full_type_name(proto_type);
 
-    // First check to see if enum is internally declared before checking all proto file descriptors
-    for proto_enum in parent_message_descriptor.enum_type.iter() {
-        if proto_enum.name.as_ref().unwrap() == &message_type {
-            return proto_enum;
-        }
-    }
+    proto_files.iter().filter_map(|proto| get_type_scope(proto, full_type_name))
+        .find_map(|(_, enums, type_name)| enums.iter().find(|proto_enum| proto_enum.name() == type_name))
+        .ok_or_else(|| format!("Unable to find proto enum: {} in any of the proto files!", full_type_name))
+}
 
-    // If not internally declared it should be declared in the root of one of the attached file descriptors
-    for proto in proto_files.iter() {
-        if proto_type.contains(proto.package.as_ref().unwrap()) {
-            for proto_enum in proto.enum_type.iter() {
-                if proto_enum.name.as_ref().unwrap() == &message_type {
-                    return proto_enum;
-                }
-            }
-        }
+/// If the fully qualified type name falls under the package of the proto file, this returns the messages and enums
+/// declared in the scope of the type (the root of the file or the message it's nested in) along with the type's own name
+fn get_type_scope<'a, 'b>(proto: &'a FileDescriptorProto, full_type_name: &'b str) -> Option<(&'a [DescriptorProto], &'a [EnumDescriptorProto], &'b str)> {
+    let type_path = match proto.package() {
+        "" => full_type_name,
+        package => full_type_name.strip_prefix(package)?.strip_prefix('.')?,
+    };
+
+    let mut type_names = type_path.split('.').collect::<Vec<_>>();
+    let type_name = type_names.pop()?;
+
+    let (mut messages, mut enums) = (proto.message_type.as_slice(), proto.enum_type.as_slice());
+    for parent_type_name in type_names {
+        let parent_message = messages.iter().find(|proto_message| proto_message.name() == parent_type_name)?;
+        messages = parent_message.nested_type.as_slice();
+        enums = parent_message.enum_type.as_slice();
     }
 
-    panic!("TODO: Something like: Unable to find proto enum!!");
+    Some((messages, enums, type_name))
 }
 
 trait ProtoFieldExt {
     fn get_field_specification(&self) -> FieldSpecification;
-    fn get_field_type(&self, proto_descriptors: &Vec<FileDescriptorProto>, field_specification: &FieldSpecification, parent_message_descriptor: &DescriptorProto) -> FieldType;
+    fn get_field_type(&self, proto_descriptors: &Vec<FileDescriptorProto>, field_specification: &FieldSpecification, ancestor_type_names: &[String]) -> Result<FieldType, String>;
     fn get_field_number(&self) -> u64;
     fn get_oneof_index(&self) -> Option<u64>;
 }
@@ -290,8 +320,10 @@ impl ProtoFieldExt for FieldDescriptorProto {
         }
     }
 
-    fn get_field_type(&self, proto_descriptors: &Vec<FileDescriptorProto>, field_specification: &FieldSpecification, parent_message_descriptor: &DescriptorProto) -> FieldType {
-        match self.r#type.unwrap() {
+    /// Messages referencing themselves (or any message they are nested in) can't be flattened into columns, so these
+    /// fields are kept as the raw proto bytes of the message instead
+    fn get_field_type(&self, proto_descriptors: &Vec<FileDescriptorProto>, field_specification: &FieldSpecification, ancestor_type_names: &[String]) -> Result<FieldType, String> {
+        let field_type = match self.r#type.unwrap() {
             x if x == (Type::Double as i32) => FieldType::Double,
             x if x == (Type::Float as i32) => FieldType::Float,
             x if x == (Type::Int64 as i32) => FieldType::Int64,
@@ -302,19 +334,25 @@ impl ProtoFieldExt for FieldDescriptorProto {
             x if x == (Type::Bool as i32) => FieldType::Bool,
             x if x == (Type::String as i32) => FieldType::String,
             x if x == (Type::Message as i32) => {
-                FieldType::Message(MessageInfo::new(proto_descriptors, self.type_name(), field_specification.clone(), Some(parent_message_descriptor)))
+                if ancestor_type_names.iter().any(|ancestor_type_name| ancestor_type_name == get_full_type_name(self.type_name())) {
+                    FieldType::Bytes
+                } else {
+                    FieldType::Message(MessageInfo::new_with_ancestors(proto_descriptors, self.type_name(), field_specification.clone(), ancestor_type_names)?)
+                }
             },
             x if x == (Type::Bytes as i32) => FieldType::Bytes,
             x if x == (Type::Uint32 as i32) => FieldType::Uint32,
             x if x == (Type::Enum as i32) => {
-                FieldType::Enum(EnumInfo::new(proto_descriptors, self.type_name(), parent_message_descriptor))
+                FieldType::Enum(EnumInfo::new(proto_descriptors, self.type_name())?)
             },
             x if x == (Type::Sfixed32 as i32) => FieldType::Sfixed32,
             x if x == (Type::Sfixed64 as i32) => FieldType::Sfixed64,
             x if x == (Type::Sint32 as i32) => FieldType::Sint32,
             x if x == (Type::Sint64 as i32) => FieldType::Sint64,
-            _ => unreachable!()
-        }
+            x => return Err(format!("Unsupported proto field type: {}!", x)),
+        };
+
+        Ok(field_type)
     }
 
     fn get_field_number(&self) -> u64 {
@@ -101,6 +101,8 @@ pub fn generate(name: &Ident, data_struct: DataStruct, starting_tag: u8) -> Toke
 
                 derives::proto_structure_info::MessageInfo {
                     type_name: stringify!(#name).to_string(),
+                    full_type_name: stringify!(#name).to_string(),
+                    is_map_entry: false,
                     field_specification: derives::proto_structure_info::FieldSpecification::Required, // Get's overriden by parent struct later if a subfield to another type
                     fields,
                     oneof_groups: #oneof_groups_initialisation
@@ -132,8 +132,8 @@ pub(crate) fn add_abis(
         );
 
         let contract_name = get_input("Contract Name", None, false);
-        add_abi_to_project(abi_file_contents, &contract_name, project_dir);
-        add_block_to_object_mapping_code(contract_name);
+        add_abi_to_project(&abi_file_contents, &contract_name, project_dir);
+        add_block_to_object_mapping_code(&abi_file_contents, &contract_name, project_dir);
     } else {
         if let Some(contract_names) = contract_names {
             for (abi_info, contract_name) in abi_infos.into_iter().zip(contract_names.into_iter()) {
@@ -143,8 +143,8 @@ pub(crate) fn add_abis(
                     &protocol_and_network_info.network,
                 );
 
-                add_abi_to_project(abi_file_contents, &contract_name, project_dir);
-                add_block_to_object_mapping_code(contract_name);
+                add_abi_to_project(&abi_file_contents, &contract_name, project_dir);
+                add_block_to_object_mapping_code(&abi_file_contents, &contract_name, project_dir);
             }
         } else {
             for abi_info in abi_infos {
@@ -155,8 +155,8 @@ pub(crate) fn add_abis(
                 );
 
                 let contract_name = get_input("Contract Name", None, false);
-                add_abi_to_project(abi_file_contents, &contract_name, project_dir);
-                add_block_to_object_mapping_code(contract_name);
+                add_abi_to_project(&abi_file_contents, &contract_name, project_dir);
+                add_block_to_object_mapping_code(&abi_file_contents, &contract_name, project_dir);
             }
         }
     }
@@ -188,8 +188,8 @@ pub(crate) fn add_abis(
         );
 
         let contract_name = get_input("Contract Name", None, false);
-        add_abi_to_project(abi_file_contents, &contract_name, project_dir);
-        add_block_to_object_mapping_code(contract_name);
+        add_abi_to_project(&abi_file_contents, &contract_name, project_dir);
+        add_block_to_object_mapping_code(&abi_file_contents, &contract_name, project_dir);
     }
 }
 
@@ -290,7 +290,7 @@ fn download_abi(
     serde_json::to_string_pretty(&result_json).unwrap()
 }
 
-fn add_abi_to_project(abi_file_contents: String, contract_name: &String, project_dir: &PathBuf) {
+fn add_abi_to_project(abi_file_contents: &str, contract_name: &String, project_dir: &PathBuf) {
     let abi_dir = project_dir.join("abi");
     let contract_filepath = if contract_name.ends_with(".json") {
         abi_dir.join(contract_name)
@@ -327,7 +327,7 @@ fn add_abi_to_project(abi_file_contents: String, contract_name: &String, project
 
     operations.push(FileContentsModification::CreateFile(File {
         filepath: contract_filepath,
-        file_contents: abi_file_contents,
+        file_contents: abi_file_contents.to_string(),
     }));
 
     safely_modify_file_contents(operations);
@@ -1,3 +1,372 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::file_modification::file_contents_modifier::{
+    safely_modify_file_contents, File, FileContentsModification,
+};
+use crate::file_modification::substreams_yaml::{Input, InputType, Module, SubstreamsYaml};
+use crate::module::get_add_module_operations;
+use crate::terminal_interface::Spinner;
+
+/// Fields added to every event message on top of the event's own params
+const EVENT_METADATA_FIELDS: [(&'static str, &'static str, &'static str); 4] = [
+    ("string", "tx_hash", "Hex(&log.receipt.transaction.hash).to_string()"),
+    ("uint32", "log_index", "log.index()"),
+    ("uint64", "log_ordinal", "log.ordinal()"),
+    ("string", "contract_address", "Hex(log.address()).to_string()"),
+];
+
+#[derive(Deserialize)]
+struct AbiItem {
+    #[serde(rename = "type")]
+    item_type: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    inputs: Vec<AbiParam>,
+    #[serde(default)]
+    anonymous: bool,
+}
+
+#[derive(Deserialize)]
+struct AbiParam {
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type")]
+    param_type: String,
+}
+
+struct EventField {
+    field_name: String,
+    proto_type: String,
+    is_repeated: bool,
+    /// Expression converting the decoded abi value into the proto field value
+    conversion: String,
+}
+
 /// Adds the boilerplate code for mapping from a block to the abi object
 /// (in most cases it's possible to generate the code for this automatically)
-pub(crate) fn add_block_to_object_mapping_code(_contract_name: String) {}
+///
+/// This generates a proto with a message for each of the contract's events (wrapped in a oneof), a
+/// map_{contract}_events module decoding all of the events from the block and it's substreams.yaml entry
+pub(crate) fn add_block_to_object_mapping_code(abi_file_contents: &str, contract_name: &str, project_dir: &PathBuf) {
+    let contract_name = contract_name.trim_end_matches(".json");
+    let contract_snake_name = to_snake_case(contract_name);
+    let contract_camel_name = to_upper_camel_case(&contract_snake_name);
+    let module_name = format!("map_{}_events", contract_snake_name);
+
+    let abi_items: Vec<AbiItem> = serde_json::from_str(abi_file_contents).expect(&format!(
+        "Unable to parse abi for contract: {}!",
+        contract_name
+    ));
+
+    let mut events = Vec::new();
+    for abi_item in abi_items.into_iter().filter(|abi_item| abi_item.item_type == "event") {
+        if abi_item.anonymous {
+            println!("Skipping anonymous event: {} of contract: {} as it can't be matched from the logs", abi_item.name, contract_name);
+        } else if events.iter().any(|event: &AbiItem| event.name == abi_item.name) {
+            println!("Skipping overloaded event: {} of contract: {} as only the first declaration gets mapped", abi_item.name, contract_name);
+        } else {
+            events.push(abi_item);
+        }
+    }
+    if events.is_empty() {
+        println!("No events found in the abi for contract: {} - skipping event mapping code", contract_name);
+        return;
+    }
+
+    let substreams_yaml_filepath = project_dir.join("substreams.yaml");
+    let mut substreams_yaml = SubstreamsYaml::load_from_file(&substreams_yaml_filepath);
+    if substreams_yaml.get_module_output_type(&module_name).is_some() {
+        println!("Module: {} already exists - skipping event mapping code for contract: {}", module_name, contract_name);
+        return;
+    }
+
+    let spinner = Spinner::new(format!("Adding event mapping code for {}", contract_name));
+
+    let proto_package = format!("messari.{}_events.v1", contract_snake_name);
+    let proto_dir = project_dir.join("proto");
+    let proto_filepath = proto_dir.join(format!("{}_events.proto", contract_snake_name));
+
+    let events = events
+        .into_iter()
+        .map(|event| {
+            let event_name = to_upper_camel_case(&to_snake_case(&event.name));
+            let event_fields = event
+                .inputs
+                .iter()
+                .enumerate()
+                .map(|(index, param)| get_event_field(param, index))
+                .collect::<Vec<_>>();
+            (event_name, event_fields)
+        })
+        .collect::<Vec<_>>();
+
+    let module = Module::map(
+        module_name.clone(),
+        None,
+        vec![Input {
+            input_type: InputType::Source,
+            input_value: "sf.ethereum.type.v2.Block".to_string(),
+        }],
+        format!("proto:{}.{}Events", proto_package, contract_camel_name),
+    );
+    let handler_file_contents = get_handler_file_contents(contract_name, &contract_snake_name, &contract_camel_name, &module_name, &events);
+
+    let (mut operations, _) = get_add_module_operations(project_dir, &mut substreams_yaml, module, handler_file_contents);
+
+    if !proto_dir.exists() {
+        operations.push(FileContentsModification::CreateFolder(proto_dir));
+    }
+    operations.push(FileContentsModification::CreateFile(File {
+        filepath: proto_filepath.clone(),
+        file_contents: get_proto_file_contents(&proto_package, &contract_camel_name, &events),
+    }));
+
+    substreams_yaml.add_protobuf_files(vec![proto_filepath]);
+    operations.push(FileContentsModification::UpdateFile(File {
+        filepath: substreams_yaml_filepath,
+        file_contents: substreams_yaml.get_file_contents(),
+    }));
+
+    safely_modify_file_contents(operations);
+
+    spinner.end_with_success_message(format!("Event mapping code added for {} in module: {}", contract_name, module_name));
+}
+
+/// Params are named the same way the abi bindings name them, with unnamed params named after their position
+fn get_event_field(param: &AbiParam, index: usize) -> EventField {
+    let mut field_name = if param.name.is_empty() {
+        format!("param{}", index)
+    } else {
+        to_snake_case(&param.name)
+    };
+    let value = format!("event.{}", field_name);
+    if EVENT_METADATA_FIELDS.iter().any(|(_, metadata_field_name, _)| metadata_field_name == &field_name) {
+        field_name = format!("{}_param", field_name);
+    }
+
+    // Arrays of simple types become repeated fields, anything nested deeper is kept as it's debug representation
+    if let Some(item_type) = param.param_type.strip_suffix(']').and_then(|param_type| param_type.rsplit_once('[')).map(|(item_type, _)| item_type) {
+        if let Some((proto_type, item_conversion)) = get_proto_type_and_conversion(item_type, "value") {
+            return EventField {
+                field_name,
+                proto_type: proto_type.to_string(),
+                is_repeated: true,
+                conversion: format!("{}.into_iter().map(|value| {}).collect()", value, item_conversion),
+            };
+        }
+    } else if let Some((proto_type, conversion)) = get_proto_type_and_conversion(&param.param_type, &value) {
+        return EventField {
+            field_name,
+            proto_type: proto_type.to_string(),
+            is_repeated: false,
+            conversion,
+        };
+    }
+
+    EventField {
+        field_name,
+        proto_type: "string".to_string(),
+        is_repeated: false,
+        conversion: format!("format!(\"{{:?}}\", {})", value),
+    }
+}
+
+/// Addresses, bytes and integers (which can be larger than 64 bits) are all stored as strings
+fn get_proto_type_and_conversion(param_type: &str, value: &str) -> Option<(&'static str, String)> {
+    match param_type {
+        "bool" => Some(("bool", value.to_string())),
+        "string" => Some(("string", value.to_string())),
+        "address" => Some(("string", format!("Hex(&{}).to_string()", value))),
+        param_type if param_type.starts_with("bytes") => Some(("string", format!("Hex(&{}).to_string()", value))),
+        param_type if param_type.starts_with("uint") || param_type.starts_with("int") => Some(("string", format!("{}.to_string()", value))),
+        _ => None,
+    }
+}
+
+fn get_proto_file_contents(proto_package: &str, contract_camel_name: &str, events: &[(String, Vec<EventField>)]) -> String {
+    let oneof_fields = events
+        .iter()
+        .enumerate()
+        .map(|(index, (event_name, _))| format!("    {} {} = {};\n", event_name, to_snake_case(event_name), index + 1))
+        .collect::<String>();
+
+    let event_messages = events
+        .iter()
+        .map(|(event_name, event_fields)| {
+            let metadata_fields = EVENT_METADATA_FIELDS
+                .iter()
+                .map(|(proto_type, field_name, _)| (proto_type.to_string(), field_name.to_string()));
+            let param_fields = event_fields.iter().map(|event_field| {
+                let proto_type = if event_field.is_repeated {
+                    format!("repeated {}", event_field.proto_type)
+                } else {
+                    event_field.proto_type.clone()
+                };
+                (proto_type, event_field.field_name.clone())
+            });
+
+            let fields = metadata_fields
+                .chain(param_fields)
+                .enumerate()
+                .map(|(index, (proto_type, field_name))| format!("  {} {} = {};\n", proto_type, field_name, index + 1))
+                .collect::<String>();
+
+            format!("message {} {{\n{}}}\n", event_name, fields)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "syntax = \"proto3\";\n\
+        \n\
+        package {};\n\
+        \n\
+        message {1}Events {{\n  \
+            repeated {1}Event items = 1;\n\
+        }}\n\
+        \n\
+        message {1}Event {{\n  \
+            oneof event {{\n\
+                {2}  \
+            }}\n\
+        }}\n\
+        \n\
+        {3}",
+        proto_package, contract_camel_name, oneof_fields, event_messages
+    )
+}
+
+fn get_handler_file_contents(contract_name: &str, contract_snake_name: &str, contract_camel_name: &str, module_name: &str, events: &[(String, Vec<EventField>)]) -> String {
+    let event_matches = events
+        .iter()
+        .map(|(event_name, event_fields)| {
+            let fields = EVENT_METADATA_FIELDS
+                .iter()
+                .map(|(_, field_name, conversion)| (field_name.to_string(), conversion.to_string()))
+                .chain(event_fields.iter().map(|event_field| (event_field.field_name.clone(), event_field.conversion.clone())))
+                .map(|(field_name, conversion)| format!("                {}: {},\n", field_name, conversion))
+                .collect::<String>();
+
+            format!(
+                "if let Some(event) = events::{}::match_and_decode(log) {{\n            \
+                    Event::{0}(pb::{0} {{\n\
+                        {1}            \
+                    }})\n        \
+                }}",
+                event_name, fields
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" else ");
+
+    format!(
+        "use substreams::Hex;\n\
+        use substreams_ethereum::pb::eth::v2 as eth;\n\
+        use substreams_ethereum::Event as _;\n\
+        \n\
+        use crate::abi::{0}::events;\n\
+        use crate::pb::{1}_events::v1 as pb;\n\
+        use crate::pb::{1}_events::v1::{1}_event::Event;\n\
+        \n\
+        /// Extracts all of the {0} events from the blocks\n\
+        #[substreams::handlers::map]\n\
+        fn {3}(block: eth::Block) -> Result<pb::{2}Events, substreams::errors::Error> {{\n    \
+            let mut items = Vec::new();\n\
+            \n    \
+            for log in block.logs() {{\n        \
+                let event = {4} else {{\n            \
+                    continue;\n        \
+                }};\n\
+                \n        \
+                items.push(pb::{2}Event {{ event: Some(event) }});\n    \
+            }}\n\
+            \n    \
+            Ok(pb::{2}Events {{ items }})\n\
+        }}\n",
+        contract_name, contract_snake_name, contract_camel_name, module_name, event_matches
+    )
+}
+
+/// Same conversion the abi bindings and prost use for names (eg. onBehalfOf -> on_behalf_of, ERC20 -> erc20)
+fn to_snake_case(name: &str) -> String {
+    let chars = name.trim_start_matches('_').chars().collect::<Vec<_>>();
+
+    let mut snake_case = String::new();
+    for (index, character) in chars.iter().enumerate() {
+        if character.is_uppercase() && index > 0 {
+            let previous_character = chars[index - 1];
+            let next_is_lowercase = chars.get(index + 1).map(|next_character| next_character.is_lowercase()).unwrap_or(false);
+            if previous_character.is_lowercase() || previous_character.is_numeric() || (previous_character.is_uppercase() && next_is_lowercase) {
+                snake_case.push('_');
+            }
+        }
+        snake_case.extend(character.to_lowercase());
+    }
+
+    snake_case
+}
+
+fn to_upper_camel_case(snake_case_name: &str) -> String {
+    snake_case_name
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first_character) => first_character.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::automapper::{get_event_field, get_proto_file_contents, to_snake_case, to_upper_camel_case, AbiParam};
+
+    #[test]
+    fn test_name_conversions() {
+        assert_eq!(to_snake_case("onBehalfOf"), "on_behalf_of");
+        assert_eq!(to_snake_case("_spender"), "spender");
+        assert_eq!(to_snake_case("ERC20"), "erc20");
+        assert_eq!(to_snake_case("LendingPoolConfigurator"), "lending_pool_configurator");
+        assert_eq!(to_snake_case("ATokenUpgraded"), "a_token_upgraded");
+        assert_eq!(to_upper_camel_case("erc20"), "Erc20");
+        assert_eq!(to_upper_camel_case("a_token_upgraded"), "ATokenUpgraded");
+    }
+
+    #[test]
+    fn test_event_proto_generation() {
+        let params = [("from", "address"), ("", "uint256"), ("tx_hash", "bytes32"), ("recipients", "address[]"), ("route", "tuple")];
+        let event_fields = params.iter().enumerate().map(|(index, (name, param_type))| get_event_field(&AbiParam {
+            name: name.to_string(),
+            param_type: param_type.to_string(),
+        }, index)).collect::<Vec<_>>();
+
+        assert_eq!(event_fields[1].conversion, "event.param1.to_string()");
+        assert_eq!(event_fields[3].conversion, "event.recipients.into_iter().map(|value| Hex(&value).to_string()).collect()");
+        assert_eq!(event_fields[4].conversion, "format!(\"{:?}\", event.route)");
+
+        let proto_file_contents = get_proto_file_contents("messari.erc20_events.v1", "Erc20", &[("Transfer".to_string(), event_fields)]);
+        assert_eq!(proto_file_contents, "syntax = \"proto3\";\n\
+            \n\
+            package messari.erc20_events.v1;\n\
+            \n\
+            message Erc20Events {\n  repeated Erc20Event items = 1;\n}\n\
+            \n\
+            message Erc20Event {\n  oneof event {\n    Transfer transfer = 1;\n  }\n}\n\
+            \n\
+            message Transfer {\n  \
+            string tx_hash = 1;\n  \
+            uint32 log_index = 2;\n  \
+            uint64 log_ordinal = 3;\n  \
+            string contract_address = 4;\n  \
+            string from = 5;\n  \
+            string param1 = 6;\n  \
+            string tx_hash_param = 7;\n  \
+            repeated string recipients = 8;\n  \
+            string route = 9;\n\
+            }\n");
+    }
+}
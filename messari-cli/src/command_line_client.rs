@@ -1,10 +1,17 @@
 use crate::commands::add::Add;
 use clap::Parser;
+use crate::commands::audit::Audit;
 use crate::commands::block_range_info::BlockRangeInfo;
+use crate::commands::compact::Compact;
+use crate::commands::graph::Graph;
 
 use crate::commands::init::Init;
 use crate::commands::process::Process;
+use crate::commands::process_all::ProcessAll;
+use crate::commands::schema::Schema;
 use crate::commands::upload_cli_to_aws::UploadCliToAws;
+use crate::commands::validate::Validate;
+use crate::commands::versions::Versions;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -12,8 +19,15 @@ pub(crate) enum CommandLineClient {
     Init(Init),
     Add(Add),
     Process(Process),
+    ProcessAll(ProcessAll),
     BlockRangeInfo(BlockRangeInfo),
+    Schema(Schema),
+    Audit(Audit),
+    Compact(Compact),
     UploadCliToAws(UploadCliToAws),
+    Versions(Versions),
+    Validate(Validate),
+    Graph(Graph),
 }
 
 impl CommandLineClient {
@@ -22,8 +36,15 @@ impl CommandLineClient {
             CommandLineClient::Init(cmd) => cmd.execute(),
             CommandLineClient::Add(cmd) => cmd.execute(),
             CommandLineClient::Process(cmd) => cmd.execute().await,
+            CommandLineClient::ProcessAll(cmd) => cmd.execute().await,
             CommandLineClient::BlockRangeInfo(cmd) => cmd.execute().await,
+            CommandLineClient::Schema(cmd) => cmd.execute(),
+            CommandLineClient::Audit(cmd) => cmd.execute().await,
+            CommandLineClient::Compact(cmd) => cmd.execute().await,
             CommandLineClient::UploadCliToAws(cmd) => cmd.execute().await,
+            CommandLineClient::Versions(cmd) => cmd.execute(),
+            CommandLineClient::Validate(cmd) => cmd.execute(),
+            CommandLineClient::Graph(cmd) => cmd.execute(),
         }
     }
 }
@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use strum_macros::{EnumIter, EnumVariantNames};
 
 use crate::abi::{add_abis, AbisArgs};
+use crate::module::{add_module, ModuleArgs};
 use crate::protocols::ProtocolAndNetworkArgs;
 use crate::terminal_interface::select_from_enum;
 use crate::utils::get_current_directory;
@@ -15,13 +16,15 @@ pub(crate) struct Add {
         short = 'd',
         long,
         value_name = "Project Directory",
-        help = "Specify where the project is that you want to add ABI to. Relative paths should start with \"./\" or \"../\". Leave blank to use the current directory."
+        help = "Specify where the project is that you want to add the ABI or module to. Relative paths should start with \"./\" or \"../\". Leave blank to use the current directory."
     )]
     pub(crate) project_dir: Option<String>,
     #[clap(flatten)]
     pub(crate) protocol_and_network_args: ProtocolAndNetworkArgs,
     #[clap(flatten)]
     pub(crate) abis_arg: AbisArgs,
+    #[clap(flatten)]
+    pub(crate) module_args: ModuleArgs,
 }
 
 impl Add {
@@ -65,6 +68,9 @@ impl Add {
             AddOperationType::Abi => {
                 execute_add_abi(project_dir, &self.protocol_and_network_args, &self.abis_arg);
             }
+            AddOperationType::Module => {
+                add_module(&self.module_args, &project_dir);
+            }
         }
     }
 }
@@ -72,6 +78,7 @@ impl Add {
 #[derive(ValueEnum, EnumIter, EnumVariantNames, Clone)]
 pub(crate) enum AddOperationType {
     Abi,
+    Module,
 }
 
 fn execute_add_abi(
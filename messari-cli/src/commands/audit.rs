@@ -0,0 +1,127 @@
+use clap::Parser;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::commands::process::{EncodingType, LocationType};
+use crate::streaming_fast::file::LocationType as Location;
+use crate::streaming_fast::file_sinks::csv::RepeatedFieldPolicy;
+use crate::streaming_fast::process_substream::{audit_output_folders, EncodingType as Encoding, process_substream};
+use crate::streaming_fast::streaming_config::ConfigArg;
+
+#[derive(Parser)]
+pub(crate) struct Audit {
+    spkg_path: String,
+    #[clap(flatten)]
+    config: ConfigArg,
+    #[arg(short, long, value_name = "Location Type", help="Defaults to checking local filepath.")]
+    location_type: Option<LocationType>,
+    #[arg(short, long, value_name = "Data location path", help="If not specified it will default to check substreams on aws and ./data/ on local.")]
+    data_location_path: Option<String>,
+    #[arg(short, long, value_name = "Bucket", help="Mandatory if location type is DWH")]
+    bucket: Option<String>,
+    #[arg(long, value_name = "Encoding Type", help="Defaults to parquet.")]
+    encoding: Option<EncodingType>,
+    #[arg(long, help="Reads every file to find files without any rows. This downloads every file when auditing a DWH location.")]
+    count_rows: bool,
+    #[arg(long, help="Processes the block ranges missing from the output folders. Only done for modules written to a single output folder.")]
+    rerun_gaps: bool,
+}
+
+impl Audit {
+    pub(crate) async fn execute(&self) {
+        let spkg_path = PathBuf::from(&self.spkg_path);
+        if !spkg_path.exists() {
+            panic!("The spkg path: {}, you gave here does not exist! Please specify a correct location for the spkg path!", self.spkg_path);
+        }
+
+        let config = self.config.parse();
+
+        let spkg_data = fs::read(spkg_path).unwrap();
+        let location_type = match self.location_type {
+            None => Location::Local,
+            Some(LocationType::Local) => Location::Local,
+            Some(LocationType::Dwh) => {
+                if self.bucket.is_none() {
+                    panic!("Bucket is mandatory if location type is DWH");
+                }
+                Location::DataWarehouse
+            },
+        };
+
+        let encoding_type = match self.encoding {
+            None => Encoding::Parquet(config.parquet.clone()),
+            Some(EncodingType::Parquet) => Encoding::Parquet(config.parquet.clone()),
+            Some(EncodingType::Jsonl) => Encoding::JsonL(config.parquet.file_size_threshold),
+            // The repeated field policy only changes the file contents, not where they get written to
+            Some(EncodingType::Csv) => Encoding::Csv(RepeatedFieldPolicy::JsonCell, config.parquet.file_size_threshold),
+        };
+
+        let data_location_path = self.data_location_path.clone().map(|path| PathBuf::from(path));
+
+        let module_audits = audit_output_folders(spkg_data.clone(), config.get_output_modules(), encoding_type.clone(), location_type.clone(), data_location_path.clone(), self.bucket.clone(), &config.storage, config.partition.clone(), config.get_chain_override(), &config.chains, self.count_rows).await
+            .unwrap_or_else(|error| panic!("Unable to audit output folders! Error: {}", error));
+
+        let mut has_issues = false;
+        let mut gaps_to_rerun = Vec::new();
+        for (module_name, output_folder_audits) in module_audits.iter() {
+            println!("Module: {}", module_name);
+            for output_folder_audit in output_folder_audits.iter() {
+                println!("  Output folder: {} ({} files)", output_folder_audit.folder_display_path, output_folder_audit.files.len());
+                if !output_folder_audit.has_issues() {
+                    println!("    No issues found");
+                    continue;
+                }
+                has_issues = true;
+
+                for (first_missing_block, last_missing_block) in output_folder_audit.gaps.iter() {
+                    println!("    Gap: blocks {} -> {} are missing", first_missing_block, last_missing_block);
+                }
+                for (file_path, overlapping_file_path) in output_folder_audit.overlaps.iter() {
+                    println!("    Overlap: {} overlaps with {}", overlapping_file_path.display(), file_path.display());
+                }
+                for file_path in output_folder_audit.empty_files.iter() {
+                    println!("    Empty file: {}", file_path.display());
+                }
+                for file_path in output_folder_audit.unparsable_files.iter() {
+                    println!("    Unparsable filename: {}", file_path.display());
+                }
+            }
+
+            // Oneof outputs written to several folders can't be rerun for a single folder, as the rerun would write
+            // the same blocks again to the folders that aren't missing them
+            if self.rerun_gaps {
+                match output_folder_audits.as_slice() {
+                    [output_folder_audit] => gaps_to_rerun.extend(output_folder_audit.gaps.iter().map(|gap| (module_name.clone(), *gap))),
+                    _ if output_folder_audits.iter().any(|output_folder_audit| !output_folder_audit.gaps.is_empty()) => {
+                        println!("  Skipping rerun of gaps for module: {} as it's written to more than one output folder", module_name);
+                    },
+                    _ => {},
+                }
+            }
+        }
+
+        let mut num_failed_reruns = 0;
+        for (module_name, (first_missing_block, last_missing_block)) in gaps_to_rerun.iter() {
+            println!("Rerunning blocks {} -> {} for module: {}", first_missing_block, last_missing_block, module_name);
+
+            let mut rerun_config = self.config.parse();
+            rerun_config.output_module = None;
+            rerun_config.output_modules = vec![module_name.clone()];
+
+            // Stop block is exclusive
+            let stop_block = (*last_missing_block + 1) as u64;
+            if let Err(error) = process_substream(spkg_data.clone(), rerun_config, encoding_type.clone(), location_type.clone(), data_location_path.clone(), self.bucket.clone(), Some(*first_missing_block), Some(stop_block), false).await {
+                eprintln!("{}", error);
+                num_failed_reruns += 1;
+            }
+        }
+
+        if !gaps_to_rerun.is_empty() {
+            println!("{}/{} gaps rerun successfully", gaps_to_rerun.len() - num_failed_reruns, gaps_to_rerun.len());
+        }
+
+        if has_issues {
+            std::process::exit(1);
+        }
+    }
+}
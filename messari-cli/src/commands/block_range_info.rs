@@ -2,8 +2,9 @@ use clap::{Parser, ValueEnum};
 use std::fs;
 use std::path::PathBuf;
 
-use crate::streaming_fast::process_substream::get_block_range_info;
+use crate::streaming_fast::process_substream::{EncodingType as Encoding, get_block_range_info};
 use crate::streaming_fast::file::LocationType as Location;
+use crate::streaming_fast::file_sinks::csv::RepeatedFieldPolicy;
 use crate::streaming_fast::streaming_config::ConfigArg;
 
 #[derive(Parser)]
@@ -17,6 +18,8 @@ pub(crate) struct BlockRangeInfo {
     data_location_path: Option<String>,
     #[arg(short, long, value_name = "Bucket", help="Mandatory if location type is DWH")]
     bucket: Option<String>,
+    #[arg(long, value_name = "Encoding Type", help="Defaults to parquet.")]
+    encoding: Option<EncodingType>,
 }
 
 #[derive(ValueEnum, Clone)]
@@ -25,6 +28,13 @@ pub(crate) enum LocationType {
     Dwh
 }
 
+#[derive(ValueEnum, Clone)]
+pub(crate) enum EncodingType {
+    Parquet,
+    Jsonl,
+    Csv
+}
+
 impl BlockRangeInfo {
     pub(crate) async fn execute(&self) {
         let spkg_path = PathBuf::from(&self.spkg_path);
@@ -46,9 +56,18 @@ impl BlockRangeInfo {
             },
         };
 
+        let encoding_type = match self.encoding {
+            None => Encoding::Parquet(config.parquet.clone()),
+            Some(EncodingType::Parquet) => Encoding::Parquet(config.parquet.clone()),
+            Some(EncodingType::Jsonl) => Encoding::JsonL(config.parquet.file_size_threshold),
+            // The repeated field policy only changes the file contents, not where they get written to
+            Some(EncodingType::Csv) => Encoding::Csv(RepeatedFieldPolicy::JsonCell, config.parquet.file_size_threshold),
+        };
+
         let data_location_path = self.data_location_path.clone().map(|path| PathBuf::from(path));
 
-        let (start_block, stop_block) = get_block_range_info(spkg_data, config.output_module.as_str(), location_type, data_location_path, self.bucket.clone(), config.get_start_block_override(), config.chain_override).await;
+        let (start_block, stop_block) = get_block_range_info(spkg_data, config.get_output_modules(), encoding_type, location_type, data_location_path, self.bucket.clone(), &config.storage, config.partition.clone(), config.get_start_block_override(), config.get_chain_override(), &config.chains, config.endpoint_override).await
+            .unwrap_or_else(|error| panic!("Unable to get block range info! Error: {}", error));
 
         println!("{{\"start_block\": {}, \"stop_block\": {}}}", start_block, stop_block);
     }
@@ -0,0 +1,67 @@
+use clap::Parser;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::commands::process::LocationType;
+use crate::streaming_fast::file::LocationType as Location;
+use crate::streaming_fast::process_substream::compact_output_folders;
+use crate::streaming_fast::streaming_config::ConfigArg;
+
+#[derive(Parser)]
+pub(crate) struct Compact {
+    spkg_path: String,
+    #[clap(flatten)]
+    config: ConfigArg,
+    #[arg(short, long, value_name = "Location Type", help="Defaults to compacting local filepath.")]
+    location_type: Option<LocationType>,
+    #[arg(short, long, value_name = "Data location path", help="If not specified it will default to substreams on aws and ./data/ on local.")]
+    data_location_path: Option<String>,
+    #[arg(short, long, value_name = "Bucket", help="Mandatory if location type is DWH")]
+    bucket: Option<String>,
+    #[arg(long, value_name = "Target file size", default_value_t = 128 * 1024 * 1024, help="Size (in bytes) files get merged up to. Files already this size are left as they are.")]
+    target_file_size: usize,
+    #[arg(long, help="Prints the files that would be merged without merging them.")]
+    dry_run: bool,
+}
+
+impl Compact {
+    pub(crate) async fn execute(&self) {
+        let spkg_path = PathBuf::from(&self.spkg_path);
+        if !spkg_path.exists() {
+            panic!("The spkg path: {}, you gave here does not exist! Please specify a correct location for the spkg path!", self.spkg_path);
+        }
+
+        let config = self.config.parse();
+
+        let spkg_data = fs::read(spkg_path).unwrap();
+        let location_type = match self.location_type {
+            None => Location::Local,
+            Some(LocationType::Local) => Location::Local,
+            Some(LocationType::Dwh) => {
+                if self.bucket.is_none() {
+                    panic!("Bucket is mandatory if location type is DWH");
+                }
+                Location::DataWarehouse
+            },
+        };
+
+        let data_location_path = self.data_location_path.clone().map(|path| PathBuf::from(path));
+
+        let module_merged_files = match compact_output_folders(spkg_data, config.get_output_modules(), location_type, data_location_path, self.bucket.clone(), &config.storage, config.partition.clone(), config.get_chain_override(), &config.chains, &config.parquet, self.target_file_size, self.dry_run).await {
+            Ok(module_merged_files) => module_merged_files,
+            Err(error) => {
+                eprintln!("Compaction failed! {}", error);
+                std::process::exit(1);
+            }
+        };
+
+        for (module_name, merged_files) in module_merged_files.iter() {
+            let num_files = merged_files.iter().map(|(_, files)| files.len()).sum::<usize>();
+            if self.dry_run {
+                println!("Module: {} - {} files would be merged into {} files", module_name, num_files, merged_files.len());
+            } else {
+                println!("Module: {} - {} files merged into {} files", module_name, num_files, merged_files.len());
+            }
+        }
+    }
+}
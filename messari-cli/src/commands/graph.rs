@@ -0,0 +1,115 @@
+use clap::{Parser, ValueEnum};
+use prost::Message;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::file_modification::file_contents_modifier::{safely_modify_file_contents, File, FileContentsModification};
+use crate::file_modification::substreams_yaml::SubstreamsYaml;
+use crate::module_graph::ModuleGraph;
+use crate::streaming_fast::streamingfast_dtos::Package;
+use crate::terminal_interface::get_success_message;
+
+#[derive(Parser)]
+pub(crate) struct Graph {
+    #[arg(value_name = "Path", help="Path of a substreams.yaml (or it's folder) or a compiled spkg. Defaults to ./substreams.yaml")]
+    path: Option<String>,
+    #[arg(short, long, value_name = "Format", help="Defaults to mermaid.")]
+    format: Option<GraphFormat>,
+    #[arg(short, long, value_name = "Output module", help="Highlights the modules needed to run these output modules. Modules that none of them need are reported as unused.")]
+    output_modules: Vec<String>,
+    #[arg(long, help="Exits with a non-zero code if any module isn't needed by the output modules.")]
+    fail_on_unused: bool,
+    #[arg(long, value_name = "Readme path", help="Replaces the first mermaid code block of the readme with the graph instead of printing it.")]
+    readme: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, PartialEq)]
+pub(crate) enum GraphFormat {
+    Mermaid,
+    Dot,
+    Json,
+}
+
+impl Graph {
+    pub(crate) fn execute(&self) {
+        let path = PathBuf::from(self.path.as_deref().unwrap_or("substreams.yaml"));
+        let graph = if path.extension().map(|extension| extension == "spkg").unwrap_or(false) {
+            let spkg_data = fs::read(&path).unwrap_or_else(|error| panic!("Unable to read spkg: {}! Error: {}", path.display(), error));
+            ModuleGraph::from_package(&Package::decode(spkg_data.as_slice()).unwrap_or_else(|error| panic!("Unable to decode spkg: {}! Error: {}", path.display(), error)))
+        } else {
+            let substreams_yaml_filepath = if path.is_dir() { path.join("substreams.yaml") } else { path };
+            if !substreams_yaml_filepath.exists() {
+                panic!("The substreams.yaml path: {}, you gave here does not exist! Please specify a correct location for the substreams.yaml or spkg!", substreams_yaml_filepath.display());
+            }
+            ModuleGraph::from_substreams_yaml(&SubstreamsYaml::load_from_file(&substreams_yaml_filepath))
+        };
+
+        let mut highlighted_modules = BTreeSet::new();
+        for output_module in self.output_modules.iter() {
+            highlighted_modules.extend(graph.get_required_modules(output_module).unwrap_or_else(|error| panic!("{}", error)));
+        }
+        let unused_modules = if self.output_modules.is_empty() {
+            Vec::new()
+        } else {
+            graph.get_unused_modules(&self.output_modules).unwrap_or_else(|error| panic!("{}", error))
+        };
+
+        let format = self.format.clone().unwrap_or(GraphFormat::Mermaid);
+        let graph_output = match format {
+            GraphFormat::Mermaid => graph.to_mermaid(&highlighted_modules),
+            GraphFormat::Dot => graph.to_dot(&highlighted_modules),
+            GraphFormat::Json => graph.to_json(&highlighted_modules, &unused_modules),
+        };
+
+        match &self.readme {
+            Some(readme_path) => {
+                if format != GraphFormat::Mermaid {
+                    panic!("Only mermaid graphs can be written to a readme!");
+                }
+                let readme_filepath = PathBuf::from(readme_path);
+                let readme_contents = fs::read_to_string(&readme_filepath).unwrap_or_else(|error| panic!("Unable to read readme: {}! Error: {}", readme_path, error));
+                let file_contents = replace_mermaid_block(&readme_contents, &graph_output)
+                    .unwrap_or_else(|| panic!("Readme: {} has no mermaid code block to replace!", readme_path));
+
+                safely_modify_file_contents(vec![FileContentsModification::UpdateFile(File {
+                    filepath: readme_filepath,
+                    file_contents,
+                })]);
+                println!("{}", get_success_message(format!("Updated the module graph in: {}", readme_path)));
+            },
+            None => println!("{}", graph_output),
+        }
+
+        // Printed to stderr so that the graph itself can still be piped into a file
+        for unused_module in unused_modules.iter() {
+            eprintln!("Unused module: {} isn't needed by any of the output modules", unused_module);
+        }
+        if self.fail_on_unused && !unused_modules.is_empty() {
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Returns None if the readme doesn't have a mermaid code block
+fn replace_mermaid_block(readme_contents: &str, graph: &str) -> Option<String> {
+    let block_start = readme_contents.find("```mermaid\n")? + "```mermaid\n".len();
+    let block_end = block_start + readme_contents[block_start..].find("```")?;
+
+    Some(format!("{}{}\n{}", &readme_contents[..block_start], graph, &readme_contents[block_end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commands::graph::replace_mermaid_block;
+
+    #[test]
+    fn test_replace_mermaid_block() {
+        let readme_contents = "# Compound V2\n\n```mermaid\ngraph TD;\n  map_old[map: map_old]\n```\n\n```mermaid\ngraph TD;\n```\n";
+        assert_eq!(
+            replace_mermaid_block(readme_contents, "graph TD;\n  map_new[map: map_new]").unwrap(),
+            "# Compound V2\n\n```mermaid\ngraph TD;\n  map_new[map: map_new]\n```\n\n```mermaid\ngraph TD;\n```\n"
+        );
+        assert!(replace_mermaid_block("# Compound V2\n", "graph TD;").is_none());
+    }
+}
@@ -1,5 +1,12 @@
 pub(crate) mod add;
+pub(crate) mod audit;
 pub(crate) mod block_range_info;
+pub(crate) mod compact;
+pub(crate) mod graph;
 pub(crate) mod init;
 pub(crate) mod process;
+pub(crate) mod process_all;
+pub(crate) mod schema;
 pub(crate) mod upload_cli_to_aws;
+pub(crate) mod validate;
+pub(crate) mod versions;
@@ -2,8 +2,9 @@ use clap::{Parser, ValueEnum};
 use std::fs;
 use std::path::PathBuf;
 
-use crate::streaming_fast::process_substream::{EncodingType, process_substream};
+use crate::streaming_fast::process_substream::{EncodingType as Encoding, process_substream};
 use crate::streaming_fast::file::LocationType as Location;
+use crate::streaming_fast::file_sinks::csv::RepeatedFieldPolicy as RepeatedPolicy;
 use crate::streaming_fast::streaming_config::ConfigArg;
 
 #[derive(Parser)]
@@ -17,10 +18,16 @@ pub(crate) struct Process {
     data_location_path: Option<String>,
     #[arg(short, long, value_name = "Bucket", help="Mandatory if location type is DWH")]
     bucket: Option<String>,
+    #[arg(long, value_name = "Encoding Type", help="Defaults to parquet.")]
+    encoding: Option<EncodingType>,
+    #[arg(long, value_name = "Repeated Field Policy", help="How repeated fields get written for csv encoding. Defaults to a json array cell.")]
+    repeated_field_policy: Option<RepeatedFieldPolicy>,
     #[arg(short, long, value_name = "Start Block")]
     start_block: Option<i64>,
     #[arg(short, long, value_name = "Stop Block")]
     stop_block: Option<u64>,
+    #[arg(long, help="Stream non-final blocks as well. Rows are held back until their block is final so that they can be rolled back on chain reorgs.")]
+    live: bool,
 }
 
 #[derive(ValueEnum, Clone)]
@@ -29,6 +36,19 @@ pub(crate) enum LocationType {
     Dwh
 }
 
+#[derive(ValueEnum, Clone)]
+pub(crate) enum EncodingType {
+    Parquet,
+    Jsonl,
+    Csv
+}
+
+#[derive(ValueEnum, Clone)]
+pub(crate) enum RepeatedFieldPolicy {
+    JsonCell,
+    Explode
+}
+
 impl Process {
     pub(crate) async fn execute(&self) {
         let spkg_path = PathBuf::from(&self.spkg_path);
@@ -50,9 +70,23 @@ impl Process {
             },
         };
 
+        let encoding_type = match self.encoding {
+            None => Encoding::Parquet(config.parquet.clone()),
+            Some(EncodingType::Parquet) => Encoding::Parquet(config.parquet.clone()),
+            Some(EncodingType::Jsonl) => Encoding::JsonL(config.parquet.file_size_threshold),
+            Some(EncodingType::Csv) => Encoding::Csv(match self.repeated_field_policy {
+                None => RepeatedPolicy::JsonCell,
+                Some(RepeatedFieldPolicy::JsonCell) => RepeatedPolicy::JsonCell,
+                Some(RepeatedFieldPolicy::Explode) => RepeatedPolicy::Explode,
+            }, config.parquet.file_size_threshold),
+        };
+
         let data_location_path = self.data_location_path.clone().map(|path| PathBuf::from(path));
 
-        process_substream(spkg_data, config, EncodingType::Parquet, location_type, data_location_path, self.bucket.clone(), self.start_block, self.stop_block).await;
+        if let Err(error) = process_substream(spkg_data, config, encoding_type, location_type, data_location_path, self.bucket.clone(), self.start_block, self.stop_block, self.live).await {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
 
         println!("Processing complete!!!");
     }
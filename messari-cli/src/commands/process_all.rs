@@ -0,0 +1,159 @@
+use clap::Parser;
+use futures::StreamExt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::commands::process::{EncodingType, LocationType, RepeatedFieldPolicy};
+use crate::streaming_fast::deployment_params::read_params_file;
+use crate::streaming_fast::file::LocationType as Location;
+use crate::streaming_fast::file_sinks::csv::RepeatedFieldPolicy as RepeatedPolicy;
+use crate::streaming_fast::process_substream::{EncodingType as Encoding, process_substream};
+use crate::streaming_fast::streaming_config::{StreamingConfig, ToJsonL};
+
+#[derive(Parser)]
+pub(crate) struct ProcessAll {
+    #[arg(long, value_name = "Params path", help="Defaults to config/params.json.")]
+    params_path: Option<String>,
+    #[arg(long, value_name = "Spkg folder", help="Folder holding the spkgs, named {package name}-vX.Y.Z.spkg or {folder name}.spkg. Defaults to each substream's own folder.")]
+    spkg_folder: Option<String>,
+    #[arg(long, value_name = "Deployment", help="Only process these deployments. Defaults to processing every deployment.")]
+    deployments: Vec<String>,
+    #[arg(long, value_name = "Parallelism", default_value_t = 2, help="Number of deployments processed at once.")]
+    parallelism: usize,
+    #[arg(short, long, value_name = "Location Type", help="Defaults to saving to local filepath.")]
+    location_type: Option<LocationType>,
+    #[arg(short, long, value_name = "Data location path", help="If not specified it will default to substreams on aws and ./data/ on local.")]
+    data_location_path: Option<String>,
+    #[arg(short, long, value_name = "Bucket", help="Mandatory if location type is DWH")]
+    bucket: Option<String>,
+    #[arg(long, value_name = "Encoding Type", help="Defaults to parquet.")]
+    encoding: Option<EncodingType>,
+    #[arg(long, value_name = "Repeated Field Policy", help="How repeated fields get written for csv encoding. Defaults to a json array cell.")]
+    repeated_field_policy: Option<RepeatedFieldPolicy>,
+    #[arg(long, value_name = "Stop Block")]
+    stop_block: Option<u64>,
+    #[arg(long, help="Prints the config built for each deployment as json lines instead of processing them.")]
+    print_configs: bool,
+}
+
+enum DeploymentStatus {
+    Succeeded(Duration),
+    Failed(String),
+}
+
+impl ProcessAll {
+    pub(crate) async fn execute(&self) {
+        let params_path = PathBuf::from(self.params_path.as_deref().unwrap_or("config/params.json"));
+        let substreams = read_params_file(&params_path).unwrap_or_else(|error| panic!("{}", error));
+        let params_folder = params_path.parent().unwrap().to_path_buf();
+
+        for deployment_name in self.deployments.iter() {
+            if !substreams.iter().any(|substream| substream.deployments.iter().any(|deployment| &deployment.name == deployment_name)) {
+                panic!("Deployment: {} is not listed in params file: {}!", deployment_name, params_path.display());
+            }
+        }
+
+        // Deployments that can't be set up still get reported on at the end rather than stopping the whole batch
+        let mut deployment_runs = Vec::new();
+        for substream in substreams.iter() {
+            let substream_folder = params_folder.join(&substream.path);
+            let spkg_folder = match &self.spkg_folder {
+                Some(spkg_folder) => PathBuf::from(spkg_folder),
+                None => substream_folder.clone(),
+            };
+
+            for deployment in substream.deployments.iter() {
+                if !self.deployments.is_empty() && !self.deployments.contains(&deployment.name) {
+                    continue;
+                }
+
+                let deployment_setup = substream.find_spkg(&substream_folder, &spkg_folder).and_then(|spkg_path| {
+                    let config = deployment.get_streaming_config(&substream.output_modules)?;
+                    Ok((spkg_path, config))
+                });
+                deployment_runs.push((deployment.name.clone(), deployment_setup));
+            }
+        }
+
+        if self.print_configs {
+            let configs = deployment_runs.into_iter().filter_map(|(deployment_name, deployment_setup)| match deployment_setup {
+                Ok((_, config)) => Some(config),
+                Err(error) => {
+                    eprintln!("Skipping deployment: {}! {}", deployment_name, error);
+                    None
+                }
+            }).collect::<Vec<_>>();
+            println!("{}", configs.to_jsonl());
+            return;
+        }
+
+        let location_type = match self.location_type {
+            None => Location::Local,
+            Some(LocationType::Local) => Location::Local,
+            Some(LocationType::Dwh) => {
+                if self.bucket.is_none() {
+                    panic!("Bucket is mandatory if location type is DWH");
+                }
+                Location::DataWarehouse
+            },
+        };
+
+        let deployment_statuses = futures::stream::iter(deployment_runs.into_iter())
+            .map(|(deployment_name, deployment_setup)| {
+                let location_type = location_type.clone();
+                async move {
+                    let deployment_status = match deployment_setup {
+                        Ok((spkg_path, config)) => self.process_deployment(spkg_path, config, location_type).await,
+                        Err(error) => DeploymentStatus::Failed(error),
+                    };
+                    (deployment_name, deployment_status)
+                }
+            })
+            .buffer_unordered(self.parallelism.max(1))
+            .collect::<Vec<_>>().await;
+
+        let mut num_failed = 0;
+        println!("Deployment status report:");
+        for (deployment_name, deployment_status) in deployment_statuses.iter() {
+            match deployment_status {
+                DeploymentStatus::Succeeded(duration) => println!("  {}: Succeeded in {}s", deployment_name, duration.as_secs()),
+                DeploymentStatus::Failed(error) => {
+                    num_failed += 1;
+                    println!("  {}: Failed\n    {}", deployment_name, error.trim_end().replace('\n', "\n    "));
+                }
+            }
+        }
+        println!("{}/{} deployments succeeded", deployment_statuses.len() - num_failed, deployment_statuses.len());
+
+        if num_failed > 0 {
+            std::process::exit(1);
+        }
+    }
+
+    async fn process_deployment(&self, spkg_path: PathBuf, config: StreamingConfig, location_type: Location) -> DeploymentStatus {
+        let spkg_data = match fs::read(&spkg_path) {
+            Ok(spkg_data) => spkg_data,
+            Err(error) => return DeploymentStatus::Failed(format!("Unable to read spkg: {}! Error: {}", spkg_path.display(), error)),
+        };
+
+        let encoding_type = match self.encoding {
+            None => Encoding::Parquet(config.parquet.clone()),
+            Some(EncodingType::Parquet) => Encoding::Parquet(config.parquet.clone()),
+            Some(EncodingType::Jsonl) => Encoding::JsonL(config.parquet.file_size_threshold),
+            Some(EncodingType::Csv) => Encoding::Csv(match self.repeated_field_policy {
+                None => RepeatedPolicy::JsonCell,
+                Some(RepeatedFieldPolicy::JsonCell) => RepeatedPolicy::JsonCell,
+                Some(RepeatedFieldPolicy::Explode) => RepeatedPolicy::Explode,
+            }, config.parquet.file_size_threshold),
+        };
+
+        let data_location_path = self.data_location_path.clone().map(|path| PathBuf::from(path));
+
+        let start_time = Instant::now();
+        match process_substream(spkg_data, config, encoding_type, location_type, data_location_path, self.bucket.clone(), None, self.stop_block, false).await {
+            Ok(()) => DeploymentStatus::Succeeded(start_time.elapsed()),
+            Err(error) => DeploymentStatus::Failed(error.to_string()),
+        }
+    }
+}
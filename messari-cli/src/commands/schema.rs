@@ -0,0 +1,91 @@
+use clap::Parser;
+use prost::Message;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::streaming_fast::file::LocationType;
+use crate::streaming_fast::output_schema::{diff_entity_schemas, EntitySchema, get_entity_schemas};
+use crate::streaming_fast::process_substream::{get_chain_info, get_sink_output_path_and_type_info};
+use crate::streaming_fast::streaming_config::StreamingConfig;
+use crate::streaming_fast::streamingfast_dtos::Package;
+
+#[derive(Parser)]
+pub(crate) struct Schema {
+    spkg_path: String,
+    #[arg(short, long, value_name = "Module", help="Module to print the schema of. Defaults to the output modules of the config.")]
+    module: Option<String>,
+    #[arg(short, long, value_name = "Config", help="Config used for the parquet settings, block metadata columns and chain of the output.")]
+    config: Option<String>,
+    #[arg(short, long, value_name = "Data location path", help="Defaults to ./data/")]
+    data_location_path: Option<String>,
+    #[arg(long, value_name = "Old spkg path", help="Diffs the schema against the schema of another version of the spkg. Exits with a non-zero code if there are any breaking changes.")]
+    diff: Option<String>,
+}
+
+impl Schema {
+    pub(crate) fn execute(&self) {
+        let config = self.config.as_ref().map(|config| StreamingConfig::from_json(config).unwrap_or_else(|error| panic!("Invalid config! {}", error)));
+
+        let module_names = match (&self.module, &config) {
+            (Some(module_name), _) => vec![module_name.clone()],
+            (None, Some(config)) => config.get_output_modules(),
+            (None, None) => panic!("Either a module or a config with output modules needs to be specified!"),
+        };
+
+        let entity_schemas = self.get_entity_schemas(&self.spkg_path, &module_names, config.as_ref());
+        for (module_name, module_entity_schemas) in module_names.iter().zip(entity_schemas.iter()) {
+            println!("Module: {}", module_name);
+            for entity_schema in module_entity_schemas.iter() {
+                println!("  Entity: {}\n  Output folder: {}", entity_schema.entity_name, entity_schema.output_folder.display());
+                let mut schema_output = Vec::new();
+                parquet::schema::printer::print_schema(&mut schema_output, &entity_schema.parquet_schema);
+                println!("  {}", String::from_utf8(schema_output).unwrap().trim_end().replace('\n', "\n  "));
+            }
+        }
+
+        let old_spkg_path = if let Some(old_spkg_path) = &self.diff {
+            old_spkg_path
+        } else {
+            return;
+        };
+
+        let old_entity_schemas = self.get_entity_schemas(old_spkg_path, &module_names, config.as_ref());
+        let mut has_breaking_changes = false;
+        for ((module_name, old_module_schemas), new_module_schemas) in module_names.iter().zip(old_entity_schemas.iter()).zip(entity_schemas.iter()) {
+            let schema_changes = diff_entity_schemas(old_module_schemas, new_module_schemas);
+            if schema_changes.is_empty() {
+                println!("No schema changes for module: {} since: {}", module_name, old_spkg_path);
+                continue;
+            }
+
+            println!("Schema changes for module: {} since: {}", module_name, old_spkg_path);
+            for schema_change in schema_changes.iter() {
+                has_breaking_changes |= schema_change.is_breaking();
+                println!("  {}", schema_change);
+            }
+        }
+
+        if has_breaking_changes {
+            std::process::exit(1);
+        }
+    }
+
+    /// Returns the entity schemas of each of the modules (in the same order as module_names)
+    fn get_entity_schemas(&self, spkg_path: &str, module_names: &[String], config: Option<&StreamingConfig>) -> Vec<Vec<EntitySchema>> {
+        let spkg_data = fs::read(spkg_path).unwrap_or_else(|error| panic!("Unable to read spkg: {}! Error: {}", spkg_path, error));
+        let package = Package::decode(spkg_data.as_slice()).unwrap();
+
+        let (parquet_config, block_metadata_columns, chains) = match config {
+            Some(config) => (config.parquet.clone(), config.block_metadata_columns, config.chains.clone()),
+            None => Default::default(),
+        };
+        let chain = config.and_then(|config| config.get_chain_override()).unwrap_or_else(|| get_chain_info(&package, &chains).unwrap_or_else(|error| panic!("{}", error)));
+        let data_location_path = self.data_location_path.clone().map(|path| PathBuf::from(path));
+
+        module_names.iter().map(|module_name| {
+            let (sink_output_path, output_type_info, _) = get_sink_output_path_and_type_info(&package, module_name, LocationType::Local, data_location_path.clone(), &chain)
+                .unwrap_or_else(|error| panic!("{}", error));
+            get_entity_schemas(output_type_info, sink_output_path, &parquet_config, block_metadata_columns)
+        }).collect()
+    }
+}
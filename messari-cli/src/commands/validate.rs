@@ -0,0 +1,232 @@
+use clap::Parser;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::file_modification::substreams_yaml::SubstreamsYaml;
+use crate::json_schema::validate_json_schema;
+use crate::streaming_fast::deployment_params::SubstreamParams;
+use crate::streaming_fast::streaming_config::Chain;
+use crate::terminal_interface::{get_error_message, get_success_message};
+use crate::utils::get_repo_root_folder;
+
+const PARAMS_SCHEMA: &str = include_str!("../../../config/schemas/params.schema.json");
+const SUBGRAPHS_SCHEMA: &str = include_str!("../../../config/schemas/subgraphs.schema.json");
+
+#[derive(Parser)]
+pub(crate) struct Validate {
+    #[arg(long, value_name = "Params path", help="Defaults to config/params.json at the root of the repo.")]
+    params_path: Option<String>,
+    #[arg(long, value_name = "Subgraphs path", help="Defaults to config/subgraphs.json at the root of the repo.")]
+    subgraphs_path: Option<String>,
+}
+
+impl Validate {
+    pub(crate) fn execute(&self) {
+        let config_folder = get_repo_root_folder().join("config");
+        let params_path = self.params_path.as_ref().map(PathBuf::from).unwrap_or_else(|| config_folder.join("params.json"));
+        let subgraphs_path = self.subgraphs_path.as_ref().map(PathBuf::from).unwrap_or_else(|| config_folder.join("subgraphs.json"));
+
+        let mut errors = Vec::new();
+
+        let params = read_json_file(&params_path);
+        let params_schema_errors = validate_json_schema(&serde_json::from_str(PARAMS_SCHEMA).unwrap(), &params)
+            .unwrap_or_else(|error| panic!("Invalid params schema! {}", error));
+        errors.extend(params_schema_errors.iter().map(|error| format!("{}: {}", params_path.display(), error)));
+
+        // The cross references can only be checked once the params file has the expected structure
+        let substreams = if params_schema_errors.is_empty() {
+            match serde_json::from_value::<Vec<SubstreamParams>>(params) {
+                Ok(substreams) => {
+                    errors.extend(get_params_errors(&substreams, params_path.parent().unwrap()).into_iter().map(|error| format!("{}: {}", params_path.display(), error)));
+                    Some(substreams)
+                },
+                Err(error) => {
+                    errors.push(format!("{}: {}", params_path.display(), error));
+                    None
+                },
+            }
+        } else {
+            None
+        };
+
+        let subgraphs = read_json_file(&subgraphs_path);
+        let subgraphs_schema_errors = validate_json_schema(&serde_json::from_str(SUBGRAPHS_SCHEMA).unwrap(), &subgraphs)
+            .unwrap_or_else(|error| panic!("Invalid subgraphs schema! {}", error));
+        errors.extend(subgraphs_schema_errors.iter().map(|error| format!("{}: {}", subgraphs_path.display(), error)));
+        if let (true, Some(substreams)) = (subgraphs_schema_errors.is_empty(), substreams) {
+            errors.extend(get_subgraphs_errors(&subgraphs, &substreams).into_iter().map(|error| format!("{}: {}", subgraphs_path.display(), error)));
+        }
+
+        if !errors.is_empty() {
+            for error in errors.iter() {
+                eprintln!("{}", get_error_message(error));
+            }
+            std::process::exit(1);
+        }
+
+        println!("{}", get_success_message(format!("{} and {} are valid", params_path.display(), subgraphs_path.display())));
+    }
+}
+
+fn read_json_file(filepath: &Path) -> Value {
+    let file_contents = fs::read_to_string(filepath).unwrap_or_else(|error| panic!("Unable to read file: {}! Error: {}", filepath.display(), error));
+    serde_json::from_str(&file_contents).unwrap_or_else(|error| panic!("Unable to parse json file: {}! Error: {}", filepath.display(), error))
+}
+
+/// Checks the modules referenced by each substream against it's substreams.yaml, and the network of each deployment.
+/// Modules from imported spkgs (prefix:module_name) aren't in the substreams.yaml so they can't be checked here
+fn get_params_errors(substreams: &[SubstreamParams], params_folder: &Path) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    for substream in substreams.iter() {
+        let substreams_yaml_filepath = params_folder.join(&substream.path).join("substreams.yaml");
+        if !substreams_yaml_filepath.exists() {
+            errors.push(format!("Substream: {} has no substreams.yaml at: {}", substream.name, substreams_yaml_filepath.display()));
+            continue;
+        }
+        let substreams_yaml = SubstreamsYaml::load_from_file(&substreams_yaml_filepath);
+        let get_module = |module_name: &str| {
+            if module_name.contains(':') {
+                Ok(None)
+            } else {
+                substreams_yaml.get_module_kind_and_inputs(module_name).map(Some)
+                    .ok_or_else(|| format!("module: {} doesn't exist in: {}", module_name, substreams_yaml_filepath.display()))
+            }
+        };
+
+        for module_name in substream.output_modules.iter().chain(substream.subgraph_module.iter()) {
+            match get_module(module_name) {
+                Ok(Some((kind, _))) if kind != "map" => errors.push(format!("Substream: {} - output module: {} is a {} module! Only map modules can be output", substream.name, module_name, kind)),
+                Ok(_) => {},
+                Err(error) => errors.push(format!("Substream: {} - output {}", substream.name, error)),
+            }
+        }
+
+        for deployment in substream.deployments.iter() {
+            if Chain::from_name(&deployment.network, &[]).is_none() {
+                errors.push(format!("Deployment: {} - network: {} is not one of the built in chains", deployment.name, deployment.network));
+            }
+
+            for module_name in deployment.start_blocks.keys() {
+                if let Err(error) = get_module(module_name) {
+                    errors.push(format!("Deployment: {} - start block given for {}", deployment.name, error));
+                }
+            }
+
+            for module_name in deployment.params.keys() {
+                match get_module(module_name) {
+                    Ok(Some((_, inputs))) if !inputs.iter().any(|(input_kind, _)| input_kind == "params") => {
+                        errors.push(format!("Deployment: {} - params given for module: {} which has no params input", deployment.name, module_name));
+                    },
+                    Ok(_) => {},
+                    Err(error) => errors.push(format!("Deployment: {} - params given for {}", deployment.name, error)),
+                }
+            }
+        }
+    }
+
+    errors
+}
+
+/// Subgraphs need to be keyed as {substream_name}/{deployment_name} for an existing substream and deployment
+fn get_subgraphs_errors(subgraphs: &Value, substreams: &[SubstreamParams]) -> Vec<String> {
+    let deployment_keys = substreams.iter().flat_map(|substream| {
+        substream.deployments.iter().map(move |deployment| format!("{}/{}", substream.name, deployment.name))
+    }).collect::<BTreeSet<_>>();
+
+    subgraphs.as_object().map(|subgraphs| subgraphs.keys().filter(|subgraph_key| !deployment_keys.contains(*subgraph_key)).map(|subgraph_key| {
+        format!("Subgraph: {} doesn't match any deployment. Subgraphs need to be keyed as {{substream_name}}/{{deployment_name}}", subgraph_key)
+    }).collect()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::commands::validate::{get_params_errors, get_subgraphs_errors, PARAMS_SCHEMA};
+    use crate::file_modification::substreams_yaml::SubstreamsYaml;
+    use crate::json_schema::validate_json_schema;
+    use crate::streaming_fast::deployment_params::SubstreamParams;
+    use crate::test_utils::get_temp_dir;
+
+    #[test]
+    fn test_params_and_subgraphs_cross_references() {
+        let temp_dir = get_temp_dir();
+        let config_folder = temp_dir.path();
+        let substream_folder = config_folder.join("test-substream");
+        fs::create_dir_all(&substream_folder).unwrap();
+        fs::write(substream_folder.join("substreams.yaml"), r#"
+specVersion: v0.1.0
+package:
+  name: test_substream
+  version: v0.1.0
+
+modules:
+  - name: store_observed_contracts
+    kind: store
+    updatePolicy: set
+    valueType: string
+    inputs:
+      - params: string
+      - source: sf.ethereum.type.v2.Block
+  - name: map_output
+    kind: map
+    inputs:
+      - store: store_observed_contracts
+        mode: deltas
+    output:
+      type: proto:messari.test.v1.Output
+"#).unwrap();
+
+        let substreams: Vec<SubstreamParams> = serde_json::from_value(serde_json::json!([
+            {
+                "name": "test-substream",
+                "path": "test-substream",
+                "outputModules": ["map_output", "store_observed_contracts"],
+                "subgraphModule": "map_entity_changes",
+                "deployments": [
+                    {
+                        "name": "test-substream-ethereum",
+                        "network": "mainnet",
+                        "params": { "store_observed_contracts": "0x7d27", "map_output": "0x311b" },
+                        "startBlocks": { "store_observed_contracts": 100, "map_output": 100, "eth:map_block": 100 }
+                    },
+                    {
+                        "name": "test-substream-unknown",
+                        "network": "unknown",
+                        "startBlocks": { "map_missing": 100 }
+                    }
+                ]
+            }
+        ])).unwrap();
+
+        // The mode of a store input sits alongside the input kind and shouldn't be mistaken for it
+        let substreams_yaml_filepath = substream_folder.join("substreams.yaml");
+        let substreams_yaml = SubstreamsYaml::load_from_file(&substreams_yaml_filepath);
+        assert_eq!(substreams_yaml.get_module_kind_and_inputs("map_output"), Some(("map".to_string(), vec![("store".to_string(), "store_observed_contracts".to_string())])));
+
+        let errors = get_params_errors(&substreams, config_folder);
+        assert_eq!(errors, vec![
+            "Substream: test-substream - output module: store_observed_contracts is a store module! Only map modules can be output".to_string(),
+            format!("Substream: test-substream - output module: map_entity_changes doesn't exist in: {}", substreams_yaml_filepath.display()),
+            "Deployment: test-substream-ethereum - params given for module: map_output which has no params input".to_string(),
+            "Deployment: test-substream-unknown - network: unknown is not one of the built in chains".to_string(),
+            format!("Deployment: test-substream-unknown - start block given for module: map_missing doesn't exist in: {}", substreams_yaml_filepath.display()),
+        ]);
+
+        let subgraphs = serde_json::json!({
+            "test-substream/test-substream-ethereum": { "services": {} },
+            "test-substream-ethereum": { "services": {} },
+        });
+        assert_eq!(get_subgraphs_errors(&subgraphs, &substreams), vec![
+            "Subgraph: test-substream-ethereum doesn't match any deployment. Subgraphs need to be keyed as {substream_name}/{deployment_name}".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_params_schema_is_valid() {
+        assert_eq!(validate_json_schema(&serde_json::from_str(PARAMS_SCHEMA).unwrap(), &serde_json::json!([])), Ok(vec![]));
+    }
+}
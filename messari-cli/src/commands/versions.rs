@@ -0,0 +1,328 @@
+use clap::{Parser, ValueEnum};
+use regex::Regex;
+use semver::Version;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::mem;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use strum_macros::{EnumIter, EnumVariantNames};
+
+use crate::file_modification::cargo_toml::CargoToml;
+use crate::file_modification::file_contents_modifier::{
+    safely_modify_file_contents, File, FileContentsModification,
+};
+use crate::file_modification::substreams_yaml::{SubstreamsYaml, VersionType};
+use crate::terminal_interface::{get_error_message, get_success_message, select_from_enum};
+use crate::utils::get_repo_root_folder;
+
+const DEFAULT_BASE_REF: &str = "origin/master";
+
+#[derive(Parser)]
+pub(crate) struct Versions {
+    pub(crate) versions_operation_type: Option<VersionsOperationType>,
+    #[arg(
+        short,
+        long,
+        value_name = "Base Ref",
+        help = "Git ref the changes are compared against. Defaults to origin/master."
+    )]
+    pub(crate) base_ref: Option<String>,
+    #[arg(
+        short,
+        long,
+        value_name = "Version Type",
+        help = "Part of the version that gets incremented by the bump operation. Defaults to patch."
+    )]
+    pub(crate) version_type: Option<VersionType>,
+}
+
+#[derive(ValueEnum, EnumIter, EnumVariantNames, Clone)]
+pub(crate) enum VersionsOperationType {
+    /// Fails if any of the substreams affected by the changes hasn't had it's version bumped
+    Check,
+    /// Bumps the version of all of the affected substreams that haven't been bumped yet
+    Bump,
+    /// Prints the affected substreams, one per line
+    List,
+}
+
+/// A top level folder of the repo that is either a substream or a crate
+struct Project {
+    is_substream: bool,
+    /// Other projects this project depends on, through local spkg imports or Cargo.toml path dependencies
+    dependencies: BTreeSet<String>,
+}
+
+impl Versions {
+    pub(crate) fn execute(&mut self) {
+        let operation_type = if let Some(operation_type) = mem::take(&mut self.versions_operation_type) {
+            operation_type
+        } else {
+            select_from_enum("Operation Type", Some(0))
+        };
+
+        let repo_root = get_repo_root_folder();
+        let base_ref = self.base_ref.clone().unwrap_or(DEFAULT_BASE_REF.to_string());
+
+        // Comparing against the merge base so that changes made to the base ref since branching off don't count
+        let merge_base = run_git_command(&repo_root, &["merge-base", &base_ref, "HEAD"])
+            .unwrap_or_else(|error| panic!("Unable to find the merge base with: {}! Error: {}", base_ref, error))
+            .trim()
+            .to_string();
+        let changed_files = run_git_command(&repo_root, &["diff", "--name-only", &merge_base])
+            .unwrap_or_else(|error| panic!("Unable to get the files changed since: {}! Error: {}", base_ref, error))
+            .lines()
+            .map(|changed_file| PathBuf::from(changed_file))
+            .collect::<Vec<_>>();
+
+        let affected_substreams = get_affected_substreams(&repo_root, &changed_files);
+
+        match operation_type {
+            VersionsOperationType::List => {
+                for substream in affected_substreams.iter() {
+                    println!("{}", substream);
+                }
+            }
+            VersionsOperationType::Check => {
+                let unbumped_substreams = affected_substreams
+                    .iter()
+                    .filter(|substream| !is_version_bumped(&repo_root, &merge_base, substream).unwrap_or_else(|error| panic!("{}", error)))
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                if !unbumped_substreams.is_empty() {
+                    eprintln!(
+                        "{}",
+                        get_error_message(format!(
+                            "The following substreams have not had their version bumped since: {}\n{}\nRun: messari versions bump",
+                            base_ref,
+                            unbumped_substreams.join(", ")
+                        ))
+                    );
+                    std::process::exit(1);
+                }
+
+                println!("{}", get_success_message(format!("All {} affected substreams have been bumped", affected_substreams.len())));
+            }
+            VersionsOperationType::Bump => {
+                let version_type = self.version_type.clone().unwrap_or(VersionType::Patch);
+
+                let mut operations = Vec::new();
+                for substream in affected_substreams.iter() {
+                    if is_version_bumped(&repo_root, &merge_base, substream).unwrap_or_else(|error| panic!("{}", error)) {
+                        println!("Skipping substream: {} as it's version has already been bumped", substream);
+                        continue;
+                    }
+
+                    let substreams_yaml_filepath = repo_root.join(substream).join("substreams.yaml");
+                    let mut substreams_yaml = SubstreamsYaml::load_from_file(&substreams_yaml_filepath);
+                    let old_version = substreams_yaml.get_version();
+                    substreams_yaml.modify_version(version_type.clone(), None);
+                    let new_version = substreams_yaml.get_version();
+
+                    let file_contents = fs::read_to_string(&substreams_yaml_filepath).unwrap();
+                    let file_contents = get_bumped_file_contents(&file_contents, &old_version, &new_version)
+                        .unwrap_or_else(|| substreams_yaml.get_file_contents());
+
+                    println!("Bumping substream: {} from: {} to: {}", substream, old_version, new_version);
+                    operations.push(FileContentsModification::UpdateFile(File {
+                        filepath: substreams_yaml_filepath,
+                        file_contents,
+                    }));
+                }
+
+                safely_modify_file_contents(operations);
+            }
+        }
+    }
+}
+
+fn run_git_command(repo_root: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo_root)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|error| error.to_string())?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Substreams that don't exist at the base ref count as bumped as they don't have a version to bump from
+fn is_version_bumped(repo_root: &Path, merge_base: &str, substream: &str) -> Result<bool, String> {
+    let base_file_contents = match run_git_command(repo_root, &["show", &format!("{}:{}/substreams.yaml", merge_base, substream)]) {
+        Ok(base_file_contents) => base_file_contents,
+        Err(_) => return Ok(true),
+    };
+
+    let base_version = SubstreamsYaml::from(base_file_contents.as_str()).get_version_string();
+    let version = SubstreamsYaml::load_from_file(&repo_root.join(substream).join("substreams.yaml")).get_version_string();
+
+    is_newer_version(&version, &base_version).map_err(|error| format!("Unable to compare the versions of substream: {}! {}", substream, error))
+}
+
+/// Versions are compared by semver precedence so pre-releases (eg. v0.2.0-rc1) come before the release they lead up to
+fn is_newer_version(version: &str, base_version: &str) -> Result<bool, String> {
+    let parse_version = |version: &str| Version::parse(version.trim_start_matches('v')).map_err(|error| format!("Version: {} is not a valid semver version! Error: {}", version, error));
+    Ok(parse_version(version)? > parse_version(base_version)?)
+}
+
+/// Only the version line gets replaced so that the formatting and comments of the substreams.yaml are kept. Returns
+/// None if the version line can't be found
+fn get_bumped_file_contents(file_contents: &str, old_version: &str, new_version: &str) -> Option<String> {
+    let version_regex = Regex::new(&format!(r#"(?m)^([ \t]+version:[ \t]*["']?v?){}(["']?[ \t]*)$"#, regex::escape(old_version))).unwrap();
+    if !version_regex.is_match(file_contents) {
+        return None;
+    }
+
+    Some(version_regex.replace(file_contents, format!("${{1}}{}${{2}}", new_version)).to_string())
+}
+
+/// Returns the substreams (by folder name) affected by the changed files (relative to the repo root), either through
+/// changes to the substream itself or to any of the spkgs or crates it depends on (directly or transitively)
+fn get_affected_substreams(repo_root: &Path, changed_files: &[PathBuf]) -> BTreeSet<String> {
+    let projects = get_projects(repo_root);
+
+    let mut affected_projects = changed_files
+        .iter()
+        .filter_map(|changed_file| changed_file.components().next())
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .filter(|project_name| projects.contains_key(project_name))
+        .collect::<BTreeSet<_>>();
+
+    let mut projects_to_check = affected_projects.iter().cloned().collect::<Vec<_>>();
+    while let Some(affected_project) = projects_to_check.pop() {
+        for (project_name, project) in projects.iter() {
+            if project.dependencies.contains(&affected_project) && affected_projects.insert(project_name.clone()) {
+                projects_to_check.push(project_name.clone());
+            }
+        }
+    }
+
+    affected_projects.into_iter().filter(|project_name| projects[project_name].is_substream).collect()
+}
+
+fn get_projects(repo_root: &Path) -> BTreeMap<String, Project> {
+    let mut projects = BTreeMap::new();
+    for entry in fs::read_dir(repo_root).expect("Unable to read the repo root folder!") {
+        let project_dir = entry.unwrap().path();
+        let project_name = project_dir.file_name().unwrap().to_string_lossy().to_string();
+        if !project_dir.is_dir() || project_name.starts_with('.') {
+            continue;
+        }
+
+        let substreams_yaml_filepath = project_dir.join("substreams.yaml");
+        let cargo_toml_filepath = project_dir.join("Cargo.toml");
+
+        let mut dependency_paths = Vec::new();
+        if substreams_yaml_filepath.exists() {
+            dependency_paths.extend(SubstreamsYaml::load_from_file(&substreams_yaml_filepath).get_local_spkg_imports());
+        }
+        if cargo_toml_filepath.exists() {
+            dependency_paths.extend(CargoToml::load_from_file(&cargo_toml_filepath).get_local_dependency_paths());
+        }
+        if !substreams_yaml_filepath.exists() && !cargo_toml_filepath.exists() {
+            continue;
+        }
+
+        let dependencies = dependency_paths
+            .iter()
+            .filter_map(|dependency_path| get_project_name(repo_root, dependency_path))
+            .filter(|dependency_name| dependency_name != &project_name)
+            .collect();
+
+        projects.insert(project_name, Project {
+            is_substream: substreams_yaml_filepath.exists(),
+            dependencies,
+        });
+    }
+
+    projects
+}
+
+/// Returns the top level folder of the repo the path falls under (None if it's outside of the repo)
+fn get_project_name(repo_root: &Path, path: &Path) -> Option<String> {
+    let mut normalized_path = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized_path.pop();
+            }
+            Component::CurDir => {}
+            component => normalized_path.push(component),
+        }
+    }
+
+    normalized_path
+        .strip_prefix(repo_root)
+        .ok()?
+        .components()
+        .next()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::commands::versions::{get_affected_substreams, get_bumped_file_contents, is_newer_version};
+    use crate::test_utils::get_temp_dir;
+
+    #[test]
+    fn test_affected_substreams() {
+        let temp_dir = get_temp_dir();
+        let repo_root = temp_dir.path();
+
+        let substreams_yaml = |name: &str, imports: &str| format!("specVersion: v0.1.0\npackage:\n  name: {}\n  version: v0.1.0\n\nimports:\n  eth: https://github.com/streamingfast/sf-ethereum/releases/download/v0.10.2/ethereum-v0.10.4.spkg\n{}", name, imports);
+        let cargo_toml = |name: &str, dependencies: &str| format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n[dependencies]\n{}", name, dependencies);
+
+        // erc20-price imports the spkg of erc20-holdings which depends on the helper crate through it's Cargo.toml
+        let projects = [
+            ("helper", None, cargo_toml("helper", "")),
+            ("erc20-holdings", Some(substreams_yaml("erc20_holdings", "")), cargo_toml("erc20-holdings", "helper = { path = \"../helper\" }\n")),
+            ("erc20-price", Some(substreams_yaml("erc20_price", "  holdings: ../erc20-holdings/erc20-holdings-v0.1.0.spkg\n")), cargo_toml("erc20-price", "")),
+            ("uniswap-v2", Some(substreams_yaml("uniswap_v2", "")), cargo_toml("uniswap-v2", "")),
+        ];
+        for (project_name, substreams_yaml, cargo_toml) in projects {
+            let project_dir = repo_root.join(project_name);
+            fs::create_dir_all(&project_dir).unwrap();
+            fs::write(project_dir.join("Cargo.toml"), cargo_toml).unwrap();
+            if let Some(substreams_yaml) = substreams_yaml {
+                fs::write(project_dir.join("substreams.yaml"), substreams_yaml).unwrap();
+            }
+        }
+
+        let affected_substreams = get_affected_substreams(repo_root, &[PathBuf::from("helper/src/lib.rs"), PathBuf::from("README.md")]);
+        assert_eq!(affected_substreams.into_iter().collect::<Vec<_>>(), vec!["erc20-holdings".to_string(), "erc20-price".to_string()]);
+
+        let affected_substreams = get_affected_substreams(repo_root, &[PathBuf::from("erc20-price/src/lib.rs")]);
+        assert_eq!(affected_substreams.into_iter().collect::<Vec<_>>(), vec!["erc20-price".to_string()]);
+    }
+
+    #[test]
+    fn test_bumped_file_contents() {
+        let file_contents = "specVersion: v0.1.0\npackage:\n  name: \"uniswap_v2\"\n  version: v0.1.9 # Bumped by the CI\n\nimports:\n  eth: https://github.com/streamingfast/sf-ethereum/releases/download/v0.10.2/ethereum-v0.10.4.spkg\n";
+        assert_eq!(get_bumped_file_contents(file_contents, "0.1.9", "0.1.10"), None);
+
+        let file_contents = file_contents.replace(" # Bumped by the CI", "");
+        assert_eq!(get_bumped_file_contents(&file_contents, "0.1.9", "0.1.10").unwrap(), file_contents.replace("v0.1.9", "v0.1.10"));
+    }
+
+    #[test]
+    fn test_newer_version() {
+        assert!(is_newer_version("v0.1.10", "v0.1.9").unwrap());
+        assert!(is_newer_version("0.2.0", "v0.1.9").unwrap());
+        assert!(!is_newer_version("v0.1.9", "v0.1.9").unwrap());
+        assert!(is_newer_version("v0.2.0-rc1", "v0.1.9").unwrap());
+        assert!(!is_newer_version("v0.2.0-rc1", "v0.2.0").unwrap());
+        assert!(is_newer_version("v0.2.0", "v0.2.0-rc1").unwrap());
+        assert!(is_newer_version("v0.2", "v0.1.9").is_err());
+    }
+}
@@ -114,6 +114,22 @@ impl CargoToml {
         self.add_crate_dependencies(dependencies, "dependencies")
     }
 
+    /// Returns the paths of all of the dependencies (including build and dev dependencies) declared with a local path
+    pub(crate) fn get_local_dependency_paths(&self) -> Vec<PathBuf> {
+        ["dependencies", "build-dependencies", "dev-dependencies"]
+            .into_iter()
+            .filter_map(|section| self.manifest.data.get(section))
+            .filter_map(|section_item| section_item.as_table_like())
+            .flat_map(|dependency_table| {
+                dependency_table
+                    .iter()
+                    .filter_map(|(_, dependency)| dependency.get("path").and_then(|path| path.as_str()))
+                    .map(|path| self.cargo_dir.join(path))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
     pub(crate) fn get_file_contents(self) -> String {
         self.manifest.to_string()
     }
@@ -7,6 +7,7 @@ use regex::Regex;
 use yaml_rust::yaml::Hash;
 use yaml_rust::{Yaml, YamlEmitter, YamlLoader};
 use clap::ValueEnum;
+use strum_macros::{EnumIter, EnumVariantNames};
 
 #[derive(Clone, ValueEnum, PartialEq)]
 pub(crate) enum VersionType {
@@ -127,6 +128,22 @@ impl SubstreamsYaml {
 
     /// Returns spkg version in form X.Y.Z
     pub(crate) fn get_version(&self) -> String {
+        let version = self.get_version_string();
+
+        // We will make sure to represent the version in form: X.Y.Z
+        let v_semver = Regex::new(r"^v\d+.\d+.\d+$").unwrap();
+        let semver = Regex::new(r"^\d+.\d+.\d+$").unwrap();
+        if v_semver.is_match(&version) {
+            version[1..].to_string()
+        } else if semver.is_match(&version) {
+            version
+        } else {
+            panic!("Couldn't extract proper versioning from spkg! Expecting version to be either in form: vX.Y.Z or X.Y.Z - actual version given: {}", version);
+        }
+    }
+
+    /// Returns the spkg version exactly as it's written in the substreams.yaml
+    pub(crate) fn get_version_string(&self) -> String {
         let contents_hashmap = self.get_contents_hashmap();
 
         let package = if let Some(package) = contents_hashmap.get(&Yaml::from_str("package")) {
@@ -134,30 +151,19 @@ impl SubstreamsYaml {
         } else {
             panic!("Error with yaml file - package section does not exist! Filepath: {}", self.substreams_yaml_dir.join("substreams.yaml").to_string_lossy());
         };
-        let version = if let Yaml::Hash(package_hashmap) = package {
+        if let Yaml::Hash(package_hashmap) = package {
             let version = if let Some(version) = package_hashmap.get(&Yaml::from_str("version")) {
                 version
             } else {
                 panic!("Error with yaml file - version section is not found in package section! Filepath: {}", self.substreams_yaml_dir.join("substreams.yaml").to_string_lossy());
             };
             if let Yaml::String(version_string) = version {
-                version_string
+                version_string.clone()
             } else {
                 panic!("Error with yaml file - version section is not a string! Filepath: {}", self.substreams_yaml_dir.join("substreams.yaml").to_string_lossy());
             }
         } else {
             panic!("Error with yaml file - package section is not a hashmap! Filepath: {}", self.substreams_yaml_dir.join("substreams.yaml").to_string_lossy());
-        };
-
-        // We will make sure to represent the version in form: X.Y.Z
-        let v_semver = Regex::new(r"^v\d+.\d+.\d+$").unwrap();
-        let semver = Regex::new(r"^\d+.\d+.\d+$").unwrap();
-        if v_semver.is_match(version) {
-            version[1..].to_string()
-        } else if semver.is_match(version) {
-            version.clone()
-        } else {
-            panic!("Couldn't extract proper versioning from spkg! Expecting version to be either in form: vX.Y.Z or X.Y.Z - actual version given: {}", version);
         }
     }
 
@@ -331,6 +337,20 @@ impl SubstreamsYaml {
         Vec::new()
     }
 
+    /// Same as above but without requiring the spkgs to exist (eg. when they haven't been packed yet). The paths are
+    /// returned as they are declared, relative to the substreams.yaml folder
+    pub(crate) fn get_local_spkg_imports(&self) -> Vec<PathBuf> {
+        let contents_hashmap = self.get_contents_hashmap();
+
+        match contents_hashmap.get(&Yaml::from_str("imports")) {
+            Some(Yaml::Hash(imports_hashmap)) => imports_hashmap.values().filter_map(|import| import.as_str()).filter(|spkg_dependency| {
+                !spkg_dependency.starts_with("https://") && !spkg_dependency.starts_with("http://")
+            }).map(|spkg_dependency| self.substreams_yaml_dir.join(spkg_dependency)).collect(),
+            Some(_) => panic!("Error with yaml file - imports section is not a hashmap! Filepath: {}", self.substreams_yaml_dir.join("substreams.yaml").to_string_lossy()),
+            None => Vec::new(),
+        }
+    }
+
     /// Returns true if an edit to the substreams.yaml was made. (false if no changes made)
     pub(crate) fn add_protobuf_files(&mut self, protobuf_file_paths: Vec<PathBuf>) -> bool {
         if protobuf_file_paths.is_empty() {
@@ -493,6 +513,57 @@ impl SubstreamsYaml {
         true
     }
 
+    /// Returns the output type of a map module or the value type of a store module (None if the module doesn't exist)
+    pub(crate) fn get_module_output_type(&self, module_name: &str) -> Option<String> {
+        let contents_hashmap = self.get_contents_hashmap();
+
+        let modules_array = contents_hashmap.get(&Yaml::from_str("modules"))?.as_vec()?;
+        let module_hashmap = modules_array.iter().filter_map(|module| module.as_hash()).find(|module_hashmap| {
+            module_hashmap.get(&Yaml::from_str("name")).and_then(|name| name.as_str()) == Some(module_name)
+        })?;
+
+        match module_hashmap.get(&Yaml::from_str("kind")).and_then(|kind| kind.as_str()) {
+            Some("map") => module_hashmap.get(&Yaml::from_str("output"))?.as_hash()?.get(&Yaml::from_str("type"))?.as_str().map(|output_type| output_type.to_string()),
+            Some("store") => module_hashmap.get(&Yaml::from_str("valueType"))?.as_str().map(|value_type| value_type.to_string()),
+            _ => panic!("Error with yaml file - module: {} is neither a map or a store module! Filepath: {}", module_name, self.substreams_yaml_dir.join("substreams.yaml").to_string_lossy()),
+        }
+    }
+
+    pub(crate) fn get_module_names(&self) -> Vec<String> {
+        let contents_hashmap = self.get_contents_hashmap();
+
+        match contents_hashmap.get(&Yaml::from_str("modules")) {
+            Some(Yaml::Array(modules_array)) => modules_array.iter().filter_map(|module| module.as_hash()).filter_map(|module_hashmap| {
+                module_hashmap.get(&Yaml::from_str("name")).and_then(|name| name.as_str()).map(|name| name.to_string())
+            }).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the kind of the module (map or store) along with each of it's inputs in the form -> (input_kind, value),
+    /// eg. (source, sf.ethereum.type.v2.Block) or (store, store_name)
+    pub(crate) fn get_module_kind_and_inputs(&self, module_name: &str) -> Option<(String, Vec<(String, String)>)> {
+        let contents_hashmap = self.get_contents_hashmap();
+
+        let modules_array = contents_hashmap.get(&Yaml::from_str("modules"))?.as_vec()?;
+        let module_hashmap = modules_array.iter().filter_map(|module| module.as_hash()).find(|module_hashmap| {
+            module_hashmap.get(&Yaml::from_str("name")).and_then(|name| name.as_str()) == Some(module_name)
+        })?;
+
+        let kind = module_hashmap.get(&Yaml::from_str("kind"))?.as_str()?.to_string();
+        let inputs = match module_hashmap.get(&Yaml::from_str("inputs")) {
+            // Store inputs can also have a mode field alongside the input kind
+            Some(Yaml::Array(inputs)) => inputs.iter().filter_map(|input| input.as_hash()).filter_map(|input_hashmap| {
+                input_hashmap.iter().filter_map(|(input_kind, value)| Some((input_kind.as_str()?, value.as_str()?)))
+                    .find(|(input_kind, _)| ["source", "map", "store", "params"].contains(input_kind))
+                    .map(|(input_kind, value)| (input_kind.to_string(), value.to_string()))
+            }).collect(),
+            _ => Vec::new(),
+        };
+
+        Some((kind, inputs))
+    }
+
     fn get_contents_hashmap(&self) -> &Hash {
         if let Yaml::Hash(contents_hashmap) = &self.yaml {
             contents_hashmap
@@ -683,6 +754,30 @@ pub(crate) struct Input {
     pub(crate) input_value: String,
 }
 
+impl From<&str> for Input {
+    /// Inputs are given in the same form as they are declared in the substreams.yaml (eg. source:sf.ethereum.type.v2.Block or map:map_pools)
+    fn from(input_arg: &str) -> Self {
+        let (input_type, input_value) = if let Some((input_type, input_value)) = input_arg.split_once(':') {
+            (input_type.trim(), input_value.trim())
+        } else {
+            panic!("Input: {} is not in the form: {{source|map|store|params}}:{{value}}!", input_arg);
+        };
+
+        let input_type = match input_type {
+            "source" => InputType::Source,
+            "map" => InputType::Map,
+            "store" => InputType::Store,
+            "params" => InputType::Params,
+            _ => panic!("Input type: {} of input: {} is not one of: source, map, store or params!", input_type, input_arg),
+        };
+
+        Input {
+            input_type,
+            input_value: input_value.to_string(),
+        }
+    }
+}
+
 impl Display for Input {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}: {}", self.input_type, self.input_value)
@@ -693,6 +788,7 @@ pub(crate) enum InputType {
     Source,
     Store,
     Map,
+    Params,
 }
 
 impl Display for InputType {
@@ -701,10 +797,12 @@ impl Display for InputType {
             InputType::Source => write!(f, "source"),
             InputType::Store => write!(f, "store"),
             InputType::Map => write!(f, "map"),
+            InputType::Params => write!(f, "params"),
         }
     }
 }
 
+#[derive(ValueEnum, EnumIter, EnumVariantNames, Clone)]
 pub(crate) enum UpdatePolicy {
     Set,
     Add,
@@ -0,0 +1,75 @@
+use jsonschema::JSONSchema;
+use serde_json::Value;
+
+/// Validates a json value against a json schema, returning every violation found along with the path to the value at
+/// fault. An error is returned if the schema itself isn't valid
+pub(crate) fn validate_json_schema(schema: &Value, value: &Value) -> Result<Vec<String>, String> {
+    let compiled_schema = JSONSchema::compile(schema).map_err(|error| error.to_string())?;
+
+    let errors = match compiled_schema.validate(value) {
+        Ok(()) => Vec::new(),
+        // The root value has an empty json pointer so paths are prefixed with $, eg. $/subgraph/services
+        Err(errors) => errors.map(|error| format!("${}: {}", error.instance_path, error)).collect(),
+    };
+
+    Ok(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::collections::BTreeSet;
+
+    use crate::json_schema::validate_json_schema;
+
+    #[test]
+    fn test_validate_json_schema() {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2019-09/schema",
+            "type": "object",
+            "additionalProperties": { "$ref": "#/$defs/subgraph" },
+            "$defs": {
+                "subgraph": {
+                    "properties": {
+                        "services": {
+                            "type": "object",
+                            "properties": {
+                                "hosted-service": { "$ref": "#/$defs/service" },
+                                "decentralized-network": { "$ref": "#/$defs/service" }
+                            },
+                            "anyOf": [
+                                { "required": ["hosted-service"] },
+                                { "required": ["decentralized-network"] }
+                            ]
+                        },
+                        "start-blocks": { "type": "array", "items": { "type": "integer" } }
+                    },
+                    "required": ["services"]
+                },
+                "service": {
+                    "type": "object",
+                    "properties": { "slug": { "type": "string" } },
+                    "required": ["slug"]
+                }
+            }
+        });
+
+        assert!(validate_json_schema(&schema, &json!({
+            "b": { "services": { "hosted-service": { "slug": "b" } }, "start-blocks": [1, 2] },
+        })).unwrap().is_empty());
+
+        let errors = validate_json_schema(&schema, &json!({
+            "b": { "services": {}, "start-blocks": [1, "2"] },
+            "c": { "services": { "decentralized-network": { "slug": 3 } } },
+            "d": {},
+        })).unwrap();
+        let error_paths = errors.iter().map(|error| error.split_once(": ").unwrap().0).collect::<BTreeSet<_>>();
+        assert_eq!(error_paths, BTreeSet::from(["$/b/services", "$/b/start-blocks/1", "$/c/services/decentralized-network/slug", "$/d"]));
+    }
+
+    #[test]
+    fn test_invalid_schema_is_rejected() {
+        let schema = json!({ "type": "object", "properties": { "slug": { "type": "text" } } });
+        assert!(validate_json_schema(&schema, &json!({ "slug": "a" })).is_err());
+    }
+}
@@ -3,8 +3,13 @@ mod automapper;
 mod command_line_client;
 mod commands;
 mod file_modification;
+mod json_schema;
+mod module;
+mod module_graph;
 mod protocols;
 mod template_files;
+#[cfg(test)]
+mod test_utils;
 mod terminal_interface;
 mod utils;
 mod streaming_fast;
@@ -0,0 +1,585 @@
+use clap::{Parser, ValueEnum};
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use strum_macros::{EnumIter, EnumVariantNames};
+
+use crate::file_modification::file_contents_modifier::{
+    safely_modify_file_contents, File, FileContentsModification,
+};
+use crate::file_modification::substreams_yaml::{
+    Input, InputType, Module, SubstreamsYaml, UpdatePolicy,
+};
+use crate::terminal_interface::{get_input, get_success_message, select_from_enum};
+
+#[derive(Parser)]
+pub(crate) struct ModuleArgs {
+    #[arg(long, value_name = "Module Name")]
+    pub(crate) module_name: Option<String>,
+    #[arg(long, value_name = "Module Kind")]
+    pub(crate) module_kind: Option<ModuleKind>,
+    #[arg(
+        long,
+        value_name = "Initial Block",
+        help = "Leave blank to use the initial block of the module's inputs."
+    )]
+    pub(crate) initial_block: Option<u64>,
+    #[arg(
+        long,
+        value_name = "Inputs",
+        help = "Inputs are declared the same way as in the substreams.yaml, eg. source:sf.ethereum.type.v2.Block or store:store_pools. Multiple inputs can be specified at once with comma separation."
+    )]
+    pub(crate) inputs: Option<String>,
+    #[arg(
+        long,
+        value_name = "Output Type",
+        help = "Output type of a map module, eg. proto:messari.erc20.v1.TransferEvents"
+    )]
+    pub(crate) output_type: Option<String>,
+    #[arg(
+        long,
+        value_name = "Value Type",
+        help = "Value type of a store module, eg. bigint or proto:messari.erc20.v1.TransferEvent"
+    )]
+    pub(crate) value_type: Option<String>,
+    #[arg(long, value_name = "Update Policy")]
+    pub(crate) update_policy: Option<UpdatePolicy>,
+}
+
+#[derive(ValueEnum, EnumIter, EnumVariantNames, Clone, PartialEq)]
+pub(crate) enum ModuleKind {
+    Map,
+    Store,
+}
+
+/// A rust type used in a handler signature along with the imports it needs
+struct RustType {
+    imports: Vec<String>,
+    name: String,
+}
+
+/// Appends the module to the substreams.yaml and adds a handler stub for it in src/modules/N_{module_name}.rs
+pub(crate) fn add_module(module_args: &ModuleArgs, project_dir: &PathBuf) {
+    let substreams_yaml_filepath = project_dir.join("substreams.yaml");
+    if !substreams_yaml_filepath.exists() {
+        panic!(
+            "Project supplied: {}, is not a substreams project. It contains no substreams.yaml file!",
+            project_dir.to_string_lossy()
+        );
+    }
+    let mut substreams_yaml = SubstreamsYaml::load_from_file(&substreams_yaml_filepath);
+
+    let module_name = if let Some(module_name) = &module_args.module_name {
+        module_name.clone()
+    } else {
+        get_input("Module Name", None, false)
+    };
+    if !Regex::new(r"^[a-z][a-z0-9_]*$").unwrap().is_match(&module_name) {
+        panic!("Module name: {} has to be in snake case as it's also used as the name of the handler function!", module_name);
+    }
+
+    let module_kind = if let Some(module_kind) = &module_args.module_kind {
+        module_kind.clone()
+    } else {
+        select_from_enum("Module Kind", Some(0))
+    };
+
+    let initial_block = if let Some(initial_block) = module_args.initial_block {
+        Some(initial_block)
+    } else if module_args.module_name.is_some() {
+        // Module was declared through the cmd line args so we don't ask for the optional parts of it
+        None
+    } else {
+        let initial_block = get_input("Initial Block (Leave blank to skip)", Some("Initial Block"), true);
+        if initial_block.is_empty() {
+            None
+        } else {
+            Some(initial_block.parse::<u64>().expect(&format!("Initial block: {} is not a valid block number!", initial_block)))
+        }
+    };
+
+    let inputs = if let Some(inputs) = &module_args.inputs {
+        inputs.split(",").into_iter().map(|input| input.into()).collect::<Vec<Input>>()
+    } else {
+        let mut inputs: Vec<Input> = Vec::new();
+        loop {
+            let input = if inputs.is_empty() {
+                get_input("Input (eg. source:sf.ethereum.type.v2.Block or map:map_pools)", Some("Input"), false)
+            } else {
+                get_input("Input (Leave blank to finish)", Some("Input"), true)
+            };
+            if input.is_empty() {
+                break;
+            }
+            inputs.push(input.as_str().into());
+        }
+        inputs
+    };
+    if inputs.is_empty() {
+        panic!("Module: {} needs at least one input!", module_name);
+    }
+
+    let input_params = inputs.iter().map(|input| get_input_param(input, &substreams_yaml)).collect::<Vec<_>>();
+
+    let (handler_file_contents, module) = match module_kind {
+        ModuleKind::Map => {
+            let output_type = if let Some(output_type) = &module_args.output_type {
+                output_type.clone()
+            } else {
+                get_input("Output Type (eg. proto:messari.erc20.v1.TransferEvents)", Some("Output Type"), false)
+            };
+            if !output_type.starts_with("proto:") {
+                panic!("Output type: {} of map module: {} has to be a proto type!", output_type, module_name);
+            }
+
+            let output_rust_type = get_proto_rust_type(&output_type);
+            let error_rust_type = RustType {
+                imports: vec!["substreams::errors::Error".to_string()],
+                name: "Error".to_string(),
+            };
+            let handler_file_contents = get_handler_file_contents(
+                "map",
+                &module_name,
+                &input_params,
+                None,
+                Some(format!("Result<{}, {}>", output_rust_type.name, error_rust_type.name)),
+                vec![&output_rust_type, &error_rust_type],
+            );
+
+            (handler_file_contents, Module::map(module_name.clone(), initial_block, inputs, output_type))
+        }
+        ModuleKind::Store => {
+            let value_type = if let Some(value_type) = &module_args.value_type {
+                value_type.clone()
+            } else {
+                get_input("Value Type (eg. bigint or proto:messari.erc20.v1.TransferEvent)", Some("Value Type"), false)
+            };
+            let update_policy = if let Some(update_policy) = &module_args.update_policy {
+                update_policy.clone()
+            } else {
+                select_from_enum("Update Policy", Some(0))
+            };
+
+            let store_rust_type = get_store_rust_type(&update_policy.to_string(), &value_type)
+                .unwrap_or_else(|error| panic!("{}", error));
+            let handler_file_contents = get_handler_file_contents(
+                "store",
+                &module_name,
+                &input_params,
+                Some(("store".to_string(), &store_rust_type)),
+                None,
+                vec![],
+            );
+
+            (handler_file_contents, Module::store(module_name.clone(), initial_block, update_policy, value_type, inputs))
+        }
+    };
+
+    let (mut operations, module_filepath) = get_add_module_operations(project_dir, &mut substreams_yaml, module, handler_file_contents);
+    operations.push(FileContentsModification::UpdateFile(File {
+        filepath: substreams_yaml_filepath,
+        file_contents: substreams_yaml.get_file_contents(),
+    }));
+
+    safely_modify_file_contents(operations);
+
+    println!(
+        "{}",
+        get_success_message(format!(
+            "Module: {} added! Handler stub: {}",
+            module_name,
+            module_filepath.to_string_lossy()
+        ))
+    );
+}
+
+/// Adds the module to the substreams.yaml contents and returns the operations for adding it's handler to
+/// src/modules/N_{module_name}.rs along with the handler filepath. Writing the substreams.yaml is left to the caller
+/// so that any other changes to it can be made first
+pub(crate) fn get_add_module_operations(
+    project_dir: &PathBuf,
+    substreams_yaml: &mut SubstreamsYaml,
+    module: Module,
+    handler_file_contents: String,
+) -> (Vec<FileContentsModification>, PathBuf) {
+    let module_name = module.name().clone();
+    if !substreams_yaml.add_module(module) {
+        panic!(
+            "Module: {} already exists in the substreams.yaml of project: {}!",
+            module_name,
+            project_dir.to_string_lossy()
+        );
+    }
+
+    let modules_dir = project_dir.join("src").join("modules");
+    let module_filepath = modules_dir.join(format!("{}_{}.rs", get_next_module_number(&modules_dir), module_name));
+
+    let mut operations = Vec::new();
+
+    let modules_mod_filepath = modules_dir.join("mod.rs");
+    let module_declaration = format!(
+        "#[path = \"{}\"]\nmod {};\n",
+        module_filepath.file_name().unwrap().to_string_lossy(),
+        module_name
+    );
+    if !modules_dir.exists() {
+        operations.push(FileContentsModification::CreateFolder(modules_dir));
+
+        let lib_filepath = project_dir.join("src").join("lib.rs");
+        let lib_file_contents = fs::read_to_string(&lib_filepath).expect(&format!(
+            "Unable to read lib.rs contents! Filepath: {}",
+            lib_filepath.to_string_lossy()
+        ));
+        operations.push(FileContentsModification::UpdateFile(File {
+            filepath: lib_filepath,
+            file_contents: format!("{}\nmod modules;\n", lib_file_contents.trim_end()),
+        }));
+    }
+    if modules_mod_filepath.exists() {
+        let mod_file_contents = fs::read_to_string(&modules_mod_filepath).expect(&format!(
+            "Unable to read modules/mod.rs contents! Filepath: {}",
+            modules_mod_filepath.to_string_lossy()
+        ));
+        operations.push(FileContentsModification::UpdateFile(File {
+            filepath: modules_mod_filepath,
+            file_contents: format!("{}\n{}", mod_file_contents.trim_end(), module_declaration).trim_start().to_string(),
+        }));
+    } else {
+        operations.push(FileContentsModification::CreateFile(File {
+            filepath: modules_mod_filepath,
+            file_contents: module_declaration,
+        }));
+    }
+
+    operations.push(FileContentsModification::CreateFile(File {
+        filepath: module_filepath.clone(),
+        file_contents: handler_file_contents,
+    }));
+
+    (operations, module_filepath)
+}
+
+/// Modules are numbered in the order they get added, eg. 1_map_pools.rs, 2_store_pools.rs
+fn get_next_module_number(modules_dir: &PathBuf) -> u64 {
+    if !modules_dir.exists() {
+        return 1;
+    }
+
+    let module_numbers = fs::read_dir(modules_dir)
+        .expect(&format!(
+            "Unable to read modules folder: {}",
+            modules_dir.to_string_lossy()
+        ))
+        .filter_map(|entry| {
+            let file_name = entry.ok()?.file_name().to_string_lossy().to_string();
+            file_name.split_once('_')?.0.parse::<u64>().ok()
+        })
+        .collect::<Vec<_>>();
+
+    module_numbers.into_iter().max().unwrap_or(0) + 1
+}
+
+/// Returns the param name and type the input gets passed into the handler as
+fn get_input_param(input: &Input, substreams_yaml: &SubstreamsYaml) -> (String, RustType) {
+    match input.input_type {
+        InputType::Source => {
+            let rust_type = match input.input_value.as_str() {
+                "sf.ethereum.type.v2.Block" => "substreams_ethereum::pb::eth::v2::Block",
+                "sf.substreams.v1.Clock" => "substreams::pb::substreams::Clock",
+                _ => panic!("Source: {} is not supported! Supported sources: sf.ethereum.type.v2.Block, sf.substreams.v1.Clock", input.input_value),
+            };
+            let name = rust_type.split("::").last().unwrap().to_string();
+
+            (
+                name.to_lowercase(),
+                RustType {
+                    imports: vec![rust_type.to_string()],
+                    name,
+                },
+            )
+        }
+        InputType::Map => {
+            let output_type = substreams_yaml.get_module_output_type(&input.input_value).expect(&format!(
+                "Map module: {} used as an input does not exist in the substreams.yaml!",
+                input.input_value
+            ));
+            (input.input_value.clone(), get_proto_rust_type(&output_type))
+        }
+        InputType::Store => {
+            let value_type = substreams_yaml.get_module_output_type(&input.input_value).expect(&format!(
+                "Store module: {} used as an input does not exist in the substreams.yaml!",
+                input.input_value
+            ));
+            let store_rust_type = get_store_rust_type("get", &value_type).unwrap_or_else(|error| panic!("{}", error));
+            (input.input_value.clone(), store_rust_type)
+        }
+        InputType::Params => (
+            "params".to_string(),
+            RustType {
+                imports: vec![],
+                name: "String".to_string(),
+            },
+        ),
+    }
+}
+
+/// Proto types are generated in the pb module of the project without the messari prefix
+/// (eg. proto:messari.erc20.v1.TransferEvents -> crate::pb::erc20::v1::TransferEvents)
+fn get_proto_rust_type(proto_type: &str) -> RustType {
+    let full_type_name = proto_type.trim_start_matches("proto:").trim_start_matches("messari.");
+    let name = full_type_name.split('.').last().unwrap().to_string();
+
+    RustType {
+        imports: vec![format!("crate::pb::{}", full_type_name.replace('.', "::"))],
+        name,
+    }
+}
+
+/// Store types are named after the store operation and the value type, eg. StoreGetProto<Pool> or StoreAddBigInt.
+/// Only numeric value types can be used with the add update policy
+fn get_store_rust_type(store_operation: &str, value_type: &str) -> Result<RustType, String> {
+    let store_operation = format!("{}{}", store_operation[..1].to_uppercase(), &store_operation[1..]);
+
+    let value_type_suffix = match value_type {
+        "bigint" => "BigInt",
+        "bigdecimal" | "bigfloat" => "BigDecimal",
+        "int64" => "Int64",
+        "float64" => "Float64",
+        "string" => "String",
+        "bytes" => "Raw",
+        value_type if value_type.starts_with("proto:") => "Proto",
+        _ => return Err(format!("Value type: {} is not supported! Expecting one of: bigint, bigdecimal, int64, float64, string, bytes or proto:{{type}}", value_type)),
+    };
+    if store_operation == "Add" && matches!(value_type_suffix, "String" | "Raw" | "Proto") {
+        return Err(format!("Value type: {} can't be used with the add update policy! Expecting one of: bigint, bigdecimal, int64 or float64", value_type));
+    }
+    let store_type_name = format!("Store{}{}", store_operation, value_type_suffix);
+    let mut imports = vec![format!("substreams::store::{}", store_type_name)];
+
+    let name = if value_type_suffix == "Proto" {
+        let proto_rust_type = get_proto_rust_type(value_type);
+        imports.extend(proto_rust_type.imports);
+        format!("{}<{}>", store_type_name, proto_rust_type.name)
+    } else {
+        store_type_name
+    };
+
+    Ok(RustType {
+        imports,
+        name,
+    })
+}
+
+fn get_handler_file_contents(
+    handler_type: &str,
+    module_name: &str,
+    input_params: &[(String, RustType)],
+    output_param: Option<(String, &RustType)>,
+    return_type: Option<String>,
+    other_types: Vec<&RustType>,
+) -> String {
+    let imports = input_params
+        .iter()
+        .map(|(_, rust_type)| rust_type)
+        .chain(output_param.iter().map(|(_, rust_type)| *rust_type))
+        .chain(other_types.into_iter())
+        .flat_map(|rust_type| rust_type.imports.iter().cloned())
+        .collect::<BTreeSet<_>>();
+
+    let params = input_params
+        .iter()
+        .map(|(param_name, rust_type)| (param_name.clone(), rust_type.name.clone()))
+        .chain(output_param.map(|(param_name, rust_type)| (param_name, rust_type.name.clone())))
+        .map(|(param_name, type_name)| format!("    {}: {},\n", param_name, type_name))
+        .collect::<String>();
+
+    let return_type = return_type.map(|return_type| format!(" -> {}", return_type)).unwrap_or_default();
+
+    format!(
+        "{}\n\n\
+        #[substreams::handlers::{}]\n\
+        fn {}(\n\
+        {}){} {{\n    \
+            todo!()\n\
+        }}\n",
+        imports.into_iter().map(|import| format!("use {};", import)).collect::<Vec<_>>().join("\n"),
+        handler_type,
+        module_name,
+        params,
+        return_type
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::file_modification::file_contents_modifier::FileContentsModification;
+    use crate::file_modification::substreams_yaml::{Input, Module, SubstreamsYaml, UpdatePolicy};
+    use crate::module::{
+        get_add_module_operations, get_handler_file_contents, get_input_param, get_next_module_number,
+        get_store_rust_type,
+    };
+    use crate::test_utils::get_temp_dir;
+
+    const SUBSTREAMS_YAML: &str = r#"
+specVersion: v0.1.0
+package:
+  name: test_substream
+  version: v0.1.0
+
+modules:
+  - name: map_pools
+    kind: map
+    inputs:
+      - source: sf.ethereum.type.v2.Block
+    output:
+      type: proto:messari.test.v1.Pools
+
+  - name: store_pools
+    kind: store
+    updatePolicy: set
+    valueType: proto:messari.test.v1.Pool
+    inputs:
+      - map: map_pools
+"#;
+
+    fn get_substreams_yaml(project_dir: &std::path::Path) -> SubstreamsYaml {
+        fs::write(project_dir.join("substreams.yaml"), SUBSTREAMS_YAML).unwrap();
+        SubstreamsYaml::load_from_file(&project_dir.join("substreams.yaml"))
+    }
+
+    fn get_test_module(name: &str) -> Module {
+        Module::map(name.to_string(), None, vec!["source:sf.substreams.v1.Clock".into()], "proto:messari.test.v1.Pools".to_string())
+    }
+
+    #[test]
+    fn test_get_next_module_number() {
+        let temp_dir = get_temp_dir();
+        let modules_dir = temp_dir.path().join("modules");
+        assert_eq!(get_next_module_number(&modules_dir), 1);
+
+        fs::create_dir_all(&modules_dir).unwrap();
+        fs::write(modules_dir.join("mod.rs"), "").unwrap();
+        fs::write(modules_dir.join("1_map_pools.rs"), "").unwrap();
+        fs::write(modules_dir.join("3_store_pools.rs"), "").unwrap();
+        assert_eq!(get_next_module_number(&modules_dir), 4);
+    }
+
+    #[test]
+    fn test_first_module_registers_modules_folder() {
+        let temp_dir = get_temp_dir();
+        let project_dir = temp_dir.path().to_path_buf();
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        fs::write(project_dir.join("src").join("lib.rs"), "mod pb;\n").unwrap();
+        let mut substreams_yaml = get_substreams_yaml(&project_dir);
+
+        let (operations, module_filepath) = get_add_module_operations(&project_dir, &mut substreams_yaml, get_test_module("map_events"), "handler".to_string());
+
+        let modules_dir = project_dir.join("src").join("modules");
+        assert_eq!(module_filepath, modules_dir.join("1_map_events.rs"));
+        assert_eq!(operations.len(), 4);
+        assert!(matches!(&operations[0], FileContentsModification::CreateFolder(folder) if folder == &modules_dir));
+        assert!(matches!(&operations[1], FileContentsModification::UpdateFile(file)
+            if file.filepath == project_dir.join("src").join("lib.rs") && file.file_contents == "mod pb;\nmod modules;\n"));
+        assert!(matches!(&operations[2], FileContentsModification::CreateFile(file)
+            if file.filepath == modules_dir.join("mod.rs") && file.file_contents == "#[path = \"1_map_events.rs\"]\nmod map_events;\n"));
+        assert!(matches!(&operations[3], FileContentsModification::CreateFile(file)
+            if file.filepath == module_filepath && file.file_contents == "handler"));
+        assert!(substreams_yaml.get_file_contents().contains("name: map_events"));
+    }
+
+    #[test]
+    fn test_module_is_appended_to_existing_modules() {
+        let temp_dir = get_temp_dir();
+        let project_dir = temp_dir.path().to_path_buf();
+        let modules_dir = project_dir.join("src").join("modules");
+        fs::create_dir_all(&modules_dir).unwrap();
+        fs::write(modules_dir.join("mod.rs"), "#[path = \"1_map_pools.rs\"]\nmod map_pools;\n").unwrap();
+        fs::write(modules_dir.join("1_map_pools.rs"), "").unwrap();
+        let mut substreams_yaml = get_substreams_yaml(&project_dir);
+
+        let (operations, module_filepath) = get_add_module_operations(&project_dir, &mut substreams_yaml, get_test_module("map_events"), "handler".to_string());
+
+        assert_eq!(module_filepath, modules_dir.join("2_map_events.rs"));
+        assert_eq!(operations.len(), 2);
+        assert!(matches!(&operations[0], FileContentsModification::UpdateFile(file)
+            if file.filepath == modules_dir.join("mod.rs")
+                && file.file_contents == "#[path = \"1_map_pools.rs\"]\nmod map_pools;\n#[path = \"2_map_events.rs\"]\nmod map_events;\n"));
+        assert!(matches!(&operations[1], FileContentsModification::CreateFile(file) if file.filepath == module_filepath));
+    }
+
+    #[test]
+    #[should_panic(expected = "Module: map_pools already exists")]
+    fn test_existing_module_panics() {
+        let temp_dir = get_temp_dir();
+        let project_dir = temp_dir.path().to_path_buf();
+        let mut substreams_yaml = get_substreams_yaml(&project_dir);
+
+        get_add_module_operations(&project_dir, &mut substreams_yaml, get_test_module("map_pools"), "handler".to_string());
+    }
+
+    #[test]
+    fn test_store_rust_types() {
+        let store_rust_type = get_store_rust_type(&UpdatePolicy::Add.to_string(), "bigint").unwrap();
+        assert_eq!(store_rust_type.name, "StoreAddBigInt");
+        assert_eq!(store_rust_type.imports, vec!["substreams::store::StoreAddBigInt".to_string()]);
+
+        assert_eq!(get_store_rust_type(&UpdatePolicy::Add.to_string(), "bigfloat").unwrap().name, "StoreAddBigDecimal");
+        assert_eq!(get_store_rust_type(&UpdatePolicy::Set.to_string(), "string").unwrap().name, "StoreSetString");
+        assert_eq!(get_store_rust_type(&UpdatePolicy::Set.to_string(), "bytes").unwrap().name, "StoreSetRaw");
+
+        let store_rust_type = get_store_rust_type("get", "proto:messari.test.v1.Pool").unwrap();
+        assert_eq!(store_rust_type.name, "StoreGetProto<Pool>");
+        assert_eq!(
+            store_rust_type.imports,
+            vec!["substreams::store::StoreGetProto".to_string(), "crate::pb::test::v1::Pool".to_string()]
+        );
+
+        for value_type in ["string", "bytes", "proto:messari.test.v1.Pool"] {
+            assert!(get_store_rust_type(&UpdatePolicy::Add.to_string(), value_type).is_err());
+        }
+        assert!(get_store_rust_type(&UpdatePolicy::Set.to_string(), "int32").is_err());
+    }
+
+    #[test]
+    fn test_handler_signature() {
+        let temp_dir = get_temp_dir();
+        let substreams_yaml = get_substreams_yaml(temp_dir.path());
+
+        let input_params = ["params:min_liquidity", "source:sf.substreams.v1.Clock", "map:map_pools", "store:store_pools"]
+            .into_iter()
+            .map(|input| get_input_param(&Input::from(input), &substreams_yaml))
+            .collect::<Vec<_>>();
+        let store_rust_type = get_store_rust_type(&UpdatePolicy::Add.to_string(), "int64").unwrap();
+
+        let handler_file_contents = get_handler_file_contents(
+            "store",
+            "store_pool_count",
+            &input_params,
+            Some(("store".to_string(), &store_rust_type)),
+            None,
+            vec![],
+        );
+
+        assert_eq!(
+            handler_file_contents,
+            "use crate::pb::test::v1::Pool;\n\
+            use crate::pb::test::v1::Pools;\n\
+            use substreams::pb::substreams::Clock;\n\
+            use substreams::store::StoreAddInt64;\n\
+            use substreams::store::StoreGetProto;\n\n\
+            #[substreams::handlers::store]\n\
+            fn store_pool_count(\n    \
+                params: String,\n    \
+                clock: Clock,\n    \
+                map_pools: Pools,\n    \
+                store_pools: StoreGetProto<Pool>,\n    \
+                store: StoreAddInt64,\n\
+            ) {\n    \
+                todo!()\n\
+            }\n"
+        );
+    }
+}
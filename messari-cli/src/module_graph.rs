@@ -0,0 +1,312 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use crate::file_modification::substreams_yaml::SubstreamsYaml;
+use crate::streaming_fast::streamingfast_dtos::module::input::Input;
+use crate::streaming_fast::streamingfast_dtos::module::Kind;
+use crate::streaming_fast::streamingfast_dtos::Package;
+
+const HIGHLIGHT_COLOUR: &str = "#f9d71c";
+
+/// The modules of a substream along with the inputs of each, in the order they are declared
+#[derive(Serialize)]
+pub(crate) struct ModuleGraph {
+    modules: Vec<ModuleNode>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct ModuleNode {
+    name: String,
+    kind: String,
+    inputs: Vec<ModuleNodeInput>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub(crate) enum ModuleNodeInput {
+    Source(String),
+    Map(String),
+    Store(String),
+    Params(String),
+}
+
+impl ModuleNodeInput {
+    /// Name of the module this input is the output of, if it is a module input
+    fn get_module_name(&self) -> Option<&String> {
+        match self {
+            ModuleNodeInput::Map(module_name) | ModuleNodeInput::Store(module_name) => Some(module_name),
+            ModuleNodeInput::Source(_) | ModuleNodeInput::Params(_) => None,
+        }
+    }
+}
+
+impl ModuleGraph {
+    pub(crate) fn from_substreams_yaml(substreams_yaml: &SubstreamsYaml) -> Self {
+        let modules = substreams_yaml.get_module_names().into_iter().map(|module_name| {
+            let (kind, inputs) = substreams_yaml.get_module_kind_and_inputs(&module_name)
+                .unwrap_or_else(|| panic!("Error with yaml file - module: {} has no kind!", module_name));
+            let inputs = inputs.into_iter().map(|(input_kind, value)| match input_kind.as_str() {
+                "source" => ModuleNodeInput::Source(value),
+                "map" => ModuleNodeInput::Map(value),
+                "store" => ModuleNodeInput::Store(value),
+                _ => ModuleNodeInput::Params(value),
+            }).collect();
+
+            ModuleNode {
+                name: module_name,
+                kind,
+                inputs,
+            }
+        }).collect();
+
+        ModuleGraph {
+            modules,
+        }
+    }
+
+    pub(crate) fn from_package(package: &Package) -> Self {
+        let modules = package.modules.as_ref().map(|modules| modules.modules.iter().map(|module| {
+            let kind = match module.kind.as_ref() {
+                Some(Kind::KindMap(_)) => "map",
+                Some(Kind::KindStore(_)) => "store",
+                None => panic!("Module: {} in the spkg has no kind!", module.name),
+            };
+            let inputs = module.inputs.iter().filter_map(|input| input.input.as_ref()).map(|input| match input {
+                Input::Source(source) => ModuleNodeInput::Source(source.r#type.clone()),
+                Input::Map(map) => ModuleNodeInput::Map(map.module_name.clone()),
+                Input::Store(store) => ModuleNodeInput::Store(store.module_name.clone()),
+                Input::Params(params) => ModuleNodeInput::Params(params.value.clone()),
+            }).collect();
+
+            ModuleNode {
+                name: module.name.clone(),
+                kind: kind.to_string(),
+                inputs,
+            }
+        }).collect()).unwrap_or_default();
+
+        ModuleGraph {
+            modules,
+        }
+    }
+
+    /// Returns the module along with all of the modules it depends on, either directly or indirectly. Modules imported
+    /// from other spkgs in a substreams.yaml aren't part of the graph, so their own dependencies can't be followed
+    pub(crate) fn get_required_modules(&self, module_name: &str) -> Result<BTreeSet<String>, String> {
+        if !self.modules.iter().any(|module| module.name == module_name) {
+            return Err(format!("Module: {} doesn't exist! Modules: {}", module_name, self.modules.iter().map(|module| module.name.as_str()).collect::<Vec<_>>().join(", ")));
+        }
+
+        let mut required_modules = BTreeSet::from([module_name.to_string()]);
+        let mut modules_to_check = vec![module_name.to_string()];
+        while let Some(module_name) = modules_to_check.pop() {
+            let module = match self.modules.iter().find(|module| module.name == module_name) {
+                Some(module) => module,
+                None => continue,
+            };
+            for input_module_name in module.inputs.iter().filter_map(|input| input.get_module_name()) {
+                if required_modules.insert(input_module_name.clone()) {
+                    modules_to_check.push(input_module_name.clone());
+                }
+            }
+        }
+
+        Ok(required_modules)
+    }
+
+    /// Modules that none of the output modules depend on
+    pub(crate) fn get_unused_modules(&self, output_modules: &[String]) -> Result<Vec<String>, String> {
+        let mut required_modules = BTreeSet::new();
+        for output_module in output_modules.iter() {
+            required_modules.extend(self.get_required_modules(output_module)?);
+        }
+
+        Ok(self.modules.iter().filter(|module| !required_modules.contains(&module.name)).map(|module| module.name.clone()).collect())
+    }
+
+    /// Same layout as the graphs printed by `substreams graph`, with each module followed by it's inputs
+    pub(crate) fn to_mermaid(&self, highlighted_modules: &BTreeSet<String>) -> String {
+        let mut lines = vec!["graph TD;".to_string()];
+        for module in self.modules.iter() {
+            lines.push(format!("  {}[{}: {}]", module.name, module.kind, module.name));
+            for input in module.inputs.iter() {
+                match input {
+                    ModuleNodeInput::Source(source_type) => lines.push(format!("  {}[source: {}] --> {}", source_type, source_type, module.name)),
+                    ModuleNodeInput::Map(input_module_name) | ModuleNodeInput::Store(input_module_name) => lines.push(format!("  {} --> {}", input_module_name, module.name)),
+                    ModuleNodeInput::Params(_) => lines.push(format!("  {}:params[params] --> {}", module.name, module.name)),
+                }
+            }
+        }
+
+        if !highlighted_modules.is_empty() {
+            lines.push(format!("  classDef highlighted fill:{};", HIGHLIGHT_COLOUR));
+            lines.push(format!("  class {} highlighted;", highlighted_modules.iter().cloned().collect::<Vec<_>>().join(",")));
+        }
+
+        lines.join("\n")
+    }
+
+    pub(crate) fn to_dot(&self, highlighted_modules: &BTreeSet<String>) -> String {
+        let mut lines = vec!["digraph modules {".to_string()];
+        for module in self.modules.iter() {
+            let style = if highlighted_modules.contains(&module.name) {
+                format!(" style=filled fillcolor=\"{}\"", HIGHLIGHT_COLOUR)
+            } else {
+                String::new()
+            };
+            lines.push(format!("  \"{}\" [label=\"{}: {}\"{}];", module.name, module.kind, module.name, style));
+            for input in module.inputs.iter() {
+                match input {
+                    ModuleNodeInput::Source(source_type) => {
+                        lines.push(format!("  \"{}\" [label=\"source: {}\" shape=box];", source_type, source_type));
+                        lines.push(format!("  \"{}\" -> \"{}\";", source_type, module.name));
+                    },
+                    ModuleNodeInput::Map(input_module_name) | ModuleNodeInput::Store(input_module_name) => lines.push(format!("  \"{}\" -> \"{}\";", input_module_name, module.name)),
+                    ModuleNodeInput::Params(_) => {
+                        lines.push(format!("  \"{}:params\" [label=\"params\" shape=box];", module.name));
+                        lines.push(format!("  \"{}:params\" -> \"{}\";", module.name, module.name));
+                    },
+                }
+            }
+        }
+        lines.push("}".to_string());
+
+        lines.join("\n")
+    }
+
+    pub(crate) fn to_json(&self, highlighted_modules: &BTreeSet<String>, unused_modules: &[String]) -> String {
+        let mut graph_json = serde_json::to_value(self).unwrap();
+        graph_json["highlightedModules"] = serde_json::json!(highlighted_modules);
+        graph_json["unusedModules"] = serde_json::json!(unused_modules);
+
+        serde_json::to_string_pretty(&graph_json).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use std::collections::BTreeSet;
+
+    use crate::file_modification::substreams_yaml::SubstreamsYaml;
+    use crate::module_graph::ModuleGraph;
+    use crate::streaming_fast::streamingfast_dtos::module::input::{Input, Map, Params, Source, Store};
+    use crate::streaming_fast::streamingfast_dtos::module::{Input as ModuleInput, Kind, KindMap, KindStore};
+    use crate::streaming_fast::streamingfast_dtos::{Module, Modules, Package};
+
+    const SUBSTREAMS_YAML: &str = r#"
+specVersion: v0.1.0
+package:
+  name: test_substream
+  version: v0.1.0
+
+modules:
+  - name: map_market_listed
+    kind: map
+    inputs:
+      - source: sf.ethereum.type.v2.Block
+    output:
+      type: proto:messari.test.v1.MarketListed
+  - name: store_token
+    kind: store
+    updatePolicy: set
+    valueType: string
+    inputs:
+      - params: string
+      - map: map_market_listed
+  - name: map_output
+    kind: map
+    inputs:
+      - map: map_market_listed
+      - store: store_token
+        mode: deltas
+    output:
+      type: proto:messari.test.v1.Output
+  - name: map_unused
+    kind: map
+    inputs:
+      - source: sf.ethereum.type.v2.Block
+    output:
+      type: proto:messari.test.v1.Unused
+"#;
+
+    #[test]
+    fn test_module_graph() {
+        let graph = ModuleGraph::from_substreams_yaml(&SubstreamsYaml::from(SUBSTREAMS_YAML));
+
+        let required_modules = graph.get_required_modules("map_output").unwrap();
+        assert_eq!(required_modules, BTreeSet::from(["map_market_listed".to_string(), "map_output".to_string(), "store_token".to_string()]));
+        assert_eq!(graph.get_unused_modules(&["map_output".to_string()]).unwrap(), vec!["map_unused".to_string()]);
+        assert!(graph.get_required_modules("map_missing").is_err());
+
+        assert_eq!(graph.to_mermaid(&required_modules), [
+            "graph TD;",
+            "  map_market_listed[map: map_market_listed]",
+            "  sf.ethereum.type.v2.Block[source: sf.ethereum.type.v2.Block] --> map_market_listed",
+            "  store_token[store: store_token]",
+            "  store_token:params[params] --> store_token",
+            "  map_market_listed --> store_token",
+            "  map_output[map: map_output]",
+            "  map_market_listed --> map_output",
+            "  store_token --> map_output",
+            "  map_unused[map: map_unused]",
+            "  sf.ethereum.type.v2.Block[source: sf.ethereum.type.v2.Block] --> map_unused",
+            "  classDef highlighted fill:#f9d71c;",
+            "  class map_market_listed,map_output,store_token highlighted;",
+        ].join("\n"));
+
+        let dot = graph.to_dot(&required_modules);
+        assert!(dot.contains("  \"map_output\" [label=\"map: map_output\" style=filled fillcolor=\"#f9d71c\"];"));
+        assert!(dot.contains("  \"map_unused\" [label=\"map: map_unused\"];"));
+        assert!(dot.contains("  \"store_token\" -> \"map_output\";"));
+
+        let graph_json: serde_json::Value = serde_json::from_str(&graph.to_json(&required_modules, &["map_unused".to_string()])).unwrap();
+        assert_eq!(graph_json["modules"][1]["inputs"], serde_json::json!([
+            { "kind": "params", "value": "string" },
+            { "kind": "map", "value": "map_market_listed" },
+        ]));
+        assert_eq!(graph_json["unusedModules"], serde_json::json!(["map_unused"]));
+    }
+
+    #[test]
+    fn test_module_graph_from_package() {
+        let package = Package {
+            modules: Some(Modules {
+                modules: vec![
+                    Module {
+                        name: "map_market_listed".to_string(),
+                        kind: Some(Kind::KindMap(KindMap { output_type: "proto:messari.test.v1.MarketListed".to_string() })),
+                        inputs: vec![ModuleInput { input: Some(Input::Source(Source { r#type: "sf.ethereum.type.v2.Block".to_string() })) }],
+                        ..Default::default()
+                    },
+                    Module {
+                        name: "store_token".to_string(),
+                        kind: Some(Kind::KindStore(KindStore { value_type: "string".to_string(), ..Default::default() })),
+                        inputs: vec![
+                            ModuleInput { input: Some(Input::Params(Params { value: "0x7d27".to_string() })) },
+                            ModuleInput { input: Some(Input::Map(Map { module_name: "map_market_listed".to_string() })) },
+                        ],
+                        ..Default::default()
+                    },
+                    Module {
+                        name: "map_output".to_string(),
+                        kind: Some(Kind::KindMap(KindMap { output_type: "proto:messari.test.v1.Output".to_string() })),
+                        inputs: vec![
+                            ModuleInput { input: Some(Input::Map(Map { module_name: "map_market_listed".to_string() })) },
+                            ModuleInput { input: Some(Input::Store(Store { module_name: "store_token".to_string(), ..Default::default() })) },
+                        ],
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let package = Package::decode(package.encode_to_vec().as_slice()).unwrap();
+
+        let graph = ModuleGraph::from_package(&package);
+        let yaml_graph = ModuleGraph::from_substreams_yaml(&SubstreamsYaml::from(SUBSTREAMS_YAML.split("  - name: map_unused").next().unwrap()));
+        assert_eq!(graph.to_mermaid(&BTreeSet::new()), yaml_graph.to_mermaid(&BTreeSet::new()));
+        assert!(graph.get_unused_modules(&["map_output".to_string()]).unwrap().is_empty());
+    }
+}
@@ -1,34 +1,50 @@
-use std::env;
-use prost::Message;
-use tonic::metadata::MetadataValue;
-use tonic::transport::Channel;
-
 use crate::streaming_fast::block_client::fetch_client::FetchClient;
 use crate::streaming_fast::block_client::single_block_request::BlockNumber;
-use crate::streaming_fast::eth;
-use crate::streaming_fast::streaming_config::Chain;
-
-pub(crate) async fn get_latest_block_number(chain: &Chain) -> i64 {
-    let streamingfast_token = env::var("SUBSTREAMS_API_TOKEN").unwrap();
-    let token_metadata = MetadataValue::try_from(streamingfast_token.as_str()).unwrap();
+use crate::streaming_fast::streaming_config::StreamingEndpoint;
+use crate::streaming_fast::streaming_fast_utils::FromUnsignedVarint;
 
-    let mut client = FetchClient::with_interceptor(
-        Channel::builder(chain.get_endpoint()).connect_lazy(),
-        move |mut r: tonic::Request<()>| {
-            r.metadata_mut().insert("authorization", token_metadata.clone());
-            Ok(r)
-        },
-    );
+pub(crate) async fn get_latest_block_number(endpoint: &StreamingEndpoint) -> Result<i64, String> {
+    let mut client = FetchClient::with_interceptor(endpoint.get_channel(), endpoint.get_interceptor());
 
     let req = SingleBlockRequest {
         transforms: [].to_vec(),
         reference: Some(single_block_request::Reference::BlockNumber(BlockNumber{num: u64::MAX})),
     };
 
-    let response = client.block(req).await.unwrap();
-    let response_block = eth::Block::decode(response.get_ref().block.as_ref().unwrap().value.as_ref()).unwrap();
+    let response = client.block(req).await.map_err(|status| format!("Unable to fetch the latest block! Status: {:?} - {}", status.code(), status.message()))?;
+    let block_data = response.get_ref().block.as_ref().ok_or_else(|| "Latest block response is missing the block!".to_string())?.value.as_slice();
 
-    response_block.number as i64
+    Ok(get_block_number(block_data, endpoint.get_block_number_field_tag())? as i64)
+}
+
+/// Reads the block number straight out of the encoded block so that we don't need the block proto of every chain
+fn get_block_number(mut block_data: &[u8], block_number_field_tag: u32) -> Result<u64, String> {
+    while !block_data.is_empty() {
+        let tag = u64::from_unsigned_varint(&mut block_data).ok_or_else(|| "Unable to read field tag of block!".to_string())?;
+        let field_number = tag >> 3;
+        let wire_type = (tag & 0x07) as u8;
+
+        let field_length = match wire_type {
+            0 => {
+                let value = u64::from_unsigned_varint(&mut block_data).ok_or_else(|| "Unable to read varint field of block!".to_string())?;
+                if field_number == block_number_field_tag as u64 {
+                    return Ok(value);
+                }
+                continue;
+            }
+            1 => 8,
+            2 => usize::from_unsigned_varint(&mut block_data).ok_or_else(|| "Unable to read field length of block!".to_string())?,
+            5 => 4,
+            _ => return Err(format!("Unexpected wire type: {} found in block!", wire_type)),
+        };
+
+        if block_data.len() < field_length {
+            return Err(format!("Block data is smaller than the length: {} of field: {}!", field_length, field_number));
+        }
+        block_data = &block_data[field_length..];
+    }
+
+    Err(format!("Block number field: {} not found in block!", block_number_field_tag))
 }
 
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -261,4 +277,170 @@ pub mod fetch_client {
             self.inner.unary(request.into_request(), path, codec).await
         }
     }
-}
\ No newline at end of file
+}
+/// Generated server implementations.
+pub mod fetch_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with FetchServer.
+    #[async_trait]
+    pub trait Fetch: Send + Sync + 'static {
+        async fn block(
+            &self,
+            request: tonic::Request<super::SingleBlockRequest>,
+        ) -> Result<tonic::Response<super::SingleBlockResponse>, tonic::Status>;
+    }
+    #[derive(Debug)]
+    pub struct FetchServer<T: Fetch> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: Fetch> FetchServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+            where
+                F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for FetchServer<T>
+        where
+            T: Fetch,
+            B: Body + Send + 'static,
+            B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/sf.firehose.v2.Fetch/Block" => {
+                    #[allow(non_camel_case_types)]
+                    struct BlockSvc<T: Fetch>(pub Arc<T>);
+                    impl<T: Fetch> tonic::server::UnaryService<super::SingleBlockRequest>
+                    for BlockSvc<T> {
+                        type Response = super::SingleBlockResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SingleBlockRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).block(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = BlockSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        Ok(
+                            http::Response::builder()
+                                .status(200)
+                                .header("grpc-status", "12")
+                                .header("content-type", "application/grpc")
+                                .body(empty_body())
+                                .unwrap(),
+                        )
+                    })
+                }
+            }
+        }
+    }
+    impl<T: Fetch> Clone for FetchServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+            }
+        }
+    }
+    impl<T: Fetch> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone())
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: Fetch> tonic::server::NamedService for FetchServer<T> {
+        const NAME: &'static str = "sf.firehose.v2.Fetch";
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use crate::streaming_fast::block_client::get_block_number;
+    use crate::streaming_fast::eth;
+
+    #[test]
+    fn test_get_block_number() {
+        let block = eth::Block {
+            hash: vec![1; 32],
+            number: 17_000_000,
+            size: 1234,
+            ..Default::default()
+        };
+        assert_eq!(get_block_number(&block.encode_to_vec(), 3), Ok(17_000_000));
+        assert!(get_block_number(&block.encode_to_vec(), 20).is_err());
+    }
+}
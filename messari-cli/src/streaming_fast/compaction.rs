@@ -0,0 +1,285 @@
+use std::path::PathBuf;
+use parquet::schema::types::TypePtr;
+
+use crate::streaming_fast::file::{get_cursor_file_path, Location};
+use crate::streaming_fast::file_sinks::parquet::{get_parquet_file_schema, merge_parquet_files};
+use crate::streaming_fast::streaming_config::ParquetConfig;
+
+/// A parquet file in an output folder along with the block range from it's filename and it's size in bytes
+struct CompactionFile {
+    path: PathBuf,
+    start_block: i64,
+    stop_block: i64,
+    size: usize,
+    has_cursor: bool,
+}
+
+/// Files merged into a single file in the form -> (merged_file_path, merged_file_paths)
+pub(crate) type MergedFiles = (PathBuf, Vec<PathBuf>);
+
+/// Merges runs of adjacent small parquet files in the output folder (within each of it's partitions) into files of
+/// around the target size, named after the combined block range. Files only get merged with files sharing the same
+/// schema, so a run gets split wherever columns were added to the output.
+///
+/// Storage has no way of swapping files atomically, so writing the merged file is the point a merge takes effect: it's
+/// cursor gets written before it, so the merged file is never visible without it's cursor, and the files it replaces
+/// only get deleted after it. Until they are deleted (including when a compaction is interrupted) the replaced files are
+/// fully covered by the merged file, which the next compaction picks up on and deletes them
+pub(crate) async fn compact_output_folder(output_folder_path: &Location, parquet_config: &ParquetConfig, target_file_size: usize, dry_run: bool) -> Result<Vec<MergedFiles>, String> {
+    let storage = output_folder_path.get_storage();
+
+    let mut merged_files = Vec::new();
+    for files_folder_path in output_folder_path.get_files_folder_paths().await? {
+        let folder_files = storage.list_with_sizes(&files_folder_path).await?;
+        let cursor_file_paths = folder_files.iter().map(|(file_path, _)| file_path).filter(|file_path| file_path.extension().map(|extension| extension == "cursor").unwrap_or(false)).cloned().collect::<Vec<_>>();
+
+        let mut files = folder_files.into_iter().filter_map(|(file_path, size)| {
+            if file_path.extension().map(|extension| extension != "parquet").unwrap_or(true) {
+                return None;
+            }
+            let file_stem = file_path.file_stem()?.to_string_lossy().to_string();
+            let (start_block, stop_block) = file_stem.split_once('_')?;
+            Some(CompactionFile {
+                start_block: start_block.parse().ok()?,
+                stop_block: stop_block.parse().ok()?,
+                has_cursor: cursor_file_paths.contains(&get_cursor_file_path(&file_path)),
+                path: file_path,
+                size,
+            })
+        }).collect::<Vec<_>>();
+        files.sort_by_key(|file| (file.start_block, -file.stop_block));
+
+        // Leftovers of an interrupted compaction
+        let (files, covered_files): (Vec<_>, Vec<_>) = files.into_iter().fold((Vec::<CompactionFile>::new(), Vec::new()), |(mut files, mut covered_files), file| {
+            match files.last() {
+                Some(previous_file) if file.stop_block <= previous_file.stop_block => covered_files.push(file),
+                _ => files.push(file),
+            }
+            (files, covered_files)
+        });
+        if !covered_files.is_empty() {
+            println!("Deleting {} files in folder: {} already merged by a previous compaction", covered_files.len(), storage.get_display_path(&files_folder_path));
+            if !dry_run {
+                delete_files(output_folder_path, &covered_files, &cursor_file_paths).await?;
+            }
+        }
+
+        if let Some((previous_file, file)) = files.iter().zip(files.iter().skip(1)).find(|(previous_file, file)| file.start_block < previous_file.stop_block) {
+            return Err(format!("Files: {} and {} overlap! Overlapping files need to be fixed before the folder can be compacted.", storage.get_display_path(&previous_file.path), storage.get_display_path(&file.path)));
+        }
+
+        let mut file_groups = vec![Vec::new()];
+        let mut group_size = 0;
+        let mut group_schema: Option<TypePtr> = None;
+        for file in files.into_iter() {
+            if file.size >= target_file_size {
+                file_groups.push(Vec::new());
+                group_size = 0;
+                continue;
+            }
+
+            let file_data = storage.get(&file.path).await?.ok_or_else(|| format!("File: {} no longer exists!", storage.get_display_path(&file.path)))?;
+            let file_schema = get_parquet_file_schema(file_data).map_err(|error| format!("Unable to read schema of file: {}! {}", storage.get_display_path(&file.path), error))?;
+
+            // Files named after the exclusive stop block of a run are followed by a file starting at that same block
+            if let Some(previous_file) = file_groups.last().unwrap().last() {
+                let is_contiguous = file.start_block <= previous_file.stop_block + 1;
+                if !is_contiguous || group_schema.as_ref() != Some(&file_schema) {
+                    file_groups.push(Vec::new());
+                    group_size = 0;
+                }
+            }
+
+            group_size += file.size;
+            group_schema = Some(file_schema);
+            file_groups.last_mut().unwrap().push(file);
+            if group_size >= target_file_size {
+                file_groups.push(Vec::new());
+                group_size = 0;
+            }
+        }
+
+        // A merged file takes the cursor of the last file in it's group, which needs to have one for streaming to be able
+        // to resume from the merged file. So the files after the latest cursor in a group are left for a later compaction
+        // (unless none of the folder's files have cursors, in which case restarts go off the filenames instead)
+        let has_cursors = !cursor_file_paths.is_empty();
+        let file_groups = file_groups.into_iter().map(|mut file_group| {
+            if has_cursors {
+                let num_files = file_group.iter().rposition(|file| file.has_cursor).map(|file_index| file_index + 1).unwrap_or(0);
+                file_group.truncate(num_files);
+            }
+            file_group
+        }).collect::<Vec<_>>();
+
+        for file_group in file_groups.into_iter().filter(|file_group| file_group.len() > 1) {
+            let merged_file_path = files_folder_path.join(format!("{}_{}.parquet", file_group.first().unwrap().start_block, file_group.last().unwrap().stop_block));
+            println!("Merging {} files into: {}", file_group.len(), storage.get_display_path(&merged_file_path));
+            if !dry_run {
+                merge_files(output_folder_path, &file_group, &merged_file_path, &cursor_file_paths, parquet_config).await?;
+            }
+            merged_files.push((merged_file_path, file_group.into_iter().map(|file| file.path).collect()));
+        }
+    }
+
+    Ok(merged_files)
+}
+
+async fn merge_files(output_folder_path: &Location, files: &[CompactionFile], merged_file_path: &PathBuf, cursor_file_paths: &[PathBuf], parquet_config: &ParquetConfig) -> Result<(), String> {
+    let storage = output_folder_path.get_storage();
+
+    let mut files_data = Vec::new();
+    for file in files.iter() {
+        files_data.push(storage.get(&file.path).await?.ok_or_else(|| format!("File: {} no longer exists!", storage.get_display_path(&file.path)))?);
+    }
+    let merged_file_data = merge_parquet_files(files_data, parquet_config)
+        .map_err(|error| format!("Unable to merge files into: {}! {}", storage.get_display_path(merged_file_path), error))?;
+
+    // The merged file holds the same last block as the last of it's files, so streaming can still resume from it's cursor.
+    // A cursor without it's file is never read, so writing it first means the merged file shows up along with it
+    let last_cursor_file_path = get_cursor_file_path(&files.last().unwrap().path);
+    if cursor_file_paths.contains(&last_cursor_file_path) {
+        let cursor_data = storage.get(&last_cursor_file_path).await?.ok_or_else(|| format!("Cursor: {} no longer exists!", storage.get_display_path(&last_cursor_file_path)))?;
+        storage.put(&get_cursor_file_path(merged_file_path), cursor_data).await?;
+    }
+    storage.put(merged_file_path, merged_file_data).await?;
+
+    delete_files(output_folder_path, files, cursor_file_paths).await
+}
+
+/// Cursors are deleted after the files themselves, as a file without it's cursor is still restarted from correctly
+async fn delete_files(output_folder_path: &Location, files: &[CompactionFile], cursor_file_paths: &[PathBuf]) -> Result<(), String> {
+    let storage = output_folder_path.get_storage();
+
+    for file in files.iter() {
+        storage.delete(&file.path).await?;
+    }
+    for file in files.iter() {
+        let cursor_file_path = get_cursor_file_path(&file.path);
+        if cursor_file_paths.contains(&cursor_file_path) {
+            storage.delete(&cursor_file_path).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use derives::{GenRandSamples, ProtoInfo, TestData};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::streaming_fast::compaction::compact_output_folder;
+    use crate::streaming_fast::file::Location;
+    use crate::streaming_fast::file_sinks::file_sink::{BlockMetadata, FileSink};
+    use crate::streaming_fast::file_sinks::parquet::ParquetFileSink;
+    use crate::streaming_fast::storage::{LocalStorage, StorageBackend};
+    use crate::streaming_fast::streaming_config::{BlockMetadataColumns, ParquetConfig};
+    use crate::test_utils::get_temp_dir;
+
+    #[derive(TestData)]
+    pub struct CompactedRows {
+        field1: u64,
+        field2: Option<String>,
+    }
+
+    fn get_parquet_file_data(block_numbers: std::ops::Range<i64>, rng: &mut StdRng) -> Vec<u8> {
+        let mut sink = ParquetFileSink::new_with_config(CompactedRows::get_proto_structure_info(), &ParquetConfig::default(), BlockMetadataColumns::default());
+        for (sample, block_number) in CompactedRows::get_samples(10, rng).into_iter().zip(block_numbers) {
+            sink.process(&mut sample.to_proto_bytes().as_slice(), &BlockMetadata::from_block_number(block_number)).unwrap();
+        }
+        sink.make_file()
+    }
+
+    #[derive(TestData)]
+    pub struct CompactedRowsWithNewField {
+        field1: u64,
+        field2: Option<String>,
+        field3: Option<u64>,
+    }
+
+    fn get_new_field_parquet_file_data(block_numbers: std::ops::Range<i64>, rng: &mut StdRng) -> Vec<u8> {
+        let mut sink = ParquetFileSink::new_with_config(CompactedRowsWithNewField::get_proto_structure_info(), &ParquetConfig::default(), BlockMetadataColumns::default());
+        for (sample, block_number) in CompactedRowsWithNewField::get_samples(10, rng).into_iter().zip(block_numbers) {
+            sink.process(&mut sample.to_proto_bytes().as_slice(), &BlockMetadata::from_block_number(block_number)).unwrap();
+        }
+        sink.make_file()
+    }
+
+    #[tokio::test]
+    async fn test_compact_output_folder() {
+        let temp_dir = get_temp_dir();
+        let folder_path = temp_dir.path().to_path_buf();
+        let storage = LocalStorage;
+        let mut rng = StdRng::seed_from_u64(42);
+
+        // 0-29 are contiguous, 40-59 come after a gap and 59 is named after the exclusive stop block of it's run
+        for (start_block, stop_block) in [(0, 9), (10, 19), (20, 29), (40, 49), (50, 59), (59, 69), (70, 79)] {
+            storage.put(&folder_path.join(format!("{}_{}.parquet", start_block, stop_block)), get_parquet_file_data(start_block..stop_block + 1, &mut rng)).await.unwrap();
+        }
+        storage.put(&folder_path.join("20_29.cursor"), b"{\"cursor\": \"abc\", \"block_number\": 29}".to_vec()).await.unwrap();
+        storage.put(&folder_path.join("10_19.cursor"), b"{\"cursor\": \"def\", \"block_number\": 19}".to_vec()).await.unwrap();
+        // 70_79 is after the latest cursor so it gets left out of the merge
+        storage.put(&folder_path.join("59_69.cursor"), b"{\"cursor\": \"ghi\", \"block_number\": 69}".to_vec()).await.unwrap();
+
+        let location = Location::new(Arc::new(LocalStorage), folder_path.clone(), None);
+        let merged_files = compact_output_folder(&location, &ParquetConfig::default(), 1024 * 1024, true).await.unwrap();
+        assert_eq!(merged_files.iter().map(|(merged_file_path, files)| (merged_file_path.clone(), files.len())).collect::<Vec<_>>(), vec![
+            (folder_path.join("0_29.parquet"), 3),
+            (folder_path.join("40_69.parquet"), 3),
+        ]);
+        // Nothing gets changed in a dry run
+        assert_eq!(storage.list(&folder_path).await.unwrap().len(), 10);
+
+        compact_output_folder(&location, &ParquetConfig::default(), 1024 * 1024, false).await.unwrap();
+        let mut file_paths = storage.list(&folder_path).await.unwrap();
+        file_paths.sort();
+        assert_eq!(file_paths, vec![
+            folder_path.join("0_29.cursor"),
+            folder_path.join("0_29.parquet"),
+            folder_path.join("40_69.cursor"),
+            folder_path.join("40_69.parquet"),
+            folder_path.join("70_79.parquet"),
+        ]);
+
+        let merged_file_data = storage.get(&folder_path.join("0_29.parquet")).await.unwrap().unwrap();
+        let reader = SerializedFileReader::new(bytes::Bytes::from(merged_file_data)).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 30);
+        let cursor_data = storage.get(&folder_path.join("0_29.cursor")).await.unwrap().unwrap();
+        assert_eq!(cursor_data, b"{\"cursor\": \"abc\", \"block_number\": 29}".to_vec());
+
+        // Files left behind by an interrupted compaction are covered by the merged file
+        storage.put(&folder_path.join("40_49.parquet"), get_parquet_file_data(40..50, &mut rng)).await.unwrap();
+        assert!(compact_output_folder(&location, &ParquetConfig::default(), 1024 * 1024, false).await.unwrap().is_empty());
+        assert!(storage.get(&folder_path.join("40_49.parquet")).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_compaction_splits_at_schema_changes() {
+        let temp_dir = get_temp_dir();
+        let folder_path = temp_dir.path().to_path_buf();
+        let storage = LocalStorage;
+        let mut rng = StdRng::seed_from_u64(42);
+
+        // A column was added from block 20 onwards. None of the files have cursors so none get left out
+        for (start_block, stop_block) in [(0, 9), (10, 19)] {
+            storage.put(&folder_path.join(format!("{}_{}.parquet", start_block, stop_block)), get_parquet_file_data(start_block..stop_block + 1, &mut rng)).await.unwrap();
+        }
+        for (start_block, stop_block) in [(20, 29), (30, 39)] {
+            storage.put(&folder_path.join(format!("{}_{}.parquet", start_block, stop_block)), get_new_field_parquet_file_data(start_block..stop_block + 1, &mut rng)).await.unwrap();
+        }
+
+        let location = Location::new(Arc::new(LocalStorage), folder_path.clone(), None);
+        let merged_files = compact_output_folder(&location, &ParquetConfig::default(), 1024 * 1024, false).await.unwrap();
+        assert_eq!(merged_files.iter().map(|(merged_file_path, files)| (merged_file_path.clone(), files.len())).collect::<Vec<_>>(), vec![
+            (folder_path.join("0_19.parquet"), 2),
+            (folder_path.join("20_39.parquet"), 2),
+        ]);
+
+        let mut file_paths = storage.list(&folder_path).await.unwrap();
+        file_paths.sort();
+        assert_eq!(file_paths, vec![folder_path.join("0_19.parquet"), folder_path.join("20_39.parquet")]);
+    }
+}
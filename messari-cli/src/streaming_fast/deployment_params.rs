@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+
+use crate::file_modification::substreams_yaml::SubstreamsYaml;
+use crate::streaming_fast::streaming_config::{BlockMetadataColumns, Chain, ParamOverride, ParquetConfig, PipelineConfig, RetryConfig, StartBlockOverride, StorageConfig, StreamingConfig};
+
+/// A substream listed in the params file (config/params.json) along with every deployment of it
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SubstreamParams {
+    pub(crate) name: String,
+    /// Folder of the substream relative to the params file
+    pub(crate) path: String,
+    pub(crate) output_modules: Vec<String>,
+    /// Module the subgraph deployments of the substream are built from
+    #[serde(default)]
+    pub(crate) subgraph_module: Option<String>,
+    pub(crate) deployments: Vec<DeploymentParams>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DeploymentParams {
+    pub(crate) name: String,
+    pub(crate) network: String,
+    /// Module name -> param value
+    #[serde(default)]
+    pub(crate) params: BTreeMap<String, String>,
+    /// Module name -> initial block
+    pub(crate) start_blocks: BTreeMap<String, u64>,
+}
+
+pub(crate) fn read_params_file(params_path: &Path) -> Result<Vec<SubstreamParams>, String> {
+    let params_data = fs::read_to_string(params_path).map_err(|error| format!("Unable to read params file: {}! Error: {}", params_path.display(), error))?;
+    serde_json::from_str(&params_data).map_err(|error| format!("Unable to parse params file: {}! Error: {}", params_path.display(), error))
+}
+
+impl SubstreamParams {
+    /// `substreams pack` names spkgs after the package name in the manifest -> {package name}-vX.Y.Z.spkg while the
+    /// release workflow uploads them named after the substream folder -> {folder name}.spkg. Both are looked for (as
+    /// well as the name in the params file) and when more than one version is in the folder the latest one gets used
+    pub(crate) fn find_spkg(&self, substream_folder: &Path, spkg_folder: &Path) -> Result<PathBuf, String> {
+        let spkg_names = self.get_spkg_names(substream_folder);
+
+        let mut spkg_versions = Vec::new();
+        let mut unversioned_spkg = None;
+        for entry in fs::read_dir(spkg_folder).map_err(|error| format!("Unable to read spkg folder: {}! Error: {}", spkg_folder.display(), error))? {
+            let spkg_path = entry.map_err(|error| error.to_string())?.path();
+            let file_stem = match spkg_path.file_name().unwrap().to_string_lossy().strip_suffix(".spkg") {
+                Some(file_stem) => file_stem.to_string(),
+                None => continue,
+            };
+
+            for spkg_name in spkg_names.iter() {
+                if &file_stem == spkg_name {
+                    unversioned_spkg = Some(spkg_path.clone());
+                    continue;
+                }
+
+                let version = match file_stem.strip_prefix(&format!("{}-", spkg_name)) {
+                    Some(version) => version.trim_start_matches('v'),
+                    None => continue,
+                };
+                // Other substreams can share the same name prefix (eg. erc20-price and erc20-price-oracle) so anything
+                // that isn't followed by a version is skipped over
+                if let Ok(version_numbers) = version.split('.').map(|number| number.parse::<u64>()).collect::<Result<Vec<_>, _>>() {
+                    spkg_versions.push((version_numbers, spkg_path.clone()));
+                }
+            }
+        }
+
+        spkg_versions.into_iter().max_by(|(version1, _), (version2, _)| version1.cmp(version2)).map(|(_, spkg_path)| spkg_path)
+            .or(unversioned_spkg)
+            .ok_or_else(|| format!("No spkg found for substream: {} in folder: {}! Expecting a file named: {{name}}-vX.Y.Z.spkg or {{name}}.spkg where name is one of: {}", self.name, spkg_folder.display(), spkg_names.join(", ")))
+    }
+
+    /// Names the spkg of the substream can go by -> the name in the params file, the package name in the manifest and
+    /// the name of the substream folder
+    fn get_spkg_names(&self, substream_folder: &Path) -> Vec<String> {
+        let mut spkg_names = vec![self.name.clone()];
+
+        let substreams_yaml_path = substream_folder.join("substreams.yaml");
+        if substreams_yaml_path.exists() {
+            spkg_names.push(SubstreamsYaml::load_from_file(&substreams_yaml_path).get_substream_name());
+        }
+        if let Some(folder_name) = substream_folder.file_name() {
+            spkg_names.push(folder_name.to_string_lossy().to_string());
+        }
+
+        spkg_names.sort();
+        spkg_names.dedup();
+        spkg_names
+    }
+}
+
+impl DeploymentParams {
+    /// Translates the deployment into the config the process command takes, the same way the configs uploaded for
+    /// dagster get built
+    pub(crate) fn get_streaming_config(&self, output_modules: &[String]) -> Result<StreamingConfig, String> {
+        if Chain::from_name(&self.network, &[]).is_none() {
+            return Err(format!("Network: {} of deployment: {} is not one of the built in chains!", self.network, self.name));
+        }
+
+        Ok(StreamingConfig {
+            name: Some(self.name.clone()),
+            output_module: None,
+            output_modules: output_modules.to_vec(),
+            substream_name_override: None,
+            chain_override: Some(self.network.clone()),
+            chains: vec![],
+            endpoint_override: None,
+            param_overrides: self.params.iter().map(|(module, value)| ParamOverride {
+                module: module.clone(),
+                value: value.clone(),
+            }).collect(),
+            start_block_overrides: self.start_blocks.iter().map(|(module, block_number)| StartBlockOverride {
+                module: module.clone(),
+                block_number: *block_number,
+            }).collect(),
+            parquet: ParquetConfig::default(),
+            storage: StorageConfig::default(),
+            partition: None,
+            retry: RetryConfig::default(),
+            pipeline: PipelineConfig::default(),
+            block_metadata_columns: BlockMetadataColumns::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::streaming_fast::deployment_params::SubstreamParams;
+    use crate::test_utils::get_temp_dir;
+
+    fn get_test_params() -> Vec<SubstreamParams> {
+        serde_json::from_value(serde_json::json!([
+            {
+                "name": "aave-v2",
+                "path": "../aave-v2",
+                "outputModules": ["map_output"],
+                "subgraphModule": "map_entity_changes",
+                "deployments": [
+                    {
+                        "name": "aave-v2-polygon",
+                        "network": "polygon",
+                        "params": { "store_observed_contracts": "0x7d27;0x311b" },
+                        "startBlocks": { "store_observed_contracts": 11362579, "map_output": 11362579 }
+                    },
+                    {
+                        "name": "aave-v2-unknown",
+                        "network": "unknown",
+                        "startBlocks": {}
+                    }
+                ]
+            }
+        ])).unwrap()
+    }
+
+    #[test]
+    fn test_deployment_to_streaming_config() {
+        let substream_params = get_test_params();
+        let substream = &substream_params[0];
+
+        let config = substream.deployments[0].get_streaming_config(&substream.output_modules).unwrap();
+        let config_json = serde_json::to_value(&config).unwrap();
+        assert_eq!(config_json["name"], "aave-v2-polygon");
+        assert_eq!(config_json["output_modules"], serde_json::json!(["map_output"]));
+        assert_eq!(config_json["chain_override"], "polygon");
+        assert_eq!(config_json["param_overrides"], serde_json::json!([{ "module": "store_observed_contracts", "value": "0x7d27;0x311b" }]));
+        assert_eq!(config_json["start_block_overrides"], serde_json::json!([
+            { "module": "map_output", "block_number": 11362579 },
+            { "module": "store_observed_contracts", "block_number": 11362579 },
+        ]));
+
+        assert!(substream.deployments[1].get_streaming_config(&substream.output_modules).is_err());
+    }
+
+    #[test]
+    fn test_find_latest_spkg() {
+        let temp_dir = get_temp_dir();
+        let spkg_folder = temp_dir.path();
+        for file_name in ["aave-v2-v0.9.0.spkg", "aave-v2-v0.10.1.spkg", "aave-v2-oracle-v1.0.0.spkg", "aave-v2-v0.11.0.yaml"] {
+            fs::write(spkg_folder.join(file_name), b"").unwrap();
+        }
+
+        let substream_params = get_test_params();
+        let substream_folder = spkg_folder.join("aave-v2");
+        assert_eq!(substream_params[0].find_spkg(&substream_folder, spkg_folder).unwrap(), spkg_folder.join("aave-v2-v0.10.1.spkg"));
+
+        fs::remove_file(spkg_folder.join("aave-v2-v0.9.0.spkg")).unwrap();
+        fs::remove_file(spkg_folder.join("aave-v2-v0.10.1.spkg")).unwrap();
+        assert!(substream_params[0].find_spkg(&substream_folder, spkg_folder).is_err());
+    }
+
+    #[test]
+    fn test_find_spkg_with_repo_naming() {
+        let temp_dir = get_temp_dir();
+        let params_folder = temp_dir.path();
+        let substream_folder = params_folder.join("aave-v2");
+        fs::create_dir_all(&substream_folder).unwrap();
+        fs::write(substream_folder.join("substreams.yaml"), "specVersion: v0.1.0\npackage:\n  name: aave_v2\n  version: v0.2.0\n").unwrap();
+
+        let substream_params = get_test_params();
+
+        // Uploaded by the release workflow -> named after the substream folder
+        fs::write(substream_folder.join("aave-v2.spkg"), b"").unwrap();
+        assert_eq!(substream_params[0].find_spkg(&substream_folder, &substream_folder).unwrap(), substream_folder.join("aave-v2.spkg"));
+
+        // Built with substreams pack -> named after the package name in the manifest
+        for file_name in ["aave_v2-v0.1.0.spkg", "aave_v2-v0.2.0.spkg", "aave_v2_oracle-v1.0.0.spkg"] {
+            fs::write(substream_folder.join(file_name), b"").unwrap();
+        }
+        assert_eq!(substream_params[0].find_spkg(&substream_folder, &substream_folder).unwrap(), substream_folder.join("aave_v2-v0.2.0.spkg"));
+
+        let eth_supply_folder = params_folder.join("eth-supply");
+        fs::create_dir_all(&eth_supply_folder).unwrap();
+        fs::write(eth_supply_folder.join("substreams.yaml"), "specVersion: v0.1.0\npackage:\n  name: eth_supply\n  version: v0.1.0\n").unwrap();
+        fs::write(eth_supply_folder.join("eth_supply-v0.1.0.spkg"), b"").unwrap();
+        assert_eq!(substream_params[0].find_spkg(&eth_supply_folder, &eth_supply_folder).unwrap(), eth_supply_folder.join("eth_supply-v0.1.0.spkg"));
+    }
+}
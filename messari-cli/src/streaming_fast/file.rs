@@ -1,14 +1,16 @@
-use std::fs;
 use std::path::PathBuf;
-use tokio::fs::File as TokioFile;
-use tokio::io::AsyncWriteExt;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
 
 use crate::streaming_fast::process_substream::EncodingType;
+use crate::streaming_fast::storage::StorageBackend;
+use crate::streaming_fast::streaming_config::PartitionScheme;
 use crate::streaming_fast::streaming_fast_utils::get_file_size_string;
 
 pub(crate) struct File {
     file_data: Vec<u8>,
-    output_location: Location
+    output_location: Location,
+    cursor_info: Option<CursorInfo>,
 }
 
 impl File {
@@ -16,66 +18,113 @@ impl File {
         File {
             file_data,
             output_location,
+            cursor_info: None,
         }
     }
 
-    pub(crate) async fn save(self) {
-        match self.output_location {
-            Location::DataWarehouse(file_path, bucket_name) => {
-                let region = "us-west-2";
-                let file_data_len = self.file_data.len();
-
-                aws_sdk_s3::Client::new(&aws_config::from_env().region(region).load().await)
-                    .put_object()
-                    .bucket(bucket_name)
-                    .key(file_path.to_str().unwrap())
-                    .body(self.file_data.into())
-                    .send()
-                    .await
-                    .unwrap();
-
-                println!("S3 file uploaded!\nFilesize: {}B, Prefix: {}", get_file_size_string(file_data_len), file_path.to_string_lossy());
-            }
-            Location::Local(file_path) => {
-                let mut file = TokioFile::create(&file_path).await.unwrap();
-                file.write_all(&self.file_data).await.unwrap();
-                println!("Local file written!\nFilesize: {}B, Filepath: {}", get_file_size_string(self.file_data.len()), file_path.to_string_lossy());
-            }
-        }
+    /// Attaches the cursor of the last block contained in the file so that it gets persisted alongside it
+    pub(crate) fn set_cursor_info(&mut self, cursor_info: CursorInfo) {
+        self.cursor_info = Some(cursor_info);
+    }
+
+    /// Saves the file data, handing back it's cursor sidecar (if it has one) to be saved afterwards. This way the
+    /// caller can hold the cursor back until the files before this one are saved too, so that we never resume past
+    /// data that wasn't saved
+    pub(crate) async fn save(self) -> Result<Option<CursorFile>, String> {
+        let storage = &self.output_location.storage;
+        let file_path = &self.output_location.path;
+        let file_data_len = self.file_data.len();
+
+        storage.put(file_path, self.file_data).await?;
+        println!("File saved!\nFilesize: {}B, Filepath: {}", get_file_size_string(file_data_len), storage.get_display_path(file_path));
+
+        Ok(self.cursor_info.map(|cursor_info| CursorFile {
+            cursor_data: serde_json::to_vec(&cursor_info).unwrap(),
+            output_location: Location::new(storage.clone(), get_cursor_file_path(file_path), None),
+        }))
     }
 }
 
+pub(crate) struct CursorFile {
+    cursor_data: Vec<u8>,
+    output_location: Location,
+}
+
+impl CursorFile {
+    pub(crate) async fn save(self) -> Result<(), String> {
+        self.output_location.storage.put(&self.output_location.path, self.cursor_data).await
+    }
+}
+
+/// Cursor of the last block written to a file along with that block's number. This gets stored in a
+/// sidecar file next to the file it belongs to (eg. startBlock_stopBlock.cursor) so that streaming can resume from it
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct CursorInfo {
+    pub(crate) cursor: String,
+    pub(crate) block_number: i64,
+}
+
+/// Returns the path of the cursor sidecar belonging to the given file path
+pub(crate) fn get_cursor_file_path(file_path: &PathBuf) -> PathBuf {
+    file_path.with_extension("cursor")
+}
+
+/// A path within the storage backend it belongs to
 #[derive(Clone)]
-pub(crate) enum Location {
-    DataWarehouse(PathBuf, String),
-    Local(PathBuf)
+pub(crate) struct Location {
+    path: PathBuf,
+    storage: Arc<dyn StorageBackend>,
+    partition_scheme: Option<PartitionScheme>,
 }
 
 impl Location {
-    pub(crate) fn new(location_type: LocationType, path: PathBuf, bucket_name: Option<String>) -> Location {
-        match location_type {
-            LocationType::DataWarehouse => Location::DataWarehouse(path, bucket_name.unwrap()),
-            LocationType::Local => {
-                fs::create_dir_all(&path).unwrap();
-                Location::Local(path)
-            }
+    pub(crate) fn new(storage: Arc<dyn StorageBackend>, path: PathBuf, partition_scheme: Option<PartitionScheme>) -> Location {
+        Location {
+            path,
+            storage,
+            partition_scheme,
         }
     }
 
-    pub(crate) fn get_file_location(&self, first_block_number: i64, last_block_number: i64, encoding_type: &EncodingType) -> Location {
-        let filename = match encoding_type {
-            EncodingType::Parquet => format!("{}_{}.parquet", first_block_number, last_block_number)
+    /// Name of the partition folder the given block belongs to. None if the location isn't partitioned
+    pub(crate) fn get_partition_folder_name(&self, block_number: i64, block_timestamp: Option<i64>) -> Result<Option<String>, String> {
+        self.partition_scheme.as_ref().map(|partition_scheme| partition_scheme.get_partition_folder_name(block_number, block_timestamp)).transpose()
+    }
+
+    pub(crate) fn get_file_location(&self, first_block_number: i64, last_block_number: i64, partition_folder_name: Option<&str>, encoding_type: &EncodingType) -> Location {
+        let filename = format!("{}_{}.{}", first_block_number, last_block_number, encoding_type.get_file_extension());
+
+        let folder_path = match partition_folder_name {
+            Some(partition_folder_name) => self.path.join(partition_folder_name),
+            None => self.path.clone(),
         };
 
-        match self {
-            Location::DataWarehouse(base_path, bucket_name) => Location::DataWarehouse(base_path.join(filename), bucket_name.to_string()),
-            Location::Local(base_path) => Location::Local(base_path.join(filename))
+        Location::new(self.storage.clone(), folder_path.join(filename), None)
+    }
+
+    /// Returns the folders that files get written to, starting with the one holding the most recent files. This is
+    /// just the location itself unless it's partitioned
+    pub(crate) async fn get_files_folder_paths(&self) -> Result<Vec<PathBuf>, String> {
+        match &self.partition_scheme {
+            Some(partition_scheme) => {
+                let partition_folders = self.storage.list_folders(&self.path).await?;
+                Ok(partition_scheme.sort_partition_folders(partition_folders))
+            }
+            None => Ok(vec![self.path.clone()]),
         }
     }
+
+    pub(crate) fn get_path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub(crate) fn get_storage(&self) -> &Arc<dyn StorageBackend> {
+        &self.storage
+    }
 }
 
 #[derive(Clone)]
 pub(crate) enum LocationType {
     DataWarehouse,
     Local
-}
\ No newline at end of file
+}
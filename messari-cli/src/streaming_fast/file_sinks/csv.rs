@@ -0,0 +1,333 @@
+use std::mem;
+use derives::proto_structure_info::{FieldInfo, FieldSpecification, FieldType, MessageInfo};
+use serde_json::{Map, Value};
+
+use crate::streaming_fast::file_sinks::file_sink::{BlockMetadata, FileSink};
+use crate::streaming_fast::file_sinks::helpers::field_naming::get_flattened_field_name;
+use crate::streaming_fast::file_sinks::helpers::json_decoder::JsonDecoder;
+use crate::streaming_fast::streaming_config::{BlockMetadataColumns, ParquetConfig};
+
+/// Defines how repeated fields get written out given that a csv cell can only hold a single value
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum RepeatedFieldPolicy {
+    /// The whole list gets written to a single cell as a json array
+    JsonCell,
+    /// Each value in the list gets it's own row. If a message has more than one repeated field then their values get
+    /// lined up by index, so the nth row holds the nth value of each list (left empty once a shorter list runs out).
+    /// Non repeated fields get repeated on every row. Empty lists still get a row so that no block data is dropped
+    Explode,
+}
+
+pub(crate) struct CsvFileSink {
+    decoder: JsonDecoder,
+    message_info: MessageInfo,
+    repeated_field_policy: RepeatedFieldPolicy,
+    struct_is_required: bool,
+    block_metadata_columns: BlockMetadataColumns,
+    file_size_threshold: usize,
+    header: Vec<u8>,
+    file_data: Vec<u8>,
+}
+
+impl CsvFileSink {
+    pub(crate) fn new_with_config(output_type_info: MessageInfo, repeated_field_policy: RepeatedFieldPolicy, file_size_threshold: usize, block_metadata_columns: BlockMetadataColumns) -> Self {
+        let struct_is_required = output_type_info.field_specification == FieldSpecification::Required;
+
+        // block_number (along with the other block metadata) is the primary key for our data so we make sure it always comes first
+        let mut column_names = block_metadata_columns.get_column_names().into_iter().map(|column_name| column_name.to_string()).collect::<Vec<_>>();
+        add_column_names(&output_type_info, &mut Vec::new(), repeated_field_policy, &mut column_names);
+
+        let mut header = Vec::new();
+        write_row(&mut header, &column_names);
+
+        CsvFileSink {
+            decoder: JsonDecoder::new(output_type_info.clone()),
+            message_info: output_type_info,
+            repeated_field_policy,
+            struct_is_required,
+            block_metadata_columns,
+            file_size_threshold,
+            header,
+            file_data: Vec::new(),
+        }
+    }
+
+    fn get_rows(&self, values: &Map<String, Value>) -> Vec<Vec<String>> {
+        get_message_rows(&self.message_info, values, self.repeated_field_policy).0
+    }
+}
+
+impl FileSink for CsvFileSink {
+    fn new(output_type_info: MessageInfo) -> Self {
+        CsvFileSink::new_with_config(output_type_info, RepeatedFieldPolicy::JsonCell, ParquetConfig::default().file_size_threshold, BlockMetadataColumns::default())
+    }
+
+    fn process(&mut self, proto_data: &mut &[u8], block_metadata: &BlockMetadata) -> Result<(), String> {
+        let values = if proto_data.is_empty() {
+            if !self.struct_is_required {
+                return Ok(());
+            }
+            self.decoder.get_default_values()?
+        } else {
+            self.decoder.decode(proto_data)?
+        };
+
+        if self.file_data.is_empty() {
+            self.file_data.extend_from_slice(&self.header);
+        }
+
+        let block_metadata_cells = block_metadata.get_column_values(&self.block_metadata_columns)?.into_iter().map(|(_, value)| get_cell(&value)).collect::<Vec<_>>();
+        for row in self.get_rows(&values) {
+            let mut cells = block_metadata_cells.clone();
+            cells.extend(row);
+            write_row(&mut self.file_data, &cells);
+        }
+
+        Ok(())
+    }
+
+    fn is_full(&self) -> bool {
+        self.file_data.len() > self.file_size_threshold
+    }
+
+    fn make_file(&mut self) -> Vec<u8> {
+        mem::take(&mut self.file_data)
+    }
+}
+
+/// Nested message fields are flattened out into a column per field unless they are repeated and written as json cells
+fn is_flattened(field_info: &FieldInfo, repeated_field_policy: RepeatedFieldPolicy) -> bool {
+    matches!(field_info.field_type, FieldType::Message(_)) && !(is_repeated(field_info) && repeated_field_policy == RepeatedFieldPolicy::JsonCell)
+}
+
+fn is_repeated(field_info: &FieldInfo) -> bool {
+    matches!(field_info.field_specification, FieldSpecification::Repeated | FieldSpecification::Packed)
+}
+
+fn add_column_names(message_info: &MessageInfo, hierarchy_trace: &mut Vec<String>, repeated_field_policy: RepeatedFieldPolicy, column_names: &mut Vec<String>) {
+    for field_info in message_info.fields.iter() {
+        match &field_info.field_type {
+            FieldType::Message(field_message_info) if is_flattened(field_info, repeated_field_policy) => {
+                hierarchy_trace.push(field_info.field_name.clone());
+                add_column_names(field_message_info, hierarchy_trace, repeated_field_policy, column_names);
+                hierarchy_trace.pop();
+            }
+            _ => column_names.push(get_flattened_field_name(hierarchy_trace, &field_info.field_name)),
+        }
+    }
+}
+
+fn get_num_columns(message_info: &MessageInfo, repeated_field_policy: RepeatedFieldPolicy) -> usize {
+    let mut column_names = Vec::new();
+    add_column_names(message_info, &mut Vec::new(), repeated_field_policy, &mut column_names);
+    column_names.len()
+}
+
+/// Returns the cells for each row a message gets written out to, in the same order as the columns from add_column_names,
+/// along with whether the rows were exploded out of a repeated field
+fn get_message_rows(message_info: &MessageInfo, values: &Map<String, Value>, repeated_field_policy: RepeatedFieldPolicy) -> (Vec<Vec<String>>, bool) {
+    let field_rows = message_info.fields.iter().map(|field_info| {
+        let value = values.get(&field_info.field_name).unwrap_or(&Value::Null);
+        get_field_rows(field_info, value, repeated_field_policy)
+    }).collect::<Vec<_>>();
+
+    // Exploded fields get lined up by index while everything else is a single row that gets repeated on every row
+    let num_rows = field_rows.iter().filter(|(_, exploded)| *exploded).map(|(rows, _)| rows.len()).max().unwrap_or_default().max(1);
+    let exploded = field_rows.iter().any(|(_, exploded)| *exploded);
+
+    let mut rows = vec![Vec::new(); num_rows];
+    for (field_info, (field_rows, field_exploded)) in message_info.fields.iter().zip(field_rows) {
+        for (row_index, row) in rows.iter_mut().enumerate() {
+            match field_rows.get(if field_exploded { row_index } else { 0 }) {
+                Some(field_row) => row.extend(field_row.iter().cloned()),
+                None => row.extend(get_empty_cells(field_info, repeated_field_policy)),
+            }
+        }
+    }
+
+    (rows, exploded)
+}
+
+fn get_field_rows(field_info: &FieldInfo, value: &Value, repeated_field_policy: RepeatedFieldPolicy) -> (Vec<Vec<String>>, bool) {
+    let flattened = is_flattened(field_info, repeated_field_policy);
+
+    if is_repeated(field_info) && repeated_field_policy == RepeatedFieldPolicy::Explode {
+        let values = value.as_array().map(|values| values.as_slice()).unwrap_or_default();
+        let rows = values.iter().flat_map(|value| get_single_value_rows(field_info, value, flattened, repeated_field_policy).0).collect();
+        return (rows, true);
+    }
+
+    get_single_value_rows(field_info, value, flattened, repeated_field_policy)
+}
+
+fn get_single_value_rows(field_info: &FieldInfo, value: &Value, flattened: bool, repeated_field_policy: RepeatedFieldPolicy) -> (Vec<Vec<String>>, bool) {
+    match (&field_info.field_type, value) {
+        (FieldType::Message(message_info), Value::Object(values)) if flattened => get_message_rows(message_info, values, repeated_field_policy),
+        _ if flattened => (vec![get_empty_cells(field_info, repeated_field_policy)], false),
+        _ => (vec![vec![get_cell(value)]], false),
+    }
+}
+
+fn get_empty_cells(field_info: &FieldInfo, repeated_field_policy: RepeatedFieldPolicy) -> Vec<String> {
+    match &field_info.field_type {
+        FieldType::Message(message_info) if is_flattened(field_info, repeated_field_policy) => vec![String::new(); get_num_columns(message_info, repeated_field_policy)],
+        _ => vec![String::new()],
+    }
+}
+
+fn get_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(value) => value.clone(),
+        Value::Bool(_) | Value::Number(_) => value.to_string(),
+        Value::Array(_) | Value::Object(_) => serde_json::to_string(value).unwrap(),
+    }
+}
+
+fn write_row(data: &mut Vec<u8>, cells: &[String]) {
+    for (i, cell) in cells.iter().enumerate() {
+        if i > 0 {
+            data.push(b',');
+        }
+
+        // As per RFC 4180, cells containing separators, quotes or line breaks get quoted with any quotes doubled up
+        if cell.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+            data.push(b'"');
+            data.extend_from_slice(cell.replace('"', "\"\"").as_bytes());
+            data.push(b'"');
+        } else {
+            data.extend_from_slice(cell.as_bytes());
+        }
+    }
+    data.extend_from_slice(b"\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use derives::{GenRandSamples, ProtoInfo, TestData};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::streaming_fast::file_sinks::csv::{CsvFileSink, RepeatedFieldPolicy};
+    use crate::streaming_fast::file_sinks::file_sink::{BlockMetadata, FileSink};
+    use crate::streaming_fast::streaming_config::{BlockMetadataColumns, ParquetConfig};
+
+    #[test]
+    fn test_repeated_field_policies() {
+        #[derive(TestData)]
+        pub struct NestedStruct {
+            field1: u64,
+            field2: Vec<String>,
+        }
+
+        #[derive(TestData)]
+        pub struct RepeatedFields {
+            field1: String,
+            field2: Vec<u64>,
+            field3: NestedStruct,
+        }
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let test_data = RepeatedFields::get_samples(50, &mut rng);
+
+        let json_cell_sink = CsvFileSink::new_with_config(RepeatedFields::get_proto_structure_info(), RepeatedFieldPolicy::JsonCell, ParquetConfig::default().file_size_threshold, BlockMetadataColumns::default());
+        let explode_sink = CsvFileSink::new_with_config(RepeatedFields::get_proto_structure_info(), RepeatedFieldPolicy::Explode, ParquetConfig::default().file_size_threshold, BlockMetadataColumns::default());
+        assert_eq!(json_cell_sink.header, b"block_number,field1,field2,field3__field1,field3__field2\r\n");
+        assert_eq!(explode_sink.header, json_cell_sink.header);
+
+        for test_datum in test_data {
+            let bytes: Vec<u8> = test_datum.to_proto_bytes();
+            let values = json_cell_sink.decoder.decode(&mut bytes.as_slice()).unwrap();
+
+            let json_cell_rows = json_cell_sink.get_rows(&values);
+            assert_eq!(json_cell_rows.len(), 1);
+            assert_eq!(json_cell_rows[0][0], test_datum.field1);
+            assert_eq!(json_cell_rows[0][1], serde_json::to_string(&test_datum.field2).unwrap());
+            assert_eq!(json_cell_rows[0][2], test_datum.field3.field1.to_string());
+            assert_eq!(json_cell_rows[0][3], serde_json::to_string(&test_datum.field3.field2).unwrap());
+
+            let explode_rows = explode_sink.get_rows(&values);
+            assert_eq!(explode_rows.len(), test_datum.field2.len().max(test_datum.field3.field2.len()).max(1));
+            for (row_index, row) in explode_rows.iter().enumerate() {
+                assert_eq!(row.len(), 4);
+                assert_eq!(row[0], test_datum.field1);
+                assert_eq!(row[1], test_datum.field2.get(row_index).map(|value| value.to_string()).unwrap_or_default());
+                assert_eq!(row[2], test_datum.field3.field1.to_string());
+                assert_eq!(row[3], test_datum.field3.field2.get(row_index).cloned().unwrap_or_default());
+            }
+        }
+    }
+
+    #[test]
+    fn test_explode_lines_up_repeated_fields_by_index() {
+        #[derive(TestData)]
+        pub struct TwoRepeatedFields {
+            field1: String,
+            field2: Vec<u64>,
+            field3: Vec<String>,
+        }
+
+        let mut sink = CsvFileSink::new_with_config(TwoRepeatedFields::get_proto_structure_info(), RepeatedFieldPolicy::Explode, ParquetConfig::default().file_size_threshold, BlockMetadataColumns::default());
+
+        let bytes = TwoRepeatedFields {
+            field1: "value".to_string(),
+            field2: vec![1, 2, 3],
+            field3: vec!["a".to_string(), "b".to_string()],
+        }.to_proto_bytes();
+        sink.process(&mut bytes.as_slice(), &BlockMetadata::from_block_number(10)).unwrap();
+
+        let bytes = TwoRepeatedFields {
+            field1: "empty".to_string(),
+            field2: Vec::new(),
+            field3: Vec::new(),
+        }.to_proto_bytes();
+        sink.process(&mut bytes.as_slice(), &BlockMetadata::from_block_number(11)).unwrap();
+
+        assert_eq!(sink.make_file(), b"block_number,field1,field2,field3\r\n10,value,1,a\r\n10,value,2,b\r\n10,value,3,\r\n11,empty,,\r\n");
+    }
+
+    #[test]
+    fn test_block_metadata_columns() {
+        #[derive(TestData)]
+        pub struct SimpleStruct {
+            field1: String,
+        }
+
+        let block_metadata_columns = BlockMetadataColumns {
+            block_hash: true,
+            block_timestamp: false,
+            row_index: true,
+        };
+        let mut sink = CsvFileSink::new_with_config(SimpleStruct::get_proto_structure_info(), RepeatedFieldPolicy::JsonCell, ParquetConfig::default().file_size_threshold, block_metadata_columns);
+
+        let bytes = SimpleStruct { field1: "value".to_string() }.to_proto_bytes();
+        let block_metadata = BlockMetadata {
+            block_number: 10,
+            block_hash: "abcd".to_string(),
+            block_timestamp: Some(1_700_000_000),
+            row_index: 2,
+        };
+        sink.process(&mut bytes.as_slice(), &block_metadata).unwrap();
+
+        assert_eq!(sink.make_file(), b"block_number,block_hash,row_index,field1\r\n10,abcd,2,value\r\n");
+    }
+
+    #[test]
+    fn test_configured_file_size_threshold() {
+        #[derive(TestData)]
+        pub struct SimpleStruct {
+            field1: String,
+        }
+
+        let mut sink = CsvFileSink::new_with_config(SimpleStruct::get_proto_structure_info(), RepeatedFieldPolicy::JsonCell, 48, BlockMetadataColumns::default());
+        assert!(!sink.is_full());
+
+        let bytes = SimpleStruct { field1: "value".to_string() }.to_proto_bytes();
+        sink.process(&mut bytes.as_slice(), &BlockMetadata::from_block_number(1)).unwrap();
+        assert!(!sink.is_full());
+        for block_number in 2..5 {
+            sink.process(&mut bytes.as_slice(), &BlockMetadata::from_block_number(block_number)).unwrap();
+        }
+        assert!(sink.is_full());
+    }
+}
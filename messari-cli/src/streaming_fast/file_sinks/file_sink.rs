@@ -1,12 +1,59 @@
 use derives::proto_structure_info::MessageInfo;
+use serde_json::Value;
+
+use crate::streaming_fast::streaming_config::BlockMetadataColumns;
 
 pub(crate) trait FileSink: Send + Sync {
     /// Initialises itself from a message type field
     fn new(output_type_info: MessageInfo) -> Self where Self: Sized;
 
-    /// If during a process of a new block output a the building file reaches the right size then it will return the file content
-    fn process(&mut self, proto_data: &mut &[u8], block_number: i64) -> Result<Option<Vec<u8>>, String>;
+    /// Adds the output of a block (or one of it's items) to the file being built
+    fn process(&mut self, proto_data: &mut &[u8], block_metadata: &BlockMetadata) -> Result<(), String>;
+
+    /// Whether the file being built has reached the right size. Only checked once the whole output of a block has been
+    /// processed so that the rows of a block never get split across files
+    fn is_full(&self) -> bool;
 
     /// Essentially flushes whatever data the encoder currently has into a file
     fn make_file(&mut self) -> Vec<u8>;
-}
\ No newline at end of file
+}
+
+/// Info on the block (taken from it's clock) that a row of module output belongs to
+#[derive(Clone, Debug, Default)]
+pub(crate) struct BlockMetadata {
+    pub(crate) block_number: i64,
+    pub(crate) block_hash: String,
+    /// Unix timestamp in seconds. None if the block's clock has no timestamp
+    pub(crate) block_timestamp: Option<i64>,
+    pub(crate) row_index: i64,
+}
+
+impl BlockMetadata {
+    /// Values for each of the block metadata columns, in the same order as BlockMetadataColumns::get_column_names
+    pub(crate) fn get_column_values(&self, block_metadata_columns: &BlockMetadataColumns) -> Result<Vec<(&'static str, Value)>, String> {
+        let mut column_values = vec![("block_number", Value::from(self.block_number))];
+        if block_metadata_columns.block_hash {
+            column_values.push(("block_hash", Value::from(self.block_hash.clone())));
+        }
+        if block_metadata_columns.block_timestamp {
+            column_values.push(("block_timestamp", Value::from(self.get_block_timestamp()?)));
+        }
+        if block_metadata_columns.row_index {
+            column_values.push(("row_index", Value::from(self.row_index)));
+        }
+        Ok(column_values)
+    }
+
+    /// Errors rather than falling back to a default so that a missing timestamp never gets written as 1970-01-01
+    pub(crate) fn get_block_timestamp(&self) -> Result<i64, String> {
+        self.block_timestamp.ok_or_else(|| format!("Block: {} has no timestamp to fill the block_timestamp column with!", self.block_number))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn from_block_number(block_number: i64) -> Self {
+        BlockMetadata {
+            block_number,
+            ..Default::default()
+        }
+    }
+}
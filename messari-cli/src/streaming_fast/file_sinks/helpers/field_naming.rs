@@ -0,0 +1,11 @@
+const FLATTENED_FIELD_NAME_SEPARATOR: &str = "__";
+
+/// Nested message fields get flattened out into their own columns, named by joining the names of each
+/// parent field and the field itself => parent__child
+pub(in crate::streaming_fast::file_sinks) fn get_flattened_field_name(hierarchy_trace: &[String], field_name: &str) -> String {
+    if hierarchy_trace.is_empty() {
+        field_name.to_string()
+    } else {
+        format!("{}{}{}", hierarchy_trace.join(FLATTENED_FIELD_NAME_SEPARATOR), FLATTENED_FIELD_NAME_SEPARATOR, field_name)
+    }
+}
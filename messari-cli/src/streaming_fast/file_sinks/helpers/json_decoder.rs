@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use serde_json::{Map, Number, Value};
+use derives::proto_structure_info::{FieldInfo, FieldSpecification, FieldType, MessageInfo};
+
+use crate::streaming_fast::streaming_fast_utils::FromUnsignedVarint;
+
+/// Decodes proto data into json values using the structure info of the proto message. Unlike the parquet
+/// decoders this builds up a full json object for each message rather than column by column data
+pub(in crate::streaming_fast::file_sinks) struct JsonDecoder {
+    message_info: MessageInfo,
+}
+
+impl JsonDecoder {
+    pub(in crate::streaming_fast::file_sinks) fn new(message_info: MessageInfo) -> Self {
+        JsonDecoder {
+            message_info,
+        }
+    }
+
+    pub(in crate::streaming_fast::file_sinks) fn decode(&self, data: &mut &[u8]) -> Result<Map<String, Value>, String> {
+        decode_message(data, &self.message_info, &self.message_info.type_name)
+    }
+
+    /// Returns the json object for a message where no proto data was given for any of it's fields
+    pub(in crate::streaming_fast::file_sinks) fn get_default_values(&self) -> Result<Map<String, Value>, String> {
+        decode_message(&mut [].as_slice(), &self.message_info, &self.message_info.type_name)
+    }
+}
+
+fn decode_message(data: &mut &[u8], message_info: &MessageInfo, flattened_field_name: &str) -> Result<Map<String, Value>, String> {
+    let mut values_seen: HashMap<u64, Value> = HashMap::new();
+
+    loop {
+        if data.is_empty() {
+            break;
+        }
+
+        let tag = match u64::from_unsigned_varint(data) {
+            Some(tag) => tag,
+            None => {
+                break;
+            }
+        };
+
+        let field_number = tag >> 3;
+        let wire_type = (tag & 0x07) as u8;
+
+        let field_info = match message_info.fields.iter().find(|field_info| field_info.field_number == field_number) {
+            Some(field_info) => field_info,
+            None => return Err(format!("FieldNumber: {}, is not a valid field! Struct name: {}", field_number, flattened_field_name)),
+        };
+        let field_name = format!("{}.{}", flattened_field_name, field_info.field_name);
+
+        match field_info.field_specification {
+            FieldSpecification::Required | FieldSpecification::Optional => {
+                if values_seen.contains_key(&field_number) {
+                    return Err(format!("Non repeated field: {}, seen more than once!", field_name));
+                }
+                values_seen.insert(field_number, decode_value(data, wire_type, field_info, &field_name)?);
+            }
+            FieldSpecification::Repeated | FieldSpecification::Packed => {
+                let values = values_seen.entry(field_number).or_insert_with(|| Value::Array(Vec::new()));
+                let values = values.as_array_mut().unwrap();
+                if field_info.field_specification == FieldSpecification::Packed && wire_type == 2 {
+                    let packed_values_data_size = match usize::from_unsigned_varint(data) {
+                        Some(len) => len,
+                        None => return Err(format!("Error reading encoded packed values data size when decoding proto data for field: {}! Unprocessed proto data: {:?}", field_name, data)),
+                    };
+                    if data.len() < packed_values_data_size {
+                        return Err(format!("Error with insufficient data for reading proto data for field: {}! Size of packed values data: {}B, Size of unprocessed proto data; {}B", field_name, packed_values_data_size, data.len()));
+                    }
+                    let (mut packed_values_data, remainder) = data.split_at(packed_values_data_size);
+                    *data = remainder;
+                    let packed_wire_type = get_scalar_wire_type(&field_info.field_type);
+                    while !packed_values_data.is_empty() {
+                        values.push(decode_value(&mut packed_values_data, packed_wire_type, field_info, &field_name)?);
+                    }
+                } else {
+                    values.push(decode_value(data, wire_type, field_info, &field_name)?);
+                }
+            }
+        }
+    }
+
+    let mut message = Map::new();
+    for field_info in message_info.fields.iter() {
+        let value = match values_seen.remove(&field_info.field_number) {
+            Some(value) => value,
+            None => get_null_or_default_value(field_info, &format!("{}.{}", flattened_field_name, field_info.field_name))?,
+        };
+        message.insert(field_info.field_name.clone(), value);
+    }
+
+    Ok(message)
+}
+
+/// This is triggered when the proto data does not contain a value for a given field.
+fn get_null_or_default_value(field_info: &FieldInfo, field_name: &str) -> Result<Value, String> {
+    match field_info.field_specification {
+        FieldSpecification::Optional => Ok(Value::Null),
+        FieldSpecification::Repeated | FieldSpecification::Packed => Ok(Value::Array(Vec::new())),
+        FieldSpecification::Required => {
+            let value = match &field_info.field_type {
+                FieldType::Double | FieldType::Float => Value::from(0_f64),
+                FieldType::Int64 | FieldType::Uint64 | FieldType::Int32 | FieldType::Fixed64 | FieldType::Fixed32 | FieldType::Uint32 |
+                FieldType::Sfixed32 | FieldType::Sfixed64 | FieldType::Sint32 | FieldType::Sint64 => Value::from(0),
+                FieldType::Bool => Value::Bool(false),
+                FieldType::String => Value::String(String::new()),
+                FieldType::Bytes => Value::String("0x".to_string()),
+                FieldType::Enum(_) => {
+                    // Assuming 0 to be the default enum variant value
+                    match field_info.get_enum_mappings().get(&0) {
+                        Some(default_enum_value) => Value::String(default_enum_value.clone()),
+                        None => return Err(format!("No default enum value set for field: {}! (Assuming a default value to be the 0 variant!)", field_name)),
+                    }
+                }
+                FieldType::Message(message_info) => {
+                    if !message_info.oneof_groups.is_empty() {
+                        return Err(format!("Null response given for struct with oneOf fields! Field: {}", field_name));
+                    }
+                    Value::Object(decode_message(&mut [].as_slice(), message_info, field_name)?)
+                }
+            };
+            Ok(value)
+        }
+    }
+}
+
+fn get_scalar_wire_type(field_type: &FieldType) -> u8 {
+    match field_type {
+        FieldType::Double | FieldType::Fixed64 | FieldType::Sfixed64 => 1,
+        FieldType::Float | FieldType::Fixed32 | FieldType::Sfixed32 => 5,
+        _ => 0,
+    }
+}
+
+fn decode_value(data: &mut &[u8], wire_type: u8, field_info: &FieldInfo, field_name: &str) -> Result<Value, String> {
+    macro_rules! decode_value {
+        ($field_type:literal @ $expected_wire_type:literal => $val:ident = $try_read:expr => $insert:expr) => {{
+            if $expected_wire_type != wire_type {
+                return Err(format!("Wire type read: {}, expected wire type: {}! Proto data for field: {}, type: {}, data: {:?}", wire_type, $expected_wire_type, field_name, $field_type, data));
+            }
+
+            match $try_read {
+                Some($val) => $insert,
+                None => return Err(format!("Error reading proto data for field: {}! Type: {}, data: {:?}", field_name, $field_type, data)),
+            }
+        }};
+    }
+
+    let value = match &field_info.field_type {
+        FieldType::Double => decode_value! { "Double" @ 1 => b = try_read_8_bytes(data) => float_value(f64::from_le_bytes(b)) },
+        FieldType::Float => decode_value! { "Float" @ 5 => b = try_read_4_bytes(data) => float_value(f32::from_le_bytes(b) as f64) },
+        FieldType::Int32 => decode_value! { "Int32" @ 0 => b = u64::from_unsigned_varint(data) => Value::from(b as i64 as i32) },
+        FieldType::Int64 => decode_value! { "Int64" @ 0 => b = u64::from_unsigned_varint(data) => Value::from(b as i64) },
+        FieldType::Uint32 => decode_value! { "UInt32" @ 0 => b = u64::from_unsigned_varint(data) => Value::from(b as u32) },
+        FieldType::Uint64 => decode_value! { "UInt64" @ 0 => b = u64::from_unsigned_varint(data) => Value::from(b) },
+        FieldType::Sint32 => decode_value! { "SInt32" @ 0 => b = u64::from_unsigned_varint(data) => Value::from(decode_zigzag(b) as i32) },
+        FieldType::Sint64 => decode_value! { "SInt64" @ 0 => b = u64::from_unsigned_varint(data) => Value::from(decode_zigzag(b)) },
+        FieldType::Fixed32 => decode_value! { "Fixed32" @ 5 => b = try_read_4_bytes(data) => Value::from(u32::from_le_bytes(b)) },
+        FieldType::Fixed64 => decode_value! { "Fixed64" @ 1 => b = try_read_8_bytes(data) => Value::from(u64::from_le_bytes(b)) },
+        FieldType::Sfixed32 => decode_value! { "SFixed32" @ 5 => b = try_read_4_bytes(data) => Value::from(i32::from_le_bytes(b)) },
+        FieldType::Sfixed64 => decode_value! { "SFixed64" @ 1 => b = try_read_8_bytes(data) => Value::from(i64::from_le_bytes(b)) },
+        FieldType::Bool => decode_value! { "Bool" @ 0 => b = u64::from_unsigned_varint(data) => Value::Bool(b != 0) },
+        FieldType::String => decode_value! { "String" @ 2 => b = read_length_delimited(data) => Value::String(String::from_utf8_lossy(b).to_string()) },
+        FieldType::Bytes => decode_value! { "Bytes" @ 2 => b = read_length_delimited(data) => Value::String(to_hex_string(b)) },
+        FieldType::Enum(_) => {
+            let enum_key = decode_value! { "Enum" @ 0 => b = u64::from_unsigned_varint(data) => b };
+            match field_info.get_enum_mappings().get(&enum_key) {
+                Some(enum_value) => Value::String(enum_value.clone()),
+                None => return Err(format!("Error when parsing enum value - no enum variant found for value: {}! Field: {}", enum_key, field_name)),
+            }
+        }
+        FieldType::Message(message_info) => {
+            let mut struct_data = decode_value! { "Struct" @ 2 => b = read_length_delimited(data) => b };
+            Value::Object(decode_message(&mut struct_data, message_info, field_name)?)
+        }
+    };
+
+    Ok(value)
+}
+
+fn float_value(value: f64) -> Value {
+    // Json has no representation for NaN or infinite values so these get written as nulls
+    Number::from_f64(value).map(Value::Number).unwrap_or(Value::Null)
+}
+
+fn decode_zigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn to_hex_string(bytes: &[u8]) -> String {
+    let mut hex_string = String::with_capacity(2 + bytes.len() * 2);
+    hex_string.push_str("0x");
+    for byte in bytes {
+        hex_string.push_str(&format!("{:02x}", byte));
+    }
+    hex_string
+}
+
+fn read_length_delimited<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let original = *data;
+    let len = usize::from_unsigned_varint(data)?;
+    if len > data.len() {
+        *data = original;
+        return None;
+    }
+    let (value_data, remainder) = data.split_at(len);
+    *data = remainder;
+    Some(value_data)
+}
+
+fn try_read_8_bytes(data: &mut &[u8]) -> Option<[u8; 8]> {
+    if data.len() < 8 {
+        return None;
+    }
+
+    let (value_data, remainder) = data.split_at(8);
+    *data = remainder;
+    value_data.try_into().ok()
+}
+
+fn try_read_4_bytes(data: &mut &[u8]) -> Option<[u8; 4]> {
+    if data.len() < 4 {
+        return None;
+    }
+
+    let (value_data, remainder) = data.split_at(4);
+    *data = remainder;
+    value_data.try_into().ok()
+}
@@ -1 +1,3 @@
-pub(in crate::streaming_fast::file_sinks) mod parquet;
\ No newline at end of file
+pub(in crate::streaming_fast::file_sinks) mod parquet;
+pub(in crate::streaming_fast::file_sinks) mod json_decoder;
+pub(in crate::streaming_fast::file_sinks) mod field_naming;
\ No newline at end of file
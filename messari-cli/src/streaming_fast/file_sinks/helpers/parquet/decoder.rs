@@ -5,6 +5,7 @@ use derives::proto_structure_info::FieldInfo;
 use crate::streaming_fast::file_sinks::helpers::parquet::enum_decoder::EnumDecoder;
 use crate::streaming_fast::file_sinks::helpers::parquet::field_decoder::FieldDecoder;
 use crate::streaming_fast::file_sinks::helpers::parquet::file_buffer::FileBuffer;
+use crate::streaming_fast::file_sinks::helpers::parquet::logical_type_decoder::LogicalTypeDecoder;
 use crate::streaming_fast::file_sinks::helpers::parquet::parquet_schema_builder::ParquetSchemaBuilder;
 use crate::streaming_fast::file_sinks::helpers::parquet::repetition_and_definition::{RepetitionAndDefinitionLvls, RepetitionAndDefinitionLvlStoreBuilder};
 use crate::streaming_fast::file_sinks::helpers::parquet::struct_decoder::StructDecoder;
@@ -13,22 +14,30 @@ use crate::streaming_fast::streaming_fast_utils::FromUnsignedVarint;
 pub(in crate::streaming_fast::file_sinks) enum Decoder {
     FieldDecoder(FieldDecoder),
     StructDecoder(StructDecoder),
-    EnumDecoder(EnumDecoder)
+    EnumDecoder(EnumDecoder),
+    LogicalTypeDecoder(LogicalTypeDecoder)
 }
 
 impl Decoder {
     pub(in crate::streaming_fast::file_sinks) fn new(field_info: FieldInfo, parquet_schema_builder: &mut ParquetSchemaBuilder, lvls_store_builder: &mut RepetitionAndDefinitionLvlStoreBuilder) -> Decoder {
-        if field_info.is_struct_field() {
+        if let Some(logical_type_decoder) = LogicalTypeDecoder::try_new(&field_info, parquet_schema_builder, lvls_store_builder) {
+            Decoder::LogicalTypeDecoder(logical_type_decoder)
+        } else if field_info.is_struct_field() {
             let repetition = field_info.field_specification.get_repetition();
             parquet_schema_builder.start_building_sub_group(field_info.field_name.clone());
             let (message_info, field_name) = field_info.get_struct_info();
+            let is_map_entry = message_info.is_map_entry;
 
             let decoder = Decoder::StructDecoder(StructDecoder::new(&field_name,
                                                         message_info,
                                                       parquet_schema_builder,
                                                       lvls_store_builder));
 
-            parquet_schema_builder.finish_building_sub_group(repetition);
+            if is_map_entry {
+                parquet_schema_builder.finish_building_map_sub_group();
+            } else {
+                parquet_schema_builder.finish_building_sub_group(repetition);
+            }
 
             decoder
         } else if field_info.is_enum_field() {
@@ -43,6 +52,7 @@ impl Decoder {
             Decoder::FieldDecoder(field_decoder) => field_decoder.write_data_to_parquet(row_group_writer),
             Decoder::StructDecoder(struct_decoder) => struct_decoder.write_data_to_parquet(row_group_writer),
             Decoder::EnumDecoder(enum_decoder) => enum_decoder.write_data_to_parquet(row_group_writer),
+            Decoder::LogicalTypeDecoder(logical_type_decoder) => logical_type_decoder.write_data_to_parquet(row_group_writer),
         }
     }
 
@@ -54,6 +64,9 @@ impl Decoder {
             Decoder::EnumDecoder(enum_decoder) => {
                 enum_decoder.decode(data, wire_type, uncompressed_file_size, lvls)
             }
+            Decoder::LogicalTypeDecoder(logical_type_decoder) => {
+                logical_type_decoder.decode(data, wire_type, uncompressed_file_size, lvls)
+            }
             Decoder::StructDecoder(struct_decoder) => {
                 let struct_data_length = usize::from_unsigned_varint(data).unwrap();
                 if data.len() < struct_data_length {
@@ -76,6 +89,9 @@ impl Decoder {
             Decoder::EnumDecoder(enum_decoder) => {
                 enum_decoder.push_null_or_default_value(uncompressed_file_size, lvls)
             }
+            Decoder::LogicalTypeDecoder(logical_type_decoder) => {
+                logical_type_decoder.push_null_or_default_value(uncompressed_file_size, lvls)
+            }
             Decoder::StructDecoder(struct_decoder) => struct_decoder.push_null_or_default_values(uncompressed_file_size, lvls),
         }
     }
@@ -84,6 +100,7 @@ impl Decoder {
         match self {
             Decoder::FieldDecoder(field_decoder) => field_decoder.push_null(uncompressed_file_size, lvls),
             Decoder::EnumDecoder(enum_decoder) => enum_decoder.push_null(uncompressed_file_size, lvls),
+            Decoder::LogicalTypeDecoder(logical_type_decoder) => logical_type_decoder.push_null(uncompressed_file_size, lvls),
             Decoder::StructDecoder(struct_decoder) => struct_decoder.push_nulls(uncompressed_file_size, lvls),
         }
     }
@@ -93,6 +110,7 @@ impl Decoder {
             Decoder::FieldDecoder(decoder) => decoder.get_flattened_field_name(),
             Decoder::StructDecoder(decoder) => decoder.get_flattened_field_name(),
             Decoder::EnumDecoder(decoder) => decoder.get_flattened_field_name(),
+            Decoder::LogicalTypeDecoder(decoder) => decoder.get_flattened_field_name(),
         }
     }
 }
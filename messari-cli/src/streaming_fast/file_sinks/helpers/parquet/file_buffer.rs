@@ -15,6 +15,10 @@ impl FileBuffer {
         }
     }
 
+    pub(in crate::streaming_fast::file_sinks) fn len(&self) -> usize {
+        self.file_data.lock().unwrap().len()
+    }
+
     pub(in crate::streaming_fast::file_sinks) fn get_data(self) -> Vec<u8> {
         mem::take(self.file_data.lock().unwrap().deref_mut())
     }
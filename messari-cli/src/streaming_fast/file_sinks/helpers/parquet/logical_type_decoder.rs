@@ -0,0 +1,535 @@
+use std::borrow::BorrowMut;
+use parquet::data_type::{ByteArray, ByteArrayType, FixedLenByteArray, FixedLenByteArrayType, Int64Type};
+use parquet::file::writer::SerializedRowGroupWriter;
+use derives::proto_structure_info::{FieldInfo, FieldSpecification, FieldType, MessageInfo};
+
+use crate::streaming_fast::file_sinks::helpers::parquet::file_buffer::FileBuffer;
+use crate::streaming_fast::file_sinks::helpers::parquet::parquet_schema_builder::ParquetSchemaBuilder;
+use crate::streaming_fast::file_sinks::helpers::parquet::repetition_and_definition::{RepetitionAndDefinitionLvls, RepetitionAndDefinitionLvlStore, RepetitionAndDefinitionLvlStoreBuilder};
+use crate::streaming_fast::streaming_config::BigNumberType;
+use crate::streaming_fast::streaming_fast_utils::{FromSignedVarint, FromUnsignedVarint};
+
+const ADDRESS_LENGTH: usize = 20;
+const HASH_LENGTH: usize = 32;
+
+/// Parquet column types that get used in place of the plain proto mapping for well known types
+#[derive(Clone, Debug, PartialEq)]
+pub(in crate::streaming_fast::file_sinks) enum LogicalTypeMapping {
+    /// google.protobuf.Timestamp => TIMESTAMP(MICROS)
+    Timestamp,
+    /// Messari BigInt/BigDecimal => DECIMAL(precision, scale)
+    Decimal { precision: u32, scale: u32 },
+    /// Messari BigInt/BigDecimal when the string fallback is configured
+    BigNumberString,
+    /// Addresses and hashes => FIXED_LEN_BYTE_ARRAY(length)
+    FixedLengthBinary { length: usize },
+}
+
+impl LogicalTypeMapping {
+    /// Returns the logical type mapping for the field if it's one of the well known types
+    fn from_field_info(field_info: &FieldInfo, parquet_schema_builder: &ParquetSchemaBuilder) -> Option<(LogicalTypeMapping, ValueSource)> {
+        match &field_info.field_type {
+            FieldType::Message(message_info) => {
+                if message_info.full_type_name == "google.protobuf.Timestamp" {
+                    return Some((LogicalTypeMapping::Timestamp, ValueSource::Timestamp));
+                }
+
+                if !message_info.full_type_name.starts_with("messari.") {
+                    return None;
+                }
+
+                let (wrapped_field_number, is_string) = get_wrapped_field(message_info)?;
+                let value_source = ValueSource::WrappedField { field_number: wrapped_field_number, is_string };
+                match message_info.type_name.as_str() {
+                    "BigInt" | "BigDecimal" => {
+                        let logical_type_mapping = match parquet_schema_builder.get_big_number_type() {
+                            BigNumberType::Decimal { precision, big_decimal_scale } => {
+                                let scale = if message_info.type_name == "BigInt" { 0 } else { *big_decimal_scale };
+                                LogicalTypeMapping::Decimal { precision: *precision, scale }
+                            }
+                            BigNumberType::String => LogicalTypeMapping::BigNumberString,
+                        };
+                        Some((logical_type_mapping, value_source))
+                    }
+                    "Address" if parquet_schema_builder.is_fixed_length_addresses_and_hashes_enabled() => {
+                        Some((LogicalTypeMapping::FixedLengthBinary { length: ADDRESS_LENGTH }, value_source))
+                    }
+                    _ => None,
+                }
+            }
+            FieldType::String | FieldType::Bytes if parquet_schema_builder.is_fixed_length_addresses_and_hashes_enabled() => {
+                let field_name = field_info.field_name.to_lowercase();
+                let length = if field_name.ends_with("address") {
+                    ADDRESS_LENGTH
+                } else if field_name.ends_with("hash") {
+                    HASH_LENGTH
+                } else {
+                    return None;
+                };
+                Some((LogicalTypeMapping::FixedLengthBinary { length }, ValueSource::Field { is_string: field_info.field_type == FieldType::String }))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Where the value of a logical type field is found in it's proto data
+enum ValueSource {
+    /// The field holds the value directly
+    Field { is_string: bool },
+    /// The field is a message wrapping the value in one of it's own fields
+    WrappedField { field_number: u64, is_string: bool },
+    /// google.protobuf.Timestamp message, made up of seconds and nanos fields
+    Timestamp,
+}
+
+/// Wrapper messages are expected to only have a single string or bytes field which holds the value
+fn get_wrapped_field(message_info: &MessageInfo) -> Option<(u64, bool)> {
+    if message_info.fields.len() != 1 || !message_info.fields[0].field_specification.is_optional_or_required() {
+        return None;
+    }
+
+    let field = &message_info.fields[0];
+    match field.field_type {
+        FieldType::String => Some((field.field_number, true)),
+        FieldType::Bytes => Some((field.field_number, false)),
+        _ => None,
+    }
+}
+
+pub(in crate::streaming_fast::file_sinks) struct LogicalTypeDecoder {
+    logical_type_mapping: LogicalTypeMapping,
+    value_source: ValueSource,
+    value_store: ValueStore,
+    repetition_and_definition_lvl_store: Option<RepetitionAndDefinitionLvlStore>,
+    field_specification: FieldSpecification,
+    flattened_field_name: String
+}
+
+impl LogicalTypeDecoder {
+    /// Returns None if the field doesn't have a logical type mapping, in which case it should be decoded as usual
+    pub(in crate::streaming_fast::file_sinks) fn try_new(field_info: &FieldInfo, parquet_schema_builder: &mut ParquetSchemaBuilder, lvls_store_builder: &RepetitionAndDefinitionLvlStoreBuilder) -> Option<Self> {
+        let (logical_type_mapping, value_source) = LogicalTypeMapping::from_field_info(field_info, parquet_schema_builder)?;
+
+        let repetition = field_info.field_specification.get_repetition();
+        let lvls_store = lvls_store_builder.get_store(&repetition);
+
+        let value_store = match logical_type_mapping {
+            LogicalTypeMapping::Timestamp => ValueStore::Timestamp(Vec::new()),
+            LogicalTypeMapping::Decimal { .. } | LogicalTypeMapping::FixedLengthBinary { .. } => ValueStore::FixedLenByteArray(Vec::new()),
+            LogicalTypeMapping::BigNumberString => ValueStore::String(Vec::new()),
+        };
+
+        parquet_schema_builder.add_logical_type_column_info(&field_info.field_name, &logical_type_mapping, repetition);
+        let flattened_field_name = parquet_schema_builder.get_flattened_field_name(&field_info.field_name);
+
+        Some(LogicalTypeDecoder {
+            logical_type_mapping,
+            value_source,
+            value_store,
+            repetition_and_definition_lvl_store: lvls_store,
+            field_specification: field_info.field_specification.clone(),
+            flattened_field_name
+        })
+    }
+
+    pub(in crate::streaming_fast::file_sinks) fn write_data_to_parquet(&mut self, row_group_writer: &mut SerializedRowGroupWriter<FileBuffer>) {
+        let mut serialized_column_writer = row_group_writer.next_column().unwrap().unwrap();
+
+        let (repetition_lvls, definition_lvls) = if let Some(lvls_store) = self.repetition_and_definition_lvl_store.as_ref() {
+            (lvls_store.get_repetition_lvls(), lvls_store.get_definition_lvls())
+        } else {
+            (None, None)
+        };
+
+        macro_rules! write_batch {
+            ($values_ident:ident, $value_type:ident) => {
+                {
+                    println!("Column: {}, #values: {}", self.flattened_field_name, $values_ident.len());
+
+                    serialized_column_writer.typed::<$value_type>().write_batch(
+                        $values_ident,
+                        definition_lvls,
+                        repetition_lvls
+                    ).unwrap();
+
+                    $values_ident.clear();
+                }
+            }
+        }
+
+        match self.value_store.borrow_mut() {
+            ValueStore::Timestamp(values) => write_batch!(values, Int64Type),
+            ValueStore::FixedLenByteArray(values) => write_batch!(values, FixedLenByteArrayType),
+            ValueStore::String(values) => write_batch!(values, ByteArrayType),
+        };
+
+        if let Some(repetition_and_definition_lvl_store) = self.repetition_and_definition_lvl_store.as_mut() {
+            repetition_and_definition_lvl_store.clear();
+        }
+
+        serialized_column_writer.close().unwrap();
+    }
+
+    /// This is triggered when the proto data does not contain a value for a given field.
+    pub(in crate::streaming_fast::file_sinks) fn push_null_or_default_value(&mut self, uncompressed_file_size: &mut usize, lvls: RepetitionAndDefinitionLvls) -> Result<(), String> {
+        match self.field_specification {
+            FieldSpecification::Required => {
+                self.push_value(&[], uncompressed_file_size)?;
+                if let Some(lvls_store) = self.repetition_and_definition_lvl_store.as_mut() {
+                    lvls_store.add_lvls(lvls);
+                    *uncompressed_file_size += 32;
+                }
+            },
+            _ => self.push_null(uncompressed_file_size, lvls),
+        }
+        Ok(())
+    }
+
+    pub(in crate::streaming_fast::file_sinks) fn push_null(&mut self, uncompressed_file_size: &mut usize, lvls: RepetitionAndDefinitionLvls) {
+        self.repetition_and_definition_lvl_store.as_mut().unwrap().add_lvls(lvls);
+        *uncompressed_file_size += 32;
+    }
+
+    pub(in crate::streaming_fast::file_sinks) fn decode(&mut self, data: &mut &[u8], wire_type: u8, uncompressed_file_size: &mut usize, lvls: RepetitionAndDefinitionLvls) -> Result<(), String> {
+        if wire_type != 2 {
+            return Err(format!("Wire type read: {}, expected wire type: 2! Proto data for field: {}, type: {:?}, data: {:?}", wire_type, self.flattened_field_name, self.logical_type_mapping, data));
+        }
+
+        let value_data = match read_length_delimited(data) {
+            Some(value_data) => value_data,
+            None => return Err(format!("Error reading proto data for field: {}! Type: {:?}, data: {:?}", self.flattened_field_name, self.logical_type_mapping, data)),
+        };
+        self.push_value(value_data, uncompressed_file_size)?;
+
+        match self.field_specification {
+            FieldSpecification::Required => {
+                if let Some(lvl_store) = self.repetition_and_definition_lvl_store.as_mut() {
+                    lvl_store.add_lvls(lvls);
+                    *uncompressed_file_size += 32;
+                }
+            }
+            FieldSpecification::Optional => {
+                self.repetition_and_definition_lvl_store.as_mut().unwrap().add_lvls_for_optional_field(lvls);
+                *uncompressed_file_size += 32;
+            }
+            FieldSpecification::Repeated => {
+                self.repetition_and_definition_lvl_store.as_mut().unwrap().add_lvls(lvls);
+                *uncompressed_file_size += 32;
+            }
+            FieldSpecification::Packed => unreachable!("Only length delimited fields can have a logical type mapping")
+        }
+
+        Ok(())
+    }
+
+    /// Converts the proto data for the field into it's logical type and adds it to the value store. Empty data gives the default value
+    fn push_value(&mut self, value_data: &[u8], uncompressed_file_size: &mut usize) -> Result<(), String> {
+        let (raw_value, is_string) = match self.value_source {
+            ValueSource::Timestamp => {
+                let timestamp = decode_timestamp(value_data).map_err(|error| format!("Error decoding timestamp for field: {}! {}", self.flattened_field_name, error))?;
+                if let ValueStore::Timestamp(values) = self.value_store.borrow_mut() {
+                    values.push(timestamp);
+                    *uncompressed_file_size += 64;
+                }
+                return Ok(());
+            }
+            ValueSource::Field { is_string } => (value_data, is_string),
+            ValueSource::WrappedField { field_number, is_string } => {
+                let raw_value = get_wrapped_value(value_data, field_number).map_err(|error| format!("Error decoding wrapped value for field: {}! {}", self.flattened_field_name, error))?;
+                (raw_value, is_string)
+            }
+        };
+
+        let flattened_field_name = &self.flattened_field_name;
+        let with_field_name = |error: String| format!("Error converting value for field: {}! {}", flattened_field_name, error);
+
+        match (&self.logical_type_mapping, self.value_store.borrow_mut()) {
+            (LogicalTypeMapping::Decimal { precision, scale }, ValueStore::FixedLenByteArray(values)) => {
+                let unscaled_value = if is_string {
+                    parse_decimal_string(&String::from_utf8_lossy(raw_value), *precision, *scale)
+                } else {
+                    unsigned_big_endian_to_decimal(raw_value, *precision, *scale)
+                }.map_err(with_field_name)?;
+
+                let length = get_decimal_length(*precision);
+                values.push(FixedLenByteArray::from(ByteArray::from(unscaled_value.to_be_bytes()[16-length..].to_vec())));
+                *uncompressed_file_size += 8 * length;
+            }
+            (LogicalTypeMapping::FixedLengthBinary { length }, ValueStore::FixedLenByteArray(values)) => {
+                let bytes = if is_string {
+                    parse_hex_string(&String::from_utf8_lossy(raw_value), *length)
+                } else if raw_value.is_empty() {
+                    Ok(vec![0; *length])
+                } else if raw_value.len() == *length {
+                    Ok(raw_value.to_vec())
+                } else {
+                    Err(format!("Expected {} bytes but got {}! Value: {:?}", length, raw_value.len(), raw_value))
+                }.map_err(with_field_name)?;
+
+                values.push(FixedLenByteArray::from(ByteArray::from(bytes)));
+                *uncompressed_file_size += 8 * length;
+            }
+            (LogicalTypeMapping::BigNumberString, ValueStore::String(values)) => {
+                let value = if is_string {
+                    String::from_utf8_lossy(raw_value).to_string()
+                } else {
+                    unsigned_big_endian_to_decimal_string(raw_value)
+                };
+
+                *uncompressed_file_size += value.len();
+                values.push(ByteArray::from(value.as_str()));
+            }
+            _ => unreachable!()
+        }
+
+        Ok(())
+    }
+
+    pub(in crate::streaming_fast::file_sinks) fn get_flattened_field_name(&self) -> &String {
+        &self.flattened_field_name
+    }
+}
+
+enum ValueStore {
+    Timestamp(Vec<i64>),
+    FixedLenByteArray(Vec<FixedLenByteArray>),
+    String(Vec<ByteArray>),
+}
+
+/// Number of bytes needed to store the unscaled value of a decimal with the given precision (as per the parquet spec)
+pub(in crate::streaming_fast::file_sinks) fn get_decimal_length(precision: u32) -> usize {
+    let max_unscaled_value = 10_i128.pow(precision) - 1;
+    for length in 1..16 {
+        if max_unscaled_value < (1_i128 << (8 * length - 1)) {
+            return length;
+        }
+    }
+    16
+}
+
+fn read_length_delimited<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let original = *data;
+    let len = usize::from_unsigned_varint(data)?;
+    if len > data.len() {
+        *data = original;
+        return None;
+    }
+    let (value_data, remainder) = data.split_at(len);
+    *data = remainder;
+    Some(value_data)
+}
+
+/// Skips over the value of a field that we aren't interested in
+fn skip_field(data: &mut &[u8], wire_type: u8) -> Result<(), String> {
+    let skipped = match wire_type {
+        0 => u64::from_unsigned_varint(data).is_some(),
+        1 => try_skip_bytes(data, 8),
+        2 => read_length_delimited(data).is_some(),
+        5 => try_skip_bytes(data, 4),
+        _ => false,
+    };
+
+    if skipped {
+        Ok(())
+    } else {
+        Err(format!("Unable to skip field with wire type: {}, data: {:?}", wire_type, data))
+    }
+}
+
+fn try_skip_bytes(data: &mut &[u8], num_bytes: usize) -> bool {
+    if data.len() < num_bytes {
+        return false;
+    }
+    *data = &data[num_bytes..];
+    true
+}
+
+/// Returns the (length delimited) value of the given field from a wrapper message's data. Missing values come back empty
+fn get_wrapped_value(mut data: &[u8], field_number: u64) -> Result<&[u8], String> {
+    let mut value: &[u8] = &[];
+    while !data.is_empty() {
+        let tag = u64::from_unsigned_varint(&mut data).ok_or_else(|| format!("Unable to read tag from data: {:?}", data))?;
+        let wire_type = (tag & 0x07) as u8;
+        if tag >> 3 == field_number && wire_type == 2 {
+            value = read_length_delimited(&mut data).ok_or_else(|| format!("Unable to read value from data: {:?}", data))?;
+        } else {
+            skip_field(&mut data, wire_type)?;
+        }
+    }
+    Ok(value)
+}
+
+/// Decodes a google.protobuf.Timestamp message into microseconds since the unix epoch
+fn decode_timestamp(mut data: &[u8]) -> Result<i64, String> {
+    let mut seconds = 0_i64;
+    let mut nanos = 0_i32;
+    while !data.is_empty() {
+        let tag = u64::from_unsigned_varint(&mut data).ok_or_else(|| format!("Unable to read tag from data: {:?}", data))?;
+        let wire_type = (tag & 0x07) as u8;
+        match (tag >> 3, wire_type) {
+            (1, 0) => seconds = i64::from_signed_varint(&mut data).ok_or_else(|| format!("Unable to read seconds from data: {:?}", data))?,
+            (2, 0) => nanos = i32::from_signed_varint(&mut data).ok_or_else(|| format!("Unable to read nanos from data: {:?}", data))?,
+            _ => skip_field(&mut data, wire_type)?,
+        }
+    }
+
+    seconds.checked_mul(1_000_000)
+        .and_then(|micros| micros.checked_add((nanos / 1000) as i64))
+        .ok_or_else(|| format!("Timestamp with seconds: {}, nanos: {}, is out of range!", seconds, nanos))
+}
+
+/// Parses a decimal string (eg. "-12.345" or "1.2e-5") into it's unscaled value for the given scale. Digits beyond
+/// the scale get truncated
+fn parse_decimal_string(value: &str, precision: u32, scale: u32) -> Result<i128, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(0);
+    }
+
+    let (is_negative, unsigned_value) = match value.strip_prefix('-') {
+        Some(unsigned_value) => (true, unsigned_value),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    let (mantissa, exponent) = match unsigned_value.split_once(|c: char| c == 'e' || c == 'E') {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().map_err(|_| format!("Invalid exponent in decimal value: {}", value))?),
+        None => (unsigned_value, 0),
+    };
+    let (integer_digits, fraction_digits) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    let digits = format!("{}{}", integer_digits, fraction_digits);
+    if digits.is_empty() || !digits.bytes().all(|digit| digit.is_ascii_digit()) {
+        return Err(format!("Invalid decimal value: {}", value));
+    }
+
+    let num_leading_zeros = digits.len() - digits.trim_start_matches('0').len();
+    if num_leading_zeros == digits.len() {
+        return Ok(0);
+    }
+
+    // Number of digits that end up in front of the decimal point once the value is scaled
+    let num_digits_to_keep = integer_digits.len() as i64 + exponent + scale as i64;
+    if num_digits_to_keep - num_leading_zeros as i64 > precision as i64 {
+        return Err(get_decimal_overflow_error(value, precision, scale));
+    }
+
+    let scaled_digits = if num_digits_to_keep <= 0 {
+        String::new()
+    } else if num_digits_to_keep as usize >= digits.len() {
+        format!("{}{}", digits, "0".repeat(num_digits_to_keep as usize - digits.len()))
+    } else {
+        digits[..num_digits_to_keep as usize].to_string()
+    };
+
+    let scaled_digits = scaled_digits.trim_start_matches('0');
+    let unscaled_value = if scaled_digits.is_empty() { 0 } else { scaled_digits.parse::<i128>().unwrap() };
+    Ok(if is_negative { -unscaled_value } else { unscaled_value })
+}
+
+/// Converts an unsigned big endian integer into it's unscaled value for the given scale
+fn unsigned_big_endian_to_decimal(value: &[u8], precision: u32, scale: u32) -> Result<i128, String> {
+    let significant_bytes = &value[value.iter().take_while(|byte| **byte == 0).count()..];
+    let max_unscaled_value = 10_i128.pow(precision) - 1;
+
+    let unscaled_value = if significant_bytes.len() <= 16 {
+        let value = significant_bytes.iter().fold(0_u128, |acc, byte| (acc << 8) | *byte as u128);
+        i128::try_from(value).ok().and_then(|value| value.checked_mul(10_i128.pow(scale)))
+    } else {
+        None
+    };
+
+    match unscaled_value {
+        Some(unscaled_value) if unscaled_value <= max_unscaled_value => Ok(unscaled_value),
+        _ => Err(get_decimal_overflow_error(&unsigned_big_endian_to_decimal_string(value), precision, scale)),
+    }
+}
+
+fn get_decimal_overflow_error(value: &str, precision: u32, scale: u32) -> String {
+    format!("Value: {}, doesn't fit into a decimal with precision: {} and scale: {}! Use the string big number type for values this large", value, precision, scale)
+}
+
+fn unsigned_big_endian_to_decimal_string(value: &[u8]) -> String {
+    let mut remaining = value.to_vec();
+    let mut digits = Vec::new();
+    while remaining.iter().any(|byte| *byte != 0) {
+        // Long division by 10, keeping the quotient for the next digit
+        let mut remainder = 0_u32;
+        for byte in remaining.iter_mut() {
+            let current = (remainder << 8) | *byte as u32;
+            *byte = (current / 10) as u8;
+            remainder = current % 10;
+        }
+        digits.push(char::from(b'0' + remainder as u8));
+    }
+
+    if digits.is_empty() {
+        "0".to_string()
+    } else {
+        digits.iter().rev().collect()
+    }
+}
+
+/// Parses a hex string (with or without the 0x prefix) which is expected to hold exactly length bytes. An empty string gives all zeros
+fn parse_hex_string(value: &str, length: usize) -> Result<Vec<u8>, String> {
+    let hex = value.trim_start_matches("0x").trim_start_matches("0X");
+    if hex.is_empty() {
+        return Ok(vec![0; length]);
+    }
+
+    if hex.len() != length * 2 {
+        return Err(format!("Expected a hex string of {} bytes, got: {}", length, value));
+    }
+
+    (0..hex.len()).step_by(2).map(|index| {
+        hex.get(index..index+2).and_then(|byte| u8::from_str_radix(byte, 16).ok()).ok_or_else(|| format!("Invalid hex string: {}", value))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::streaming_fast::file_sinks::helpers::parquet::logical_type_decoder::{get_decimal_length, parse_decimal_string, parse_hex_string, unsigned_big_endian_to_decimal, unsigned_big_endian_to_decimal_string};
+
+    #[test]
+    fn test_parse_decimal_string() {
+        assert_eq!(parse_decimal_string("12345", 38, 0), Ok(12345));
+        assert_eq!(parse_decimal_string("-12.345", 38, 2), Ok(-1234));
+        assert_eq!(parse_decimal_string("0.5", 38, 18), Ok(500_000_000_000_000_000));
+        assert_eq!(parse_decimal_string("1.5e3", 38, 0), Ok(1500));
+        assert_eq!(parse_decimal_string("1.2E-5", 38, 6), Ok(12));
+        assert_eq!(parse_decimal_string("", 38, 0), Ok(0));
+        assert_eq!(parse_decimal_string("99999", 5, 0), Ok(99999));
+        assert!(parse_decimal_string("100000", 5, 0).is_err());
+        assert!(parse_decimal_string("abc", 38, 0).is_err());
+    }
+
+    #[test]
+    fn test_big_endian_conversions() {
+        assert_eq!(unsigned_big_endian_to_decimal(&[0x01, 0x00], 38, 0), Ok(256));
+        assert_eq!(unsigned_big_endian_to_decimal(&[0x01, 0x00], 38, 2), Ok(25600));
+        assert_eq!(unsigned_big_endian_to_decimal(&[], 38, 0), Ok(0));
+        assert!(unsigned_big_endian_to_decimal(&[0xff; 32], 38, 0).is_err());
+
+        assert_eq!(unsigned_big_endian_to_decimal_string(&[0x01, 0x00]), "256");
+        assert_eq!(unsigned_big_endian_to_decimal_string(&[]), "0");
+        assert_eq!(unsigned_big_endian_to_decimal_string(&[0xff; 32]), "115792089237316195423570985008687907853269984665640564039457584007913129639935");
+    }
+
+    #[test]
+    fn test_decimal_length() {
+        assert_eq!(get_decimal_length(1), 1);
+        assert_eq!(get_decimal_length(2), 1);
+        assert_eq!(get_decimal_length(3), 2);
+        assert_eq!(get_decimal_length(18), 8);
+        assert_eq!(get_decimal_length(38), 16);
+    }
+
+    #[test]
+    fn test_parse_hex_string() {
+        assert_eq!(parse_hex_string("0x0102", 2), Ok(vec![1, 2]));
+        assert_eq!(parse_hex_string("ABcd", 2), Ok(vec![0xab, 0xcd]));
+        assert_eq!(parse_hex_string("", 2), Ok(vec![0, 0]));
+        assert!(parse_hex_string("0x01", 2).is_err());
+        assert!(parse_hex_string("0xzz01", 2).is_err());
+    }
+}
@@ -5,3 +5,4 @@ pub(in crate::streaming_fast::file_sinks) mod decoder;
 pub(in crate::streaming_fast::file_sinks) mod parquet_schema_builder;
 pub(in crate::streaming_fast::file_sinks) mod enum_decoder;
 pub(in crate::streaming_fast::file_sinks) mod repetition_and_definition;
+pub(in crate::streaming_fast::file_sinks) mod logical_type_decoder;
\ No newline at end of file
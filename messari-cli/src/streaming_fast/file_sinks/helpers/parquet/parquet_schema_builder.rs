@@ -1,26 +1,57 @@
 use std::sync::Arc;
-use parquet::basic::{Compression, ConvertedType, LogicalType, Repetition};
-use parquet::file::properties::{WriterProperties, WriterPropertiesPtr};
+use parquet::basic::{Compression, ConvertedType, GzipLevel, LogicalType, Repetition, TimeUnit, ZstdLevel};
+use parquet::file::properties::{EnabledStatistics, WriterProperties, WriterPropertiesPtr};
+use parquet::format::MicroSeconds;
 use parquet::schema::types::{GroupTypeBuilder, PrimitiveTypeBuilder, TypePtr};
 use derives::proto_structure_info::FieldType;
 
+use crate::streaming_fast::file_sinks::helpers::field_naming::get_flattened_field_name;
+use crate::streaming_fast::file_sinks::helpers::parquet::logical_type_decoder::{get_decimal_length, LogicalTypeMapping};
+use crate::streaming_fast::streaming_config::{BigNumberType, BlockMetadataColumns, ParquetCompression, ParquetConfig};
+
 pub(in crate::streaming_fast::file_sinks) struct ParquetSchemaBuilder {
     subgroup_fields: Vec<Vec<TypePtr>>,
     hierarchy_trace: Vec<String>,
     current_id: i32,
-    type_name: String
+    type_name: String,
+    big_number_type: BigNumberType,
+    fixed_length_addresses_and_hashes: bool,
 }
 
 impl ParquetSchemaBuilder {
-    pub(in crate::streaming_fast::file_sinks) fn new(type_name: String) -> Self {
+    /// The decimal precision and scale of the parquet config are expected to have been validated when it was loaded
+    pub(in crate::streaming_fast::file_sinks) fn new(type_name: String, parquet_config: &ParquetConfig, block_metadata_columns: &BlockMetadataColumns) -> Self {
+        // The block metadata columns come first as they make up the primary key for our data
+        let mut block_metadata_fields = Vec::new();
+        for column_name in block_metadata_columns.get_column_names() {
+            let column_builder = match column_name {
+                "block_hash" => PrimitiveTypeBuilder::new(column_name, parquet::basic::Type::BYTE_ARRAY).with_logical_type(Some(LogicalType::String)),
+                "block_timestamp" => PrimitiveTypeBuilder::new(column_name, parquet::basic::Type::INT64)
+                    .with_logical_type(Some(LogicalType::Timestamp { is_adjusted_to_u_t_c: true, unit: TimeUnit::MICROS(MicroSeconds {}) })),
+                _ => PrimitiveTypeBuilder::new(column_name, parquet::basic::Type::INT64).with_logical_type(Some(LogicalType::Integer{ bit_width: 64, is_signed: false })),
+            };
+            let column_id = block_metadata_fields.len() as i32 + 1;
+            block_metadata_fields.push(Arc::new(column_builder.with_id(column_id).with_repetition(Repetition::REQUIRED).build().unwrap()));
+        }
+
         ParquetSchemaBuilder {
-            subgroup_fields: vec![vec![Arc::new(PrimitiveTypeBuilder::new("block_number", parquet::basic::Type::INT64).with_id(1).with_repetition(Repetition::REQUIRED).with_logical_type(Some(LogicalType::Integer{ bit_width: 64, is_signed: false })).build().unwrap())]],
+            current_id: block_metadata_fields.len() as i32,
+            subgroup_fields: vec![block_metadata_fields],
             hierarchy_trace: vec![],
-            current_id: 1,
             type_name,
+            big_number_type: parquet_config.big_numbers.clone(),
+            fixed_length_addresses_and_hashes: parquet_config.fixed_length_addresses_and_hashes,
         }
     }
 
+    pub(in crate::streaming_fast::file_sinks) fn get_big_number_type(&self) -> &BigNumberType {
+        &self.big_number_type
+    }
+
+    pub(in crate::streaming_fast::file_sinks) fn is_fixed_length_addresses_and_hashes_enabled(&self) -> bool {
+        self.fixed_length_addresses_and_hashes
+    }
+
     pub(in crate::streaming_fast::file_sinks) fn start_building_sub_group(&mut self, field_name: String) {
         self.hierarchy_trace.push(field_name);
         self.subgroup_fields.push(Vec::new());
@@ -40,8 +71,19 @@ impl ParquetSchemaBuilder {
         self.subgroup_fields.last_mut().unwrap().push(Arc::new(new_field));
     }
 
+    /// Same as finish_building_sub_group but for the entries of a proto map<> field, which get stored as a parquet MAP
+    pub(in crate::streaming_fast::file_sinks) fn finish_building_map_sub_group(&mut self) {
+        let field_name = self.hierarchy_trace.pop().unwrap();
+        let mut group_fields = self.subgroup_fields.pop().unwrap();
+        let new_field = GroupTypeBuilder::new(&field_name).with_converted_type(ConvertedType::MAP).with_repetition(Repetition::REQUIRED)
+            .with_fields(&mut vec![Arc::new(GroupTypeBuilder::new("key_value").with_fields(&mut group_fields).with_repetition(Repetition::REPEATED).build().unwrap())])
+            .build().unwrap();
+
+        self.subgroup_fields.last_mut().unwrap().push(Arc::new(new_field));
+    }
+
     /// Returns the a parquet file schema and it's corresponding write properties in the form => (parquet_schema, writer_properties)
-    pub(in crate::streaming_fast::file_sinks) fn compile(mut self) -> (TypePtr, WriterPropertiesPtr) {
+    pub(in crate::streaming_fast::file_sinks) fn compile(mut self, parquet_config: &ParquetConfig) -> (TypePtr, WriterPropertiesPtr) {
         assert!(self.hierarchy_trace.len()==0 && self.subgroup_fields.len()==1);
 
         let group_builder = GroupTypeBuilder::new(&self.type_name);
@@ -49,17 +91,11 @@ impl ParquetSchemaBuilder {
 
         let parquet_schema = group_builder.with_fields(&mut group_fields).build().unwrap();
 
-        let writer_properties = Arc::new(WriterProperties::builder().set_compression(Compression::SNAPPY).build());
-
-        (Arc::new(parquet_schema), writer_properties)
+        (Arc::new(parquet_schema), get_writer_properties(parquet_config))
     }
 
     pub(in crate::streaming_fast::file_sinks) fn get_flattened_field_name(&self, field_name: &str) -> String {
-        if self.hierarchy_trace.is_empty() {
-            field_name.to_string()
-        } else {
-            format!("{}__{}", self.hierarchy_trace.join("__"), field_name)
-        }
+        get_flattened_field_name(&self.hierarchy_trace, field_name)
     }
 
     pub(in crate::streaming_fast::file_sinks) fn add_column_info(&mut self, field_name: &str, field_type: FieldType, repetition: Repetition) {
@@ -108,4 +144,47 @@ impl ParquetSchemaBuilder {
             FieldType::Message(_) => unreachable!()
         }
     }
-}
\ No newline at end of file
+
+    pub(in crate::streaming_fast::file_sinks) fn add_logical_type_column_info(&mut self, field_name: &str, logical_type_mapping: &LogicalTypeMapping, repetition: Repetition) {
+        self.current_id += 1;
+
+        let column_builder = match logical_type_mapping {
+            LogicalTypeMapping::Timestamp => PrimitiveTypeBuilder::new(field_name, parquet::basic::Type::INT64)
+                .with_logical_type(Some(LogicalType::Timestamp { is_adjusted_to_u_t_c: true, unit: TimeUnit::MICROS(MicroSeconds {}) })),
+            LogicalTypeMapping::Decimal { precision, scale } => PrimitiveTypeBuilder::new(field_name, parquet::basic::Type::FIXED_LEN_BYTE_ARRAY)
+                .with_length(get_decimal_length(*precision) as i32)
+                .with_logical_type(Some(LogicalType::Decimal { scale: *scale as i32, precision: *precision as i32 }))
+                .with_precision(*precision as i32)
+                .with_scale(*scale as i32),
+            LogicalTypeMapping::BigNumberString => PrimitiveTypeBuilder::new(field_name, parquet::basic::Type::BYTE_ARRAY)
+                .with_logical_type(Some(LogicalType::String)),
+            LogicalTypeMapping::FixedLengthBinary { length } => PrimitiveTypeBuilder::new(field_name, parquet::basic::Type::FIXED_LEN_BYTE_ARRAY)
+                .with_length(*length as i32),
+        };
+        let column = Arc::new(column_builder.with_id(self.current_id).with_repetition(repetition).build().unwrap());
+
+        if repetition == Repetition::REPEATED {
+            self.subgroup_fields.last_mut().unwrap().push(Arc::new(GroupTypeBuilder::new(field_name).with_converted_type(ConvertedType::LIST).with_repetition(Repetition::REQUIRED)
+                .with_fields(&mut vec![column]).build().unwrap()));
+        } else {
+            self.subgroup_fields.last_mut().unwrap().push(column);
+        }
+    }
+}
+
+pub(in crate::streaming_fast::file_sinks) fn get_writer_properties(parquet_config: &ParquetConfig) -> WriterPropertiesPtr {
+    let compression = match parquet_config.compression {
+        ParquetCompression::Snappy => Compression::SNAPPY,
+        ParquetCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+        ParquetCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+        ParquetCompression::None => Compression::UNCOMPRESSED,
+    };
+    let statistics = if parquet_config.statistics_enabled { EnabledStatistics::Page } else { EnabledStatistics::None };
+
+    Arc::new(WriterProperties::builder()
+        .set_compression(compression)
+        .set_dictionary_enabled(parquet_config.dictionary_enabled)
+        .set_statistics_enabled(statistics)
+        .set_max_row_group_size(parquet_config.max_rows_per_row_group)
+        .build())
+}
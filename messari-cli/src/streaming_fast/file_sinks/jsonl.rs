@@ -0,0 +1,175 @@
+use std::mem;
+use derives::proto_structure_info::{FieldSpecification, MessageInfo};
+use serde_json::{Map, Value};
+
+use crate::streaming_fast::file_sinks::file_sink::{BlockMetadata, FileSink};
+use crate::streaming_fast::file_sinks::helpers::json_decoder::JsonDecoder;
+use crate::streaming_fast::streaming_config::{BlockMetadataColumns, ParquetConfig};
+
+pub(crate) struct JsonLFileSink {
+    decoder: JsonDecoder,
+    struct_is_required: bool,
+    block_metadata_columns: BlockMetadataColumns,
+    file_size_threshold: usize,
+    file_data: Vec<u8>,
+}
+
+impl JsonLFileSink {
+    pub(crate) fn new_with_config(output_type_info: MessageInfo, file_size_threshold: usize, block_metadata_columns: BlockMetadataColumns) -> Self {
+        let struct_is_required =
+            output_type_info.field_specification == FieldSpecification::Required;
+
+        JsonLFileSink {
+            decoder: JsonDecoder::new(output_type_info),
+            struct_is_required,
+            block_metadata_columns,
+            file_size_threshold,
+            file_data: Vec::new(),
+        }
+    }
+}
+
+impl FileSink for JsonLFileSink {
+    fn new(output_type_info: MessageInfo) -> Self {
+        JsonLFileSink::new_with_config(output_type_info, ParquetConfig::default().file_size_threshold, BlockMetadataColumns::default())
+    }
+
+    fn process(
+        &mut self,
+        proto_data: &mut &[u8],
+        block_metadata: &BlockMetadata,
+    ) -> Result<(), String> {
+        let values = if proto_data.is_empty() {
+            if !self.struct_is_required {
+                return Ok(());
+            }
+            self.decoder.get_default_values()?
+        } else {
+            self.decoder.decode(proto_data)?
+        };
+
+        // block_number (along with the other block metadata) is the primary key for our data so we make sure it always comes first in each row
+        let mut row = Map::new();
+        for (column_name, value) in block_metadata.get_column_values(&self.block_metadata_columns)? {
+            row.insert(column_name.to_string(), value);
+        }
+        row.extend(values);
+
+        serde_json::to_writer(&mut self.file_data, &Value::Object(row)).unwrap();
+        self.file_data.push(b'\n');
+
+        Ok(())
+    }
+
+    fn is_full(&self) -> bool {
+        self.file_data.len() > self.file_size_threshold
+    }
+
+    fn make_file(&mut self) -> Vec<u8> {
+        mem::take(&mut self.file_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use derives::{GenRandSamples, ProtoInfo, TestData};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use serde_json::Value;
+
+    use crate::streaming_fast::file_sinks::file_sink::{BlockMetadata, FileSink};
+    use crate::streaming_fast::file_sinks::jsonl::JsonLFileSink;
+    use crate::streaming_fast::streaming_config::{BlockMetadataColumns, ParquetConfig};
+
+    #[test]
+    fn test_one_row_per_block() {
+        #[derive(TestData)]
+        pub struct OptionalAndRepeatedFields {
+            field1: String,
+            field2: Option<u64>,
+            field3: Vec<String>,
+            field4: Vec<u64>,
+        }
+
+        const NUM_SAMPLES: usize = 50;
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let test_data = OptionalAndRepeatedFields::get_samples(NUM_SAMPLES, &mut rng);
+
+        let mut sink = JsonLFileSink::new(OptionalAndRepeatedFields::get_proto_structure_info());
+        for (block_number, test_datum) in test_data.iter().enumerate() {
+            let bytes: Vec<u8> = test_datum.to_proto_bytes();
+            sink.process(&mut bytes.as_slice(), &BlockMetadata::from_block_number(block_number as i64)).unwrap();
+        }
+
+        let file_data = String::from_utf8(sink.make_file()).unwrap();
+        let rows = file_data.lines().map(|line| serde_json::from_str::<Value>(line).unwrap()).collect::<Vec<_>>();
+        assert_eq!(rows.len(), NUM_SAMPLES);
+
+        for (block_number, (row, test_datum)) in rows.iter().zip(test_data.iter()).enumerate() {
+            let row = row.as_object().unwrap();
+            assert_eq!(row.keys().next().unwrap(), "block_number");
+            assert_eq!(row["block_number"], Value::from(block_number));
+            assert_eq!(row["field1"], Value::from(test_datum.field1.clone()));
+            assert_eq!(row["field2"], test_datum.field2.map(Value::from).unwrap_or(Value::Null));
+            assert_eq!(row["field3"], Value::from(test_datum.field3.clone()));
+            assert_eq!(row["field4"], Value::from(test_datum.field4.clone()));
+        }
+    }
+
+    #[test]
+    fn test_block_metadata_columns() {
+        #[derive(TestData)]
+        pub struct SimpleStruct {
+            field1: String,
+        }
+
+        let block_metadata_columns = BlockMetadataColumns {
+            block_hash: true,
+            block_timestamp: true,
+            row_index: true,
+        };
+        let mut sink = JsonLFileSink::new_with_config(SimpleStruct::get_proto_structure_info(), ParquetConfig::default().file_size_threshold, block_metadata_columns);
+
+        let bytes = SimpleStruct { field1: "value".to_string() }.to_proto_bytes();
+        let block_metadata = BlockMetadata {
+            block_number: 10,
+            block_hash: "abcd".to_string(),
+            block_timestamp: Some(1_700_000_000),
+            row_index: 2,
+        };
+        sink.process(&mut bytes.as_slice(), &block_metadata).unwrap();
+
+        let file_data = String::from_utf8(sink.make_file()).unwrap();
+        let row = serde_json::from_str::<Value>(file_data.trim()).unwrap();
+        let row = row.as_object().unwrap();
+        assert_eq!(row.keys().collect::<Vec<_>>(), vec!["block_number", "block_hash", "block_timestamp", "row_index", "field1"]);
+        assert_eq!(row["block_hash"], Value::from("abcd"));
+        assert_eq!(row["block_timestamp"], Value::from(1_700_000_000));
+        assert_eq!(row["row_index"], Value::from(2));
+
+        let block_metadata = BlockMetadata {
+            block_timestamp: None,
+            ..block_metadata
+        };
+        assert!(sink.process(&mut bytes.as_slice(), &block_metadata).is_err());
+    }
+
+    #[test]
+    fn test_configured_file_size_threshold() {
+        #[derive(TestData)]
+        pub struct SimpleStruct {
+            field1: String,
+        }
+
+        let mut sink = JsonLFileSink::new_with_config(SimpleStruct::get_proto_structure_info(), 64, BlockMetadataColumns::default());
+        let bytes = SimpleStruct { field1: "value".to_string() }.to_proto_bytes();
+
+        sink.process(&mut bytes.as_slice(), &BlockMetadata::from_block_number(1)).unwrap();
+        assert!(!sink.is_full());
+        for block_number in 2..5 {
+            sink.process(&mut bytes.as_slice(), &BlockMetadata::from_block_number(block_number)).unwrap();
+        }
+        assert!(sink.is_full());
+    }
+}
@@ -1,3 +1,5 @@
 pub(in crate::streaming_fast::file_sinks) mod helpers;
 pub(crate) mod parquet;
+pub(crate) mod jsonl;
 pub(crate) mod file_sink;
+pub(crate) mod csv;
\ No newline at end of file
@@ -1,32 +1,39 @@
+use bytes::Bytes;
 use derives::proto_structure_info::{FieldSpecification, MessageInfo};
-use parquet::data_type::Int64Type;
+use parquet::column::reader::{ColumnReader, ColumnReaderImpl};
+use parquet::column::writer::ColumnWriterImpl;
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DataType, DoubleType, FixedLenByteArrayType, FloatType, Int32Type, Int64Type, Int96Type};
 use parquet::file::properties::WriterPropertiesPtr;
-use parquet::file::writer::SerializedFileWriter;
-use parquet::schema::types::TypePtr;
+use parquet::file::reader::{FileReader, RowGroupReader, SerializedFileReader};
+use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter, SerializedRowGroupWriter};
+use parquet::schema::types::{ColumnDescriptor, TypePtr};
 
-use crate::streaming_fast::file_sinks::file_sink::FileSink;
+use crate::streaming_fast::file_sinks::file_sink::{BlockMetadata, FileSink};
 use crate::streaming_fast::file_sinks::helpers::parquet::file_buffer::FileBuffer;
-use crate::streaming_fast::file_sinks::helpers::parquet::parquet_schema_builder::ParquetSchemaBuilder;
+use crate::streaming_fast::file_sinks::helpers::parquet::parquet_schema_builder::{get_writer_properties, ParquetSchemaBuilder};
 use crate::streaming_fast::file_sinks::helpers::parquet::repetition_and_definition::{
     RepetitionAndDefinitionLvlStoreBuilder, RepetitionAndDefinitionLvls,
 };
 use crate::streaming_fast::file_sinks::helpers::parquet::struct_decoder::StructDecoder;
-
-const UNCOMPRESSED_FILE_SIZE_THRESHOLD: usize = 10 * 8 * 1024 * 1024; // 10MB, in bits
+use crate::streaming_fast::streaming_config::{BlockMetadataColumns, ParquetConfig};
 
 pub(crate) struct ParquetFileSink {
     decoder: StructDecoder,
     struct_is_required: bool,
-    uncompressed_file_size: usize,
-    block_numbers: Vec<i64>,
+    /// Size of the data that has been decoded but not yet written to a row group - in bits
+    uncompressed_row_group_size: usize,
+    block_metadata_values: BlockMetadataValues,
     parquet_schema: TypePtr,
     writer_properties: WriterPropertiesPtr,
+    file_size_threshold: usize,
+    max_rows_per_row_group: usize,
+    file_writer: Option<(SerializedFileWriter<FileBuffer>, FileBuffer)>,
 }
 
-impl FileSink for ParquetFileSink {
-    fn new(output_type_info: MessageInfo) -> Self {
+impl ParquetFileSink {
+    pub(crate) fn new_with_config(output_type_info: MessageInfo, parquet_config: &ParquetConfig, block_metadata_columns: BlockMetadataColumns) -> Self {
         let mut parquet_schema_builder =
-            ParquetSchemaBuilder::new(output_type_info.type_name.clone());
+            ParquetSchemaBuilder::new(output_type_info.type_name.clone(), parquet_config, &block_metadata_columns);
 
         let struct_is_required =
             output_type_info.field_specification == FieldSpecification::Required;
@@ -38,90 +45,306 @@ impl FileSink for ParquetFileSink {
             &mut RepetitionAndDefinitionLvlStoreBuilder::new(),
         );
 
-        let (parquet_schema, writer_properties) = parquet_schema_builder.compile();
+        let (parquet_schema, writer_properties) = parquet_schema_builder.compile(parquet_config);
 
         ParquetFileSink {
             decoder,
             struct_is_required,
-            uncompressed_file_size: 0,
-            block_numbers: vec![],
+            uncompressed_row_group_size: 0,
+            block_metadata_values: BlockMetadataValues::new(block_metadata_columns),
             parquet_schema,
             writer_properties,
+            file_size_threshold: parquet_config.file_size_threshold,
+            max_rows_per_row_group: parquet_config.max_rows_per_row_group.max(1),
+            file_writer: None,
+        }
+    }
+
+    pub(crate) fn get_parquet_schema(&self) -> TypePtr {
+        self.parquet_schema.clone()
+    }
+
+    /// Writes all of the data decoded so far into a new row group of the file currently being built
+    fn write_row_group(&mut self) {
+        if self.block_metadata_values.is_empty() {
+            return;
         }
+
+        let (file_writer, _) = self.file_writer.get_or_insert_with(|| {
+            let file_buffer = FileBuffer::new();
+            let file_writer = SerializedFileWriter::new(
+                file_buffer.clone(),
+                self.parquet_schema.clone(),
+                self.writer_properties.clone(),
+            )
+            .unwrap();
+            (file_writer, file_buffer)
+        });
+        let mut row_group_writer = file_writer.next_row_group().unwrap();
+
+        // We need to add the block metadata to the first columns before adding the rest of the data from the proto decoding (block_number is the primary key for our data!)
+        self.block_metadata_values.write_data_to_parquet(&mut row_group_writer);
+
+        self.decoder.write_data_to_parquet(&mut row_group_writer);
+
+        row_group_writer.close().unwrap();
+        self.uncompressed_row_group_size = 0;
+    }
+
+    /// Size of the file if it were to be written out now - in bytes
+    fn get_file_size_estimate(&self) -> usize {
+        let written_size = self.file_writer.as_ref().map(|(_, file_buffer)| file_buffer.len()).unwrap_or(0);
+        written_size + self.uncompressed_row_group_size / 8
+    }
+}
+
+impl FileSink for ParquetFileSink {
+    fn new(output_type_info: MessageInfo) -> Self {
+        ParquetFileSink::new_with_config(output_type_info, &ParquetConfig::default(), BlockMetadataColumns::default())
     }
 
     fn process(
         &mut self,
         proto_data: &mut &[u8],
-        block_number: i64,
-    ) -> Result<Option<Vec<u8>>, String> {
+        block_metadata: &BlockMetadata,
+    ) -> Result<(), String> {
         if proto_data.is_empty() {
             if self.struct_is_required {
                 self.decoder.push_null_or_default_values(
-                    &mut self.uncompressed_file_size,
+                    &mut self.uncompressed_row_group_size,
                     RepetitionAndDefinitionLvls::new(),
                 )?;
-                self.block_numbers.push(block_number);
+                self.block_metadata_values.push(block_metadata)?;
             }
         } else {
             self.decoder.decode(
                 proto_data,
                 2,
-                &mut self.uncompressed_file_size,
+                &mut self.uncompressed_row_group_size,
                 RepetitionAndDefinitionLvls::new(),
             )?;
-            self.block_numbers.push(block_number);
+            self.block_metadata_values.push(block_metadata)?;
         }
 
-        if self.uncompressed_file_size > UNCOMPRESSED_FILE_SIZE_THRESHOLD {
-            self.uncompressed_file_size = 0;
-            Ok(Some(self.make_file()))
-        } else {
-            Ok(None)
+        if self.block_metadata_values.len() >= self.max_rows_per_row_group {
+            self.write_row_group();
         }
+
+        Ok(())
+    }
+
+    fn is_full(&self) -> bool {
+        self.get_file_size_estimate() > self.file_size_threshold
     }
 
     fn make_file(&mut self) -> Vec<u8> {
-        if self.block_numbers.is_empty() {
-            return Vec::new();
+        self.write_row_group();
+
+        match self.file_writer.take() {
+            Some((file_writer, file_buffer)) => {
+                file_writer.close().unwrap();
+                file_buffer.get_data()
+            }
+            None => Vec::new(),
         }
+    }
+}
 
-        let file_buffer = FileBuffer::new();
-        let mut file_writer = SerializedFileWriter::new(
-            file_buffer.clone(),
-            self.parquet_schema.clone(),
-            self.writer_properties.clone(),
-        )
-        .unwrap();
-        let mut row_group_writer = file_writer.next_row_group().unwrap();
+/// Reads the schema from the footer of a parquet file
+pub(crate) fn get_parquet_file_schema(file_data: Vec<u8>) -> Result<TypePtr, String> {
+    let file_reader = SerializedFileReader::new(Bytes::from(file_data)).map_err(|error| format!("Unable to read parquet file! Error: {}", error))?;
+    Ok(file_reader.metadata().file_metadata().schema_descr().root_schema_ptr())
+}
 
-        println!(
-            "Column: block_numbers, #values: {}",
-            self.block_numbers.len()
-        );
+/// Merges parquet files sharing the same schema into a single file, keeping the rows in the same order. The row groups
+/// of the files get combined until they reach the max rows per row group of the config
+pub(crate) fn merge_parquet_files(files_data: Vec<Vec<u8>>, parquet_config: &ParquetConfig) -> Result<Vec<u8>, String> {
+    let file_readers = files_data.into_iter().map(|file_data| SerializedFileReader::new(Bytes::from(file_data)))
+        .collect::<Result<Vec<_>, _>>().map_err(|error| format!("Unable to read parquet file! Error: {}", error))?;
+    let schema_descriptor = file_readers.first().ok_or("No parquet files given to merge!")?.metadata().file_metadata().schema_descr_ptr();
+    if file_readers.iter().any(|file_reader| file_reader.metadata().file_metadata().schema_descr().root_schema() != schema_descriptor.root_schema()) {
+        return Err("Parquet files with different schemas can't be merged!".to_string());
+    }
 
-        // We need to add the block_numbers to the first column before adding the rest of the data from the proto decoding (block_number is the primary key for our data!)
-        let mut serialized_column_writer = row_group_writer.next_column().unwrap().unwrap();
-        serialized_column_writer
-            .typed::<Int64Type>()
-            .write_batch(self.block_numbers.as_slice(), None, None)
-            .unwrap();
-        serialized_column_writer.close().unwrap();
-        self.block_numbers.clear();
+    // Each merged row group in the form -> [(file_index, row_group_index)]
+    let max_rows_per_row_group = parquet_config.max_rows_per_row_group.max(1) as i64;
+    let mut merged_row_groups = Vec::new();
+    let mut row_groups = Vec::new();
+    let mut num_rows = 0;
+    for (file_index, file_reader) in file_readers.iter().enumerate() {
+        for (row_group_index, row_group_metadata) in file_reader.metadata().row_groups().iter().enumerate() {
+            if !row_groups.is_empty() && num_rows + row_group_metadata.num_rows() > max_rows_per_row_group {
+                merged_row_groups.push(std::mem::take(&mut row_groups));
+                num_rows = 0;
+            }
+            row_groups.push((file_index, row_group_index));
+            num_rows += row_group_metadata.num_rows();
+        }
+    }
+    if !row_groups.is_empty() {
+        merged_row_groups.push(row_groups);
+    }
 
-        self.decoder.write_data_to_parquet(&mut row_group_writer);
+    let file_buffer = FileBuffer::new();
+    let mut file_writer = SerializedFileWriter::new(file_buffer.clone(), schema_descriptor.root_schema_ptr(), get_writer_properties(parquet_config))
+        .map_err(|error| format!("Unable to create merged parquet file! Error: {}", error))?;
+    for row_groups in merged_row_groups.into_iter() {
+        let mut row_group_writer = file_writer.next_row_group().map_err(|error| error.to_string())?;
+        for column_index in 0..schema_descriptor.num_columns() {
+            let mut column_writer = row_group_writer.next_column().map_err(|error| error.to_string())?.unwrap();
+            for (file_index, row_group_index) in row_groups.iter() {
+                let column_reader = file_readers[*file_index].get_row_group(*row_group_index)
+                    .and_then(|row_group_reader| row_group_reader.get_column_reader(column_index))
+                    .map_err(|error| format!("Unable to read column: {} of parquet file! Error: {}", schema_descriptor.column(column_index).path().string(), error))?;
+                copy_column_values(column_reader, &mut column_writer, &schema_descriptor.column(column_index))
+                    .map_err(|error| format!("Unable to copy column: {} of parquet file! Error: {}", schema_descriptor.column(column_index).path().string(), error))?;
+            }
+            column_writer.close().map_err(|error| error.to_string())?;
+        }
+        row_group_writer.close().map_err(|error| error.to_string())?;
+    }
+    file_writer.close().map_err(|error| error.to_string())?;
 
-        row_group_writer.close().unwrap();
-        file_writer.close().unwrap();
+    Ok(file_buffer.get_data())
+}
+
+fn copy_column_values(column_reader: ColumnReader, column_writer: &mut SerializedColumnWriter, column_descriptor: &ColumnDescriptor) -> parquet::errors::Result<()> {
+    macro_rules! copy_typed_column_values {
+        ($column_reader:ident, $data_type:ident) => {
+            copy_typed_column_values(&mut $column_reader, column_writer.typed::<$data_type>(), column_descriptor)
+        }
+    }
+
+    match column_reader {
+        ColumnReader::BoolColumnReader(mut column_reader) => copy_typed_column_values!(column_reader, BoolType),
+        ColumnReader::Int32ColumnReader(mut column_reader) => copy_typed_column_values!(column_reader, Int32Type),
+        ColumnReader::Int64ColumnReader(mut column_reader) => copy_typed_column_values!(column_reader, Int64Type),
+        ColumnReader::Int96ColumnReader(mut column_reader) => copy_typed_column_values!(column_reader, Int96Type),
+        ColumnReader::FloatColumnReader(mut column_reader) => copy_typed_column_values!(column_reader, FloatType),
+        ColumnReader::DoubleColumnReader(mut column_reader) => copy_typed_column_values!(column_reader, DoubleType),
+        ColumnReader::ByteArrayColumnReader(mut column_reader) => copy_typed_column_values!(column_reader, ByteArrayType),
+        ColumnReader::FixedLenByteArrayColumnReader(mut column_reader) => copy_typed_column_values!(column_reader, FixedLenByteArrayType),
+    }
+}
+
+/// Copies the values of a column chunk along with it's definition and repetition levels (where the column has them)
+fn copy_typed_column_values<T: DataType>(column_reader: &mut ColumnReaderImpl<T>, column_writer: &mut ColumnWriterImpl<T>, column_descriptor: &ColumnDescriptor) -> parquet::errors::Result<()> {
+    const BATCH_SIZE: usize = 1024;
+
+    let has_definition_lvls = column_descriptor.max_def_level() > 0;
+    let has_repetition_lvls = column_descriptor.max_rep_level() > 0;
+    let mut values = vec![T::T::default(); BATCH_SIZE];
+    let mut definition_lvls = vec![0; BATCH_SIZE];
+    let mut repetition_lvls = vec![0; BATCH_SIZE];
+
+    loop {
+        let (num_values, num_lvls) = column_reader.read_batch(
+            BATCH_SIZE,
+            if has_definition_lvls { Some(definition_lvls.as_mut_slice()) } else { None },
+            if has_repetition_lvls { Some(repetition_lvls.as_mut_slice()) } else { None },
+            &mut values,
+        )?;
+        if num_values == 0 && num_lvls == 0 {
+            return Ok(());
+        }
+
+        column_writer.write_batch(
+            &values[..num_values],
+            if has_definition_lvls { Some(&definition_lvls[..num_lvls]) } else { None },
+            if has_repetition_lvls { Some(&repetition_lvls[..num_lvls]) } else { None },
+        )?;
+    }
+}
+
+/// Values of the block metadata columns for each of the rows that haven't been written to a row group yet
+struct BlockMetadataValues {
+    block_metadata_columns: BlockMetadataColumns,
+    block_numbers: Vec<i64>,
+    block_hashes: Vec<ByteArray>,
+    /// In microseconds to match the column's TIMESTAMP(MICROS) type
+    block_timestamps: Vec<i64>,
+    row_indexes: Vec<i64>,
+}
+
+impl BlockMetadataValues {
+    fn new(block_metadata_columns: BlockMetadataColumns) -> Self {
+        BlockMetadataValues {
+            block_metadata_columns,
+            block_numbers: vec![],
+            block_hashes: vec![],
+            block_timestamps: vec![],
+            row_indexes: vec![],
+        }
+    }
+
+    fn push(&mut self, block_metadata: &BlockMetadata) -> Result<(), String> {
+        // Checked before anything gets pushed so the columns never end up with different lengths
+        let block_timestamp = if self.block_metadata_columns.block_timestamp {
+            Some(block_metadata.get_block_timestamp()?)
+        } else {
+            None
+        };
+
+        self.block_numbers.push(block_metadata.block_number);
+        if self.block_metadata_columns.block_hash {
+            self.block_hashes.push(ByteArray::from(block_metadata.block_hash.as_str()));
+        }
+        if let Some(block_timestamp) = block_timestamp {
+            self.block_timestamps.push(block_timestamp * 1_000_000);
+        }
+        if self.block_metadata_columns.row_index {
+            self.row_indexes.push(block_metadata.row_index);
+        }
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.block_numbers.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.block_numbers.is_empty()
+    }
+
+    /// Writes a column for each of the block metadata columns in the same order as they appear in the schema
+    fn write_data_to_parquet(&mut self, row_group_writer: &mut SerializedRowGroupWriter<FileBuffer>) {
+        for column_name in self.block_metadata_columns.get_column_names() {
+            println!("Column: {}, #values: {}", column_name, self.block_numbers.len());
+
+            let mut serialized_column_writer = row_group_writer.next_column().unwrap().unwrap();
+            match column_name {
+                "block_hash" => serialized_column_writer.typed::<ByteArrayType>().write_batch(self.block_hashes.as_slice(), None, None),
+                "block_timestamp" => serialized_column_writer.typed::<Int64Type>().write_batch(self.block_timestamps.as_slice(), None, None),
+                "row_index" => serialized_column_writer.typed::<Int64Type>().write_batch(self.row_indexes.as_slice(), None, None),
+                _ => serialized_column_writer.typed::<Int64Type>().write_batch(self.block_numbers.as_slice(), None, None),
+            }.unwrap();
+            serialized_column_writer.close().unwrap();
+        }
 
-        file_buffer.get_data()
+        self.block_numbers.clear();
+        self.block_hashes.clear();
+        self.block_timestamps.clear();
+        self.row_indexes.clear();
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::streaming_fast::streaming_fast_utils::assert_data_sinks_to_parquet_correctly;
-    use derives::TestData;
+    use derives::proto_structure_info::{FieldInfo, FieldSpecification, FieldType, MessageInfo};
+    use parquet::basic::{LogicalType, TimeUnit};
+    use parquet::data_type::{ByteArray, Decimal};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::format::MicroSeconds;
+    use parquet::record::{Field, RowAccessor};
+    use prost::encoding;
+
+    use crate::streaming_fast::file_sinks::file_sink::{BlockMetadata, FileSink};
+    use crate::streaming_fast::file_sinks::parquet::{merge_parquet_files, ParquetFileSink};
+    use crate::streaming_fast::streaming_config::{BigNumberType, BlockMetadataColumns, ParquetCompression, ParquetConfig};
+    use crate::streaming_fast::streaming_fast_utils::{assert_data_sinks_to_parquet_correctly, assert_data_sinks_to_parquet_correctly_with_config};
+    use derives::{GenRandSamples, ProtoInfo, TestData};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
 
     #[test]
     fn test_oneof() {
@@ -256,4 +479,265 @@ mod tests {
 
         assert_data_sinks_to_parquet_correctly::<FlatSimple>();
     }
+
+    #[test]
+    fn test_multiple_row_groups() {
+        #[derive(TestData)]
+        pub struct RowGroups {
+            field1: Vec<u64>,
+            field2: Option<String>,
+            field3: u32,
+        }
+
+        let parquet_config = ParquetConfig {
+            compression: ParquetCompression::Zstd,
+            max_rows_per_row_group: 7,
+            dictionary_enabled: false,
+            statistics_enabled: false,
+            ..ParquetConfig::default()
+        };
+
+        assert_data_sinks_to_parquet_correctly_with_config::<RowGroups>(&parquet_config);
+    }
+
+    #[test]
+    fn test_merge_parquet_files() {
+        #[derive(TestData)]
+        pub struct MergedRows {
+            field1: Vec<u64>,
+            field2: Option<String>,
+            field3: Vec<AnotherStruct>,
+        }
+
+        #[derive(TestData)]
+        pub struct AnotherStruct {
+            field1: Option<u64>,
+            field2: Vec<String>,
+        }
+
+        let parquet_config = ParquetConfig {
+            max_rows_per_row_group: 25,
+            ..ParquetConfig::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut files_data = Vec::new();
+        let mut expected_rows = Vec::new();
+        for file_index in 0..4 {
+            let mut sink = ParquetFileSink::new_with_config(MergedRows::get_proto_structure_info(), &ParquetConfig { max_rows_per_row_group: 7, ..ParquetConfig::default() }, BlockMetadataColumns::default());
+            for sample in MergedRows::get_samples(10 + file_index, &mut rng) {
+                sink.process(&mut sample.to_proto_bytes().as_slice(), &BlockMetadata::from_block_number(file_index as i64)).unwrap();
+                expected_rows.push((sample, file_index as u64));
+            }
+            files_data.push(sink.make_file());
+        }
+
+        let merged_file_data = merge_parquet_files(files_data, &parquet_config).unwrap();
+        let reader = SerializedFileReader::new(bytes::Bytes::from(merged_file_data)).unwrap();
+        // 46 rows made up of row groups of at most 7 rows get combined into row groups of at most 25 rows
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 46);
+        assert!(reader.metadata().row_groups().iter().all(|row_group| row_group.num_rows() <= 25));
+        assert!(reader.metadata().num_row_groups() < 8);
+
+        let merged_rows = reader.get_row_iter(None).unwrap().map(|parquet_row| {
+            let (parsed_data, block_number) = MergedRows::get_from_parquet_row(parquet_row.get_column_iter());
+            (parsed_data, block_number.unwrap())
+        }).collect::<Vec<_>>();
+        assert_eq!(merged_rows, expected_rows);
+
+        let other_schema_file_data = {
+            let mut sink = ParquetFileSink::new_with_config(AnotherStruct::get_proto_structure_info(), &parquet_config, BlockMetadataColumns::default());
+            sink.process(&mut AnotherStruct::default().to_proto_bytes().as_slice(), &BlockMetadata::from_block_number(1)).unwrap();
+            sink.make_file()
+        };
+        let first_file_data = {
+            let mut sink = ParquetFileSink::new_with_config(MergedRows::get_proto_structure_info(), &parquet_config, BlockMetadataColumns::default());
+            sink.process(&mut MergedRows::default().to_proto_bytes().as_slice(), &BlockMetadata::from_block_number(1)).unwrap();
+            sink.make_file()
+        };
+        assert!(merge_parquet_files(vec![first_file_data, other_schema_file_data], &parquet_config).is_err());
+    }
+
+    fn get_field_info(field_name: &str, field_type: FieldType, field_specification: FieldSpecification, field_number: u64) -> FieldInfo {
+        FieldInfo {
+            field_name: field_name.to_string(),
+            field_type,
+            field_specification,
+            field_number,
+        }
+    }
+
+    fn get_message_field_info(field_name: &str, full_type_name: &str, fields: Vec<FieldInfo>, field_specification: FieldSpecification, field_number: u64) -> FieldInfo {
+        let message_info = MessageInfo {
+            type_name: full_type_name.split('.').last().unwrap().to_string(),
+            full_type_name: full_type_name.to_string(),
+            is_map_entry: full_type_name.ends_with("Entry"),
+            field_specification: field_specification.clone(),
+            fields,
+            oneof_groups: vec![],
+        };
+        get_field_info(field_name, FieldType::Message(message_info), field_specification, field_number)
+    }
+
+    fn get_well_known_types_info() -> MessageInfo {
+        MessageInfo {
+            type_name: "WellKnownTypes".to_string(),
+            full_type_name: "messari.test.v1.WellKnownTypes".to_string(),
+            is_map_entry: false,
+            field_specification: FieldSpecification::Required,
+            fields: vec![
+                get_message_field_info("created_at", "google.protobuf.Timestamp", vec![
+                    get_field_info("seconds", FieldType::Int64, FieldSpecification::Required, 1),
+                    get_field_info("nanos", FieldType::Int32, FieldSpecification::Required, 2),
+                ], FieldSpecification::Required, 1),
+                get_message_field_info("amount", "messari.test.v1.BigInt", vec![get_field_info("val", FieldType::String, FieldSpecification::Required, 1)], FieldSpecification::Required, 2),
+                get_message_field_info("price", "messari.test.v1.BigDecimal", vec![get_field_info("val", FieldType::String, FieldSpecification::Required, 1)], FieldSpecification::Optional, 3),
+                get_message_field_info("supply", "messari.test.v1.BigInt", vec![get_field_info("bytes", FieldType::Bytes, FieldSpecification::Required, 1)], FieldSpecification::Required, 4),
+                get_field_info("tx_hash", FieldType::String, FieldSpecification::Required, 5),
+                get_message_field_info("token", "messari.common.v1.Address", vec![get_field_info("address", FieldType::String, FieldSpecification::Required, 1)], FieldSpecification::Required, 6),
+                get_message_field_info("balances", "messari.test.v1.WellKnownTypes.BalancesEntry", vec![
+                    get_field_info("key", FieldType::String, FieldSpecification::Required, 1),
+                    get_field_info("value", FieldType::Uint64, FieldSpecification::Required, 2),
+                ], FieldSpecification::Repeated, 7),
+            ],
+            oneof_groups: vec![],
+        }
+    }
+
+    fn get_well_known_types_proto_data() -> Vec<u8> {
+        let mut timestamp = Vec::new();
+        encoding::int64::encode(1, &1_700_000_000, &mut timestamp);
+        encoding::int32::encode(2, &123_456_789, &mut timestamp);
+
+        let mut amount = Vec::new();
+        encoding::string::encode(1, &"123456789012345678901234567890".to_string(), &mut amount);
+        let mut price = Vec::new();
+        encoding::string::encode(1, &"1.5".to_string(), &mut price);
+        let mut supply = Vec::new();
+        encoding::bytes::encode(1, &vec![0x01, 0x00], &mut supply);
+        let mut token = Vec::new();
+        encoding::string::encode(1, &format!("0x{}", "01".repeat(20)), &mut token);
+
+        let mut data = Vec::new();
+        encoding::bytes::encode(1, &timestamp, &mut data);
+        encoding::bytes::encode(2, &amount, &mut data);
+        encoding::bytes::encode(3, &price, &mut data);
+        encoding::bytes::encode(4, &supply, &mut data);
+        encoding::string::encode(5, &format!("0x{}", "ab".repeat(32)), &mut data);
+        encoding::bytes::encode(6, &token, &mut data);
+        for (key, value) in [("a", 1_u64), ("b", 2_u64)] {
+            let mut entry = Vec::new();
+            encoding::string::encode(1, &key.to_string(), &mut entry);
+            encoding::uint64::encode(2, &value, &mut entry);
+            encoding::bytes::encode(7, &entry, &mut data);
+        }
+        data
+    }
+
+    fn get_decimal(unscaled_value: i128, scale: i32) -> Decimal {
+        Decimal::from_bytes(ByteArray::from(unscaled_value.to_be_bytes().to_vec()), 38, scale)
+    }
+
+    #[test]
+    fn test_well_known_types() {
+        let parquet_config = ParquetConfig {
+            big_numbers: BigNumberType::Decimal { precision: 38, big_decimal_scale: 18 },
+            fixed_length_addresses_and_hashes: true,
+            ..ParquetConfig::default()
+        };
+
+        let mut sink = ParquetFileSink::new_with_config(get_well_known_types_info(), &parquet_config, BlockMetadataColumns::default());
+        sink.process(&mut get_well_known_types_proto_data().as_slice(), &BlockMetadata::from_block_number(1)).unwrap();
+        sink.process(&mut [].as_slice(), &BlockMetadata::from_block_number(2)).unwrap();
+
+        let reader = SerializedFileReader::new(bytes::Bytes::from(sink.make_file())).unwrap();
+        let schema = reader.metadata().file_metadata().schema_descr();
+        assert_eq!(schema.column(1).logical_type(), Some(LogicalType::Timestamp { is_adjusted_to_u_t_c: true, unit: TimeUnit::MICROS(MicroSeconds {}) }));
+        assert_eq!(schema.column(2).logical_type(), Some(LogicalType::Decimal { scale: 0, precision: 38 }));
+        assert_eq!(schema.column(3).logical_type(), Some(LogicalType::Decimal { scale: 18, precision: 38 }));
+        assert_eq!(schema.column(5).type_length(), 32);
+        assert_eq!(schema.column(6).type_length(), 20);
+
+        let rows = reader.get_row_iter(None).unwrap().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 2);
+
+        let row = &rows[0];
+        assert_eq!(row.get_timestamp_micros(1).unwrap(), 1_700_000_000_123_456);
+        assert_eq!(row.get_decimal(2).unwrap(), &get_decimal(123456789012345678901234567890, 0));
+        assert_eq!(row.get_decimal(3).unwrap(), &get_decimal(1_500_000_000_000_000_000, 18));
+        assert_eq!(row.get_decimal(4).unwrap(), &get_decimal(256, 0));
+        assert_eq!(row.get_bytes(5).unwrap().data(), vec![0xab; 32].as_slice());
+        assert_eq!(row.get_bytes(6).unwrap().data(), vec![0x01; 20].as_slice());
+        let balances = row.get_map(7).unwrap().entries().clone();
+        assert_eq!(balances, vec![(Field::Str("a".to_string()), Field::ULong(1)), (Field::Str("b".to_string()), Field::ULong(2))]);
+
+        let default_row = &rows[1];
+        assert_eq!(default_row.get_timestamp_micros(1).unwrap(), 0);
+        assert_eq!(default_row.get_decimal(2).unwrap(), &get_decimal(0, 0));
+        assert!(matches!(default_row.get_column_iter().nth(3).unwrap().1, Field::Null));
+        assert_eq!(default_row.get_bytes(5).unwrap().data(), vec![0; 32].as_slice());
+    }
+
+    #[test]
+    fn test_big_number_string_default() {
+        let mut sink = ParquetFileSink::new(get_well_known_types_info());
+        sink.process(&mut get_well_known_types_proto_data().as_slice(), &BlockMetadata::from_block_number(1)).unwrap();
+
+        let reader = SerializedFileReader::new(bytes::Bytes::from(sink.make_file())).unwrap();
+        let row = reader.get_row_iter(None).unwrap().next().unwrap();
+        assert_eq!(row.get_string(2).unwrap(), "123456789012345678901234567890");
+        assert_eq!(row.get_string(3).unwrap(), "1.5");
+        assert_eq!(row.get_string(4).unwrap(), "256");
+        // Hashes only get stored as fixed length binary when enabled
+        assert_eq!(row.get_string(5).unwrap(), &format!("0x{}", "ab".repeat(32)));
+    }
+
+    #[test]
+    fn test_decimal_overflow_is_an_error() {
+        let parquet_config = ParquetConfig {
+            big_numbers: BigNumberType::Decimal { precision: 10, big_decimal_scale: 2 },
+            ..ParquetConfig::default()
+        };
+
+        let mut sink = ParquetFileSink::new_with_config(get_well_known_types_info(), &parquet_config, BlockMetadataColumns::default());
+        assert!(sink.process(&mut get_well_known_types_proto_data().as_slice(), &BlockMetadata::from_block_number(1)).is_err());
+    }
+
+    #[test]
+    fn test_block_metadata_columns() {
+        #[derive(TestData)]
+        pub struct SimpleStruct {
+            field1: String,
+        }
+
+        let block_metadata_columns = BlockMetadataColumns {
+            block_hash: true,
+            block_timestamp: true,
+            row_index: true,
+        };
+        let mut sink = ParquetFileSink::new_with_config(SimpleStruct::get_proto_structure_info(), &ParquetConfig::default(), block_metadata_columns);
+
+        for row_index in 0..2 {
+            let bytes = SimpleStruct { field1: format!("value{}", row_index) }.to_proto_bytes();
+            let block_metadata = BlockMetadata {
+                block_number: 10,
+                block_hash: "abcd".to_string(),
+                block_timestamp: Some(1_700_000_000),
+                row_index,
+            };
+            sink.process(&mut bytes.as_slice(), &block_metadata).unwrap();
+        }
+
+        let reader = SerializedFileReader::new(bytes::Bytes::from(sink.make_file())).unwrap();
+        let column_names = reader.metadata().file_metadata().schema_descr().columns().iter().map(|column| column.name().to_string()).collect::<Vec<_>>();
+        assert_eq!(column_names, vec!["block_number", "block_hash", "block_timestamp", "row_index", "field1"]);
+
+        for (row_index, row) in reader.get_row_iter(None).unwrap().enumerate() {
+            assert_eq!(row.get_ulong(0).unwrap(), 10);
+            assert_eq!(row.get_string(1).unwrap(), "abcd");
+            assert_eq!(row.get_timestamp_micros(2).unwrap(), 1_700_000_000_000_000);
+            assert_eq!(row.get_ulong(3).unwrap(), row_index as u64);
+            assert_eq!(row.get_string(4).unwrap(), &format!("value{}", row_index));
+        }
+    }
 }
@@ -0,0 +1,148 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::net::TcpListener;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use prost::Message;
+use tonic::Status;
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+
+use crate::streaming_fast::block_client::{SingleBlockRequest, SingleBlockResponse};
+use crate::streaming_fast::block_client::fetch_server::{Fetch, FetchServer};
+use crate::streaming_fast::eth;
+use crate::streaming_fast::streamingfast_dtos::{BlockScopedData, Clock, MapModuleOutput, Request, Response};
+use crate::streaming_fast::streamingfast_dtos::response::Message as ResponseMessage;
+use crate::streaming_fast::streamingfast_dtos::stream_server::{Stream, StreamServer};
+
+/// In-process stand-in for the StreamingFast endpoints which replays recorded responses so that
+/// substreams can be processed end to end without a connection to the real endpoints
+#[derive(Clone)]
+pub(crate) struct MockStreamingServer {
+    responses: Arc<Vec<Response>>,
+    latest_block_number: u64,
+    requests: Arc<Mutex<Vec<Request>>>,
+    /// Failures to inject into upcoming streams, each given as the number of responses to send before failing
+    stream_failures: Arc<Mutex<VecDeque<(usize, Status)>>>,
+}
+
+impl MockStreamingServer {
+    pub(crate) fn new(responses: Vec<Response>, latest_block_number: u64) -> Self {
+        MockStreamingServer {
+            responses: Arc::new(responses),
+            latest_block_number,
+            requests: Arc::new(Mutex::new(Vec::new())),
+            stream_failures: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Makes the next stream request fail with the given status once num_responses responses have been sent. Calling
+    /// this multiple times queues up failures for the streams requested after that
+    pub(crate) fn fail_next_stream_after(&self, num_responses: usize, status: Status) {
+        self.stream_failures.lock().unwrap().push_back((num_responses, status));
+    }
+
+    /// Recordings are expected to be a sequence of length delimited proto encoded responses
+    pub(crate) fn from_recording(recording_path: &Path, latest_block_number: u64) -> Self {
+        let recording = fs::read(recording_path).unwrap();
+        let mut recording_data = recording.as_slice();
+
+        let mut responses = Vec::new();
+        while !recording_data.is_empty() {
+            responses.push(Response::decode_length_delimited(&mut recording_data).unwrap());
+        }
+
+        MockStreamingServer::new(responses, latest_block_number)
+    }
+
+    /// Returns all of the stream requests received so far
+    pub(crate) fn get_requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Starts serving on a free local port and returns the endpoint to use for streaming from it
+    pub(crate) fn start(&self) -> String {
+        let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let incoming = TcpIncoming::new(address, true, None).unwrap();
+
+        let server = Server::builder()
+            .add_service(StreamServer::new(self.clone()))
+            .add_service(FetchServer::new(self.clone()));
+        tokio::spawn(server.serve_with_incoming(incoming));
+
+        format!("http://{}", address)
+    }
+}
+
+#[async_trait]
+impl Stream for MockStreamingServer {
+    type BlocksStream = Pin<Box<dyn futures::Stream<Item = Result<Response, Status>> + Send>>;
+
+    async fn blocks(&self, request: tonic::Request<Request>) -> Result<tonic::Response<Self::BlocksStream>, Status> {
+        let request = request.into_inner();
+        let start_block = request.start_block_num as u64;
+        let stop_block = request.stop_block_num;
+        self.requests.lock().unwrap().push(request);
+
+        // Only the recorded blocks within the requested range get replayed (the stop block being exclusive)
+        let responses = self.responses.iter().filter(|response| {
+            match &response.message {
+                Some(ResponseMessage::BlockScopedData(block_scoped_data)) => {
+                    let block_number = block_scoped_data.clock.as_ref().unwrap().number;
+                    block_number >= start_block && block_number < stop_block
+                }
+                _ => true,
+            }
+        }).cloned().map(Ok).collect::<Vec<_>>();
+
+        let responses = match self.stream_failures.lock().unwrap().pop_front() {
+            Some((num_responses, status)) => responses.into_iter().take(num_responses).chain(std::iter::once(Err(status))).collect(),
+            None => responses,
+        };
+
+        Ok(tonic::Response::new(Box::pin(futures::stream::iter(responses))))
+    }
+}
+
+#[async_trait]
+impl Fetch for MockStreamingServer {
+    async fn block(&self, _request: tonic::Request<SingleBlockRequest>) -> Result<tonic::Response<SingleBlockResponse>, Status> {
+        let block = eth::Block {
+            number: self.latest_block_number,
+            ..Default::default()
+        };
+
+        Ok(tonic::Response::new(SingleBlockResponse {
+            block: Some(prost_types::Any {
+                type_url: "type.googleapis.com/sf.ethereum.type.v2.Block".to_string(),
+                value: block.encode_to_vec(),
+            }),
+        }))
+    }
+}
+
+/// Creates the response the server would send for a final block with the given module output
+pub(crate) fn get_block_response(block_number: u64, module_name: &str, output_data: Vec<u8>) -> Response {
+    Response {
+        message: Some(ResponseMessage::BlockScopedData(BlockScopedData {
+            output: Some(MapModuleOutput {
+                name: module_name.to_string(),
+                map_output: Some(prost_types::Any {
+                    type_url: String::new(),
+                    value: output_data,
+                }),
+                ..Default::default()
+            }),
+            clock: Some(Clock {
+                id: format!("{:064x}", block_number),
+                number: block_number,
+                timestamp: None,
+            }),
+            cursor: format!("cursor_{}", block_number),
+            final_block_height: block_number,
+            ..Default::default()
+        })),
+    }
+}
@@ -9,6 +9,16 @@ pub(crate) mod process_substream;
 pub(crate) mod split_files_sink;
 pub(crate) mod single_file_sink;
 pub(crate) mod multiple_files_sink;
+pub(crate) mod output_modules_sink;
+pub(crate) mod output_schema;
 pub(crate) mod block_client;
-pub(crate) mod eth;
 pub(crate) mod streaming_config;
+pub(crate) mod compaction;
+pub(crate) mod deployment_params;
+pub(crate) mod storage;
+pub(crate) mod processing_error;
+
+#[cfg(test)]
+pub(crate) mod eth;
+#[cfg(test)]
+pub(crate) mod mock_streaming_server;
@@ -1,12 +1,20 @@
 use crate::streaming_fast::file::{File, Location};
+use crate::streaming_fast::file_sinks::file_sink::BlockMetadata;
 use async_trait::async_trait;
 
 #[async_trait]
 pub(crate) trait MultipleFilesSink {
-    fn process(&mut self, proto_data: &mut &[u8], block_number: i64) -> Result<Vec<File>, String>;
+    fn process(&mut self, proto_data: &mut &[u8], block_metadata: &BlockMetadata) -> Result<(), String>;
+    /// Called once the whole output of a block has been processed. Returns the files that have reached the right size,
+    /// which only get closed off here so that every file ends on a block boundary
+    fn notify_block_end(&mut self, block_number: i64) -> Vec<File>;
     fn flush_leftovers(&mut self, block_number: i64) -> Vec<File>;
     fn get_output_folder_locations(&self) -> Vec<Location>;
-    fn get_bucket_name(&self) -> Option<String>;
-    fn notify_new_block(&mut self, block_number: i64);
-    async fn set_starting_block_number(&mut self, starting_block_number: i64);
+    /// Called before the output of each block is processed. Returns any files that had to be closed off early
+    /// because the block falls into a new partition, or an error if the block's partition can't be worked out
+    fn notify_new_block(&mut self, block_number: i64, block_timestamp: Option<i64>) -> Result<Vec<File>, String>;
+    /// Returns an error if the existing output needed to work out where each file sink resumes from can't be listed
+    async fn set_starting_block_number(&mut self, starting_block_number: i64) -> Result<(), String>;
+    /// Checks that the output about to be written can be read alongside the files already in the output folders
+    async fn check_output_schema_compatibility(&self) -> Result<(), String>;
 }
\ No newline at end of file
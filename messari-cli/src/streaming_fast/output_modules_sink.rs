@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use crate::streaming_fast::file::{CursorInfo, File, Location};
+use crate::streaming_fast::sink::Sink;
+use crate::streaming_fast::streaming_fast_utils::{audit_output_folder, get_initial_block_for_module, get_start_position, OutputFolderAudit, StartPosition};
+use crate::streaming_fast::streamingfast_dtos::Package;
+
+/// Feeds the output of each of the modules streamed together into it's own sink. Much like SplitFilesSink does for
+/// oneof fields, each sink keeps track of it's own starting block and skips over any blocks before it, meaning sinks
+/// that are further ahead than others don't process the same blocks twice
+pub(crate) struct OutputModulesSink {
+    module_sinks: Vec<ModuleSink>,
+}
+
+struct ModuleSink {
+    module_name: String,
+    proto_type_name: String,
+    sink: Sink,
+    starting_block_number: i64,
+}
+
+impl OutputModulesSink {
+    /// Takes a sink for each output module in the form -> (module_name, sink, proto_type_name)
+    pub(crate) fn new(sinks: Vec<(String, Sink, String)>) -> Result<Self, String> {
+        let mut module_names_for_type = HashMap::new();
+        for (module_name, _, proto_type_name) in sinks.iter() {
+            if let Some(other_module_name) = module_names_for_type.insert(proto_type_name.clone(), module_name.clone()) {
+                return Err(format!("Output modules: {} and {} both output type: {}! Modules with the same output type would write to the same output folder so they can't be processed together!", other_module_name, module_name, proto_type_name));
+            }
+        }
+
+        Ok(OutputModulesSink {
+            module_sinks: sinks.into_iter().map(|(module_name, sink, proto_type_name)| ModuleSink {
+                module_name,
+                proto_type_name,
+                sink,
+                starting_block_number: 0, // 0 set initially as a dummy value - will be overwritten later on
+            }).collect(),
+        })
+    }
+
+    pub(crate) fn get_module_names(&self) -> Vec<String> {
+        self.module_sinks.iter().map(|module_sink| module_sink.module_name.clone()).collect()
+    }
+
+    /// Returns the output folders of each module sink in the form -> (module_name, output_folder_locations)
+    pub(crate) fn get_output_folder_locations(&self) -> Vec<(String, Vec<Location>)> {
+        self.module_sinks.iter().map(|module_sink| (module_sink.module_name.clone(), module_sink.sink.get_output_folder_locations())).collect()
+    }
+
+    /// Returns where each of the module sinks would resume from (in the same order as get_module_names). Sinks
+    /// without any output yet start from their module's initial block
+    pub(crate) async fn get_start_positions(&self, package: &Package) -> Result<Vec<StartPosition>, String> {
+        let mut start_positions = Vec::new();
+        for module_sink in self.module_sinks.iter() {
+            let fallback_starting_block = get_initial_block_for_module(package, &module_sink.proto_type_name);
+            start_positions.push(get_start_position(module_sink.sink.get_output_folder_locations(), fallback_starting_block).await?);
+        }
+        Ok(start_positions)
+    }
+
+    /// Audits each of the output folders of each module sink in the form -> (module_name, output_folder_audits)
+    pub(crate) async fn audit_output_folders(&self, package: &Package, count_rows: bool) -> Result<Vec<(String, Vec<OutputFolderAudit>)>, String> {
+        let mut module_audits = Vec::new();
+        for module_sink in self.module_sinks.iter() {
+            let initial_block = get_initial_block_for_module(package, &module_sink.proto_type_name);
+
+            let mut output_folder_audits = Vec::new();
+            for output_folder_location in module_sink.sink.get_output_folder_locations() {
+                output_folder_audits.push(audit_output_folder(&output_folder_location, initial_block, count_rows).await?);
+            }
+            module_audits.push((module_sink.module_name.clone(), output_folder_audits));
+        }
+        Ok(module_audits)
+    }
+
+    /// Takes a starting block number for each module sink (in the same order as get_module_names)
+    pub(crate) async fn set_starting_block_numbers(&mut self, starting_block_numbers: Vec<i64>) -> Result<(), String> {
+        for (module_sink, starting_block_number) in self.module_sinks.iter_mut().zip(starting_block_numbers.into_iter()) {
+            module_sink.sink.set_starting_block_number(starting_block_number).await?;
+            module_sink.starting_block_number = starting_block_number;
+        }
+        Ok(())
+    }
+
+    /// Makes sure none of the sinks would append files with a different schema to an output folder, as a folder holding
+    /// mixed schemas can't be queried as a single table
+    pub(crate) async fn check_output_schema_compatibility(&self) -> Result<(), String> {
+        for module_sink in self.module_sinks.iter() {
+            module_sink.sink.check_output_schema_compatibility().await.map_err(|error| format!("Module: {} - {}", module_sink.module_name, error))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn enable_live_mode(&mut self) {
+        for module_sink in self.module_sinks.iter_mut() {
+            module_sink.sink.enable_live_mode();
+        }
+    }
+
+    /// Passes the output of each module on to it's sink. Modules without any output for the block are given an
+    /// empty output
+    pub(crate) fn process(&mut self, module_outputs: Vec<(String, Vec<u8>)>, cursor_info: CursorInfo, block_hash: String, block_timestamp: Option<i64>, final_block_height: i64) -> Result<Vec<File>, String> {
+        let mut module_outputs = module_outputs.into_iter().collect::<HashMap<_, _>>();
+
+        let mut output_files = Vec::new();
+        for module_sink in self.module_sinks.iter_mut() {
+            if cursor_info.block_number < module_sink.starting_block_number {
+                continue;
+            }
+
+            let output_data = module_outputs.remove(&module_sink.module_name).unwrap_or_default();
+            output_files.extend(module_sink.sink.process(output_data, cursor_info.clone(), block_hash.clone(), block_timestamp, final_block_height)?);
+        }
+
+        Ok(output_files)
+    }
+
+    pub(crate) fn undo(&mut self, last_valid_block: i64) -> Result<(), String> {
+        for module_sink in self.module_sinks.iter_mut() {
+            module_sink.sink.undo(last_valid_block)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn flush_leftovers(&mut self, block_number: i64) -> Vec<File> {
+        self.module_sinks.iter_mut().flat_map(|module_sink| module_sink.sink.flush_leftovers(block_number)).collect()
+    }
+}
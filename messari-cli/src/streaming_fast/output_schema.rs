@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use bytes::Bytes;
+use derives::proto_structure_info::{FieldSpecification, MessageInfo};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::schema::types::{ColumnDescriptor, SchemaDescriptor, TypePtr};
+
+use crate::streaming_fast::file_sinks::parquet::ParquetFileSink;
+use crate::streaming_fast::process_substream::EncodingType;
+use crate::streaming_fast::sink::OutputLayout;
+use crate::streaming_fast::single_file_sink::SingleFileSink;
+use crate::streaming_fast::streaming_config::{BlockMetadataColumns, ParquetConfig};
+
+/// Schema of one of the entities a module outputs. A module outputs a single entity unless it's output (or the items
+/// of it's output) is a oneof, in which case each oneof field gets written to it's own folder (see OutputLayout)
+pub(crate) struct EntitySchema {
+    pub(crate) entity_name: String,
+    /// Folder the entity's parquet files get written to (before any partition folders)
+    pub(crate) output_folder: PathBuf,
+    pub(crate) parquet_schema: TypePtr,
+}
+
+/// Lays out the entities with the same OutputLayout and output folders as Sink::new
+pub(crate) fn get_entity_schemas(output_type_info: MessageInfo, sink_output_path: PathBuf, parquet_config: &ParquetConfig, block_metadata_columns: BlockMetadataColumns) -> Vec<EntitySchema> {
+    let (output_layout, _) = OutputLayout::new(output_type_info);
+    let encoding_type = EncodingType::Parquet(parquet_config.clone());
+
+    output_layout.get_entity_type_infos().into_iter().map(|mut entity_type_info| {
+        entity_type_info.field_specification = FieldSpecification::Required;
+        entity_type_info.assert_fields_not_manually_specified(&block_metadata_columns.get_column_names());
+
+        let entity_name = entity_type_info.type_name.clone();
+        let parquet_schema = ParquetFileSink::new_with_config(entity_type_info, parquet_config, block_metadata_columns).get_parquet_schema();
+
+        EntitySchema {
+            output_folder: SingleFileSink::get_output_folder(&sink_output_path, &entity_name, &encoding_type),
+            entity_name,
+            parquet_schema,
+        }
+    }).collect()
+}
+
+/// Returns each leaf column in the form -> column_path: column_type
+fn get_column_types(schema_descriptor: &SchemaDescriptor) -> BTreeMap<String, String> {
+    schema_descriptor.columns().iter().map(|column| {
+        (column.path().string(), get_column_type(column))
+    }).collect()
+}
+
+/// Physical and logical type of the column along with it's levels, as a change in either of the levels changes how
+/// the column has to be read
+fn get_column_type(column: &ColumnDescriptor) -> String {
+    let logical_type = match column.logical_type() {
+        Some(logical_type) => format!(" {:?}", logical_type),
+        None => match column.converted_type() {
+            parquet::basic::ConvertedType::NONE => String::new(),
+            converted_type => format!(" {}", converted_type),
+        },
+    };
+    let type_length = if column.type_length() > 0 {
+        format!("({})", column.type_length())
+    } else {
+        String::new()
+    };
+
+    format!("{}{}{} (max definition level: {}, max repetition level: {})", column.physical_type(), type_length, logical_type, column.max_def_level(), column.max_rep_level())
+}
+
+pub(crate) enum SchemaChange {
+    AddedEntity(String),
+    RemovedEntity(String),
+    AddedColumn {
+        entity_name: String,
+        column_path: String,
+    },
+    RemovedColumn {
+        entity_name: String,
+        column_path: String,
+    },
+    ChangedColumn {
+        entity_name: String,
+        column_path: String,
+        old_type: String,
+        new_type: String,
+    },
+}
+
+impl SchemaChange {
+    /// Whether data written with the new schema can't be read alongside data written with the old schema
+    pub(crate) fn is_breaking(&self) -> bool {
+        match self {
+            SchemaChange::AddedEntity(_) | SchemaChange::AddedColumn { .. } => false,
+            SchemaChange::RemovedEntity(_) | SchemaChange::RemovedColumn { .. } | SchemaChange::ChangedColumn { .. } => true,
+        }
+    }
+}
+
+impl Display for SchemaChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let breaking_tag = if self.is_breaking() { "[BREAKING] " } else { "" };
+        match self {
+            SchemaChange::AddedEntity(entity_name) => write!(f, "{}Added entity: {}", breaking_tag, entity_name),
+            SchemaChange::RemovedEntity(entity_name) => write!(f, "{}Removed entity: {}", breaking_tag, entity_name),
+            SchemaChange::AddedColumn { entity_name, column_path } => write!(f, "{}Added column: {}.{}", breaking_tag, entity_name, column_path),
+            SchemaChange::RemovedColumn { entity_name, column_path } => write!(f, "{}Removed column: {}.{}", breaking_tag, entity_name, column_path),
+            SchemaChange::ChangedColumn { entity_name, column_path, old_type, new_type } => write!(f, "{}Changed column: {}.{} from: {} to: {}", breaking_tag, entity_name, column_path, old_type, new_type),
+        }
+    }
+}
+
+/// Entities are matched up by name rather than by output folder as the output folder changes with the spkg version
+pub(crate) fn diff_entity_schemas(old_entity_schemas: &[EntitySchema], new_entity_schemas: &[EntitySchema]) -> Vec<SchemaChange> {
+    let mut schema_changes = Vec::new();
+
+    for old_entity_schema in old_entity_schemas.iter() {
+        let entity_name = &old_entity_schema.entity_name;
+        let new_entity_schema = match new_entity_schemas.iter().find(|new_entity_schema| &new_entity_schema.entity_name == entity_name) {
+            Some(new_entity_schema) => new_entity_schema,
+            None => {
+                schema_changes.push(SchemaChange::RemovedEntity(entity_name.clone()));
+                continue;
+            }
+        };
+
+        let old_schema_descriptor = SchemaDescriptor::new(old_entity_schema.parquet_schema.clone());
+        let new_schema_descriptor = SchemaDescriptor::new(new_entity_schema.parquet_schema.clone());
+        schema_changes.extend(get_column_changes(entity_name, &old_schema_descriptor, &new_schema_descriptor));
+    }
+
+    for new_entity_schema in new_entity_schemas.iter() {
+        if !old_entity_schemas.iter().any(|old_entity_schema| old_entity_schema.entity_name == new_entity_schema.entity_name) {
+            schema_changes.push(SchemaChange::AddedEntity(new_entity_schema.entity_name.clone()));
+        }
+    }
+
+    schema_changes
+}
+
+fn get_column_changes(entity_name: &str, old_schema_descriptor: &SchemaDescriptor, new_schema_descriptor: &SchemaDescriptor) -> Vec<SchemaChange> {
+    let old_column_types = get_column_types(old_schema_descriptor);
+    let new_column_types = get_column_types(new_schema_descriptor);
+
+    let mut column_changes = Vec::new();
+    for (column_path, old_type) in old_column_types.iter() {
+        match new_column_types.get(column_path) {
+            None => column_changes.push(SchemaChange::RemovedColumn {
+                entity_name: entity_name.to_string(),
+                column_path: column_path.clone(),
+            }),
+            Some(new_type) if new_type != old_type => column_changes.push(SchemaChange::ChangedColumn {
+                entity_name: entity_name.to_string(),
+                column_path: column_path.clone(),
+                old_type: old_type.clone(),
+                new_type: new_type.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for column_path in new_column_types.keys().filter(|column_path| !old_column_types.contains_key(*column_path)) {
+        column_changes.push(SchemaChange::AddedColumn {
+            entity_name: entity_name.to_string(),
+            column_path: column_path.clone(),
+        });
+    }
+
+    column_changes
+}
+
+/// Compares the schema stored in the footer of an existing parquet file against the schema that's about to be written
+/// alongside it. Only the breaking changes are returned as files with extra columns can still be read together
+pub(crate) fn get_breaking_changes_from_file(entity_name: &str, existing_file_data: Vec<u8>, parquet_schema: &TypePtr) -> Result<Vec<SchemaChange>, String> {
+    let reader = SerializedFileReader::new(Bytes::from(existing_file_data)).map_err(|error| format!("Unable to read parquet footer! Error: {}", error))?;
+    let existing_schema_descriptor = reader.metadata().file_metadata().schema_descr();
+    let schema_descriptor = SchemaDescriptor::new(parquet_schema.clone());
+
+    Ok(get_column_changes(entity_name, existing_schema_descriptor, &schema_descriptor).into_iter().filter(|schema_change| schema_change.is_breaking()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use derives::{GenRandSamples, ProtoInfo, TestData};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::streaming_fast::file_sinks::file_sink::{BlockMetadata, FileSink};
+    use crate::streaming_fast::file_sinks::parquet::ParquetFileSink;
+    use crate::streaming_fast::output_schema::{diff_entity_schemas, get_breaking_changes_from_file, get_entity_schemas, SchemaChange};
+    use crate::streaming_fast::streaming_config::{BlockMetadataColumns, ParquetConfig};
+
+    #[test]
+    fn test_diff_entity_schemas() {
+        mod old_version {
+            use derives::TestData;
+
+            #[derive(TestData)]
+            pub struct Transfer {
+                pub from: String,
+                pub amount: u64,
+                pub memo: String,
+            }
+        }
+
+        mod new_version {
+            use derives::TestData;
+
+            #[derive(TestData)]
+            pub struct Transfer {
+                pub from: String,
+                pub amount: String,
+                pub fee: Option<u64>,
+            }
+        }
+
+        let get_schemas = |type_info| get_entity_schemas(type_info, PathBuf::from("test/v1/0_1_0"), &ParquetConfig::default(), BlockMetadataColumns::default());
+        let old_schemas = get_schemas(old_version::Transfer::get_proto_structure_info());
+        let new_schemas = get_schemas(new_version::Transfer::get_proto_structure_info());
+        assert_eq!(new_schemas[0].output_folder, PathBuf::from("test/v1/0_1_0/Transfer/parquet"));
+
+        let schema_changes = diff_entity_schemas(&old_schemas, &new_schemas);
+        let schema_change_descriptions = schema_changes.iter().map(|schema_change| (schema_change.is_breaking(), schema_change.to_string())).collect::<Vec<_>>();
+        assert!(matches!(&schema_changes[0], SchemaChange::ChangedColumn { column_path, .. } if column_path == "amount"));
+        assert_eq!(schema_change_descriptions[1..].to_vec(), vec![
+            (true, "[BREAKING] Removed column: Transfer.memo".to_string()),
+            (false, "Added column: Transfer.fee".to_string()),
+        ]);
+
+        assert!(diff_entity_schemas(&old_schemas, &old_schemas).is_empty());
+
+        // The schema read back from the footer of a written file should match up with the schema it was written with
+        let mut parquet_sink = ParquetFileSink::new_with_config(old_version::Transfer::get_proto_structure_info(), &ParquetConfig::default(), BlockMetadataColumns::default());
+        for sample in old_version::Transfer::get_samples(10, &mut StdRng::seed_from_u64(42)) {
+            parquet_sink.process(&mut sample.to_proto_bytes().as_slice(), &BlockMetadata::from_block_number(1)).unwrap();
+        }
+        let existing_file_data = parquet_sink.make_file();
+
+        assert!(get_breaking_changes_from_file("Transfer", existing_file_data.clone(), &old_schemas[0].parquet_schema).unwrap().is_empty());
+        let breaking_changes = get_breaking_changes_from_file("Transfer", existing_file_data, &new_schemas[0].parquet_schema).unwrap();
+        assert_eq!(breaking_changes.len(), 2);
+        assert!(breaking_changes.iter().all(|schema_change| schema_change.is_breaking()));
+    }
+
+    #[test]
+    fn test_oneof_outputs_are_split_into_entities() {
+        #[derive(TestData)]
+        pub struct Deposit {
+            amount: u64,
+        }
+
+        #[derive(TestData)]
+        pub struct Withdraw {
+            amount: u64,
+        }
+
+        #[derive(TestData)]
+        pub enum Event {
+            Deposit(Deposit),
+            Withdraw(Withdraw),
+        }
+
+        #[derive(TestData)]
+        pub struct Events {
+            items: Vec<Event>,
+        }
+
+        let entity_schemas = get_entity_schemas(Events::get_proto_structure_info(), PathBuf::new(), &ParquetConfig::default(), BlockMetadataColumns::default());
+        let entity_names = entity_schemas.iter().map(|entity_schema| entity_schema.entity_name.as_str()).collect::<Vec<_>>();
+        assert_eq!(entity_names, vec!["Deposit", "Withdraw"]);
+
+        let removed_entity_schemas = diff_entity_schemas(&entity_schemas, &entity_schemas[..1]);
+        assert_eq!(removed_entity_schemas.iter().map(|schema_change| schema_change.to_string()).collect::<Vec<_>>(), vec!["[BREAKING] Removed entity: Withdraw"]);
+    }
+}
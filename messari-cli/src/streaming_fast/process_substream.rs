@@ -1,42 +1,51 @@
-use std::env;
 use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::Arc;
+use derives::proto_structure_info::MessageInfo;
 use futures::StreamExt;
 use prost::Message;
 use regex::Regex;
-use tonic::metadata::MetadataValue;
-use tonic::Status;
-use tonic::transport::Channel;
+use tokio::sync::mpsc;
 
 use crate::streaming_fast::block_client::get_latest_block_number;
 use crate::streaming_fast::streamingfast_dtos;
-use crate::streaming_fast::file::LocationType;
-use crate::streaming_fast::sink::Sink;
+use crate::streaming_fast::file::{CursorInfo, File, LocationType};
+use crate::streaming_fast::file_sinks::csv::RepeatedFieldPolicy;
+use crate::streaming_fast::processing_error::{FailureReason, is_transient_status, ProcessingError};
+use crate::streaming_fast::output_modules_sink::OutputModulesSink;
+use crate::streaming_fast::sink::{OutputLayout, Sink};
+use crate::streaming_fast::storage::{get_storage_backend, StorageBackend};
 use crate::streaming_fast::streamingfast_dtos::{Package, Request, Response};
 use crate::streaming_fast::proto_structure_info::get_output_type_info;
-use crate::streaming_fast::streaming_config::{Chain, StreamingConfig};
-use crate::streaming_fast::streaming_fast_utils::{get_initial_block_for_module, get_start_block_num};
+use crate::streaming_fast::streaming_config::{BlockMetadataColumns, Chain, ParquetConfig, PartitionScheme, RetryConfig, StorageConfig, StreamingConfig, StreamingEndpoint};
+use crate::streaming_fast::streaming_fast_utils::{delete_files_after_latest_cursor, OutputFolderAudit, StartPosition};
+use crate::streaming_fast::compaction::{compact_output_folder, MergedFiles};
+use crate::streaming_fast::streamingfast_dtos::module::Kind;
 use crate::streaming_fast::streamingfast_dtos::module::input::Input;
 
-pub(crate) async fn process_substream(spkg: Vec<u8>, config: StreamingConfig, encoding_type: EncodingType, location_type: LocationType, data_location_path: Option<PathBuf>, bucket_name: Option<String>, start_block_arg: Option<i64>, stop_block_arg: Option<u64>) {
-    let mut package = Package::decode(spkg.as_slice()).unwrap();
+pub(crate) async fn process_substream(spkg: Vec<u8>, config: StreamingConfig, encoding_type: EncodingType, location_type: LocationType, data_location_path: Option<PathBuf>, bucket_name: Option<String>, start_block_arg: Option<i64>, stop_block_arg: Option<u64>, live: bool) -> Result<(), ProcessingError> {
+    let mut package = Package::decode(spkg.as_slice()).map_err(|error| FailureReason::InvalidSpkg(error.to_string()))?;
 
-    let chain = if let Some(chain_override) = config.chain_override {
+    let chain = if let Some(chain_override) = config.get_chain_override() {
         for module in package.modules.as_mut().unwrap().modules.iter_mut() {
             for input in module.inputs.iter_mut() {
                 if let Input::Source(source) = input.input.as_mut().unwrap() {
                     if source.r#type.ends_with(".Block") {
-                        source.r#type = chain_override.get_proto_block_type();
+                        source.r#type = chain_override.block_type.clone();
                     }
                 }
             }
         }
         chain_override
     } else {
-        get_chain_info(&package)
+        get_chain_info(&package, &config.chains).map_err(FailureReason::Config)?
     };
 
-    let (mut sink, proto_type_name) = get_sink_and_proto_type_name(&package, config.output_module.as_str(), encoding_type, location_type, data_location_path, bucket_name, &chain);
+    let output_modules = config.get_output_modules();
+    let streamed_module = get_streamed_module(&package, &output_modules);
+
+    let storage = get_storage_backend(location_type.clone(), bucket_name, &config.storage).await.map_err(FailureReason::Config)?;
+    let mut sink = get_output_modules_sink(&package, &output_modules, encoding_type, location_type, storage, data_location_path, config.partition.clone(), config.block_metadata_columns, &chain).map_err(FailureReason::Config)?;
 
     if let Some(substream_name) = config.substream_name_override {
         package.package_meta.iter_mut().next().unwrap().name = substream_name;
@@ -58,90 +67,303 @@ pub(crate) async fn process_substream(spkg: Vec<u8>, config: StreamingConfig, en
         }
     }
 
-    let (start_block, stop_block) = get_block_range(&sink, &package, &proto_type_name, &chain, start_block_arg, stop_block_arg).await;
+    let endpoint = StreamingEndpoint::new(&chain, config.endpoint_override).map_err(FailureReason::Config)?;
+    let start_positions = sink.get_start_positions(&package).await.map_err(FailureReason::Storage)?;
+    let (start_block, stop_block, start_cursor) = get_block_range(&start_positions, &endpoint, start_block_arg, stop_block_arg).await.map_err(FailureReason::BlockRange)?;
+    let block_range = Some((start_block, stop_block));
+
+    let streamed_module = match streamed_module {
+        Ok(streamed_module) => streamed_module,
+        Err(error) => return Err(ProcessingError {
+            reason: FailureReason::OutputModules(error),
+            block_range,
+            last_processed_block: None,
+        }),
+    };
+    if output_modules.len() > 1 {
+        println!("Streaming output modules: {:?} through module: {} outside of production mode. Large block ranges will take a lot longer than giving each module it's own run!", output_modules, streamed_module);
+    }
 
-    sink.set_starting_block_number(start_block).await;
+    if let Err(error) = sink.check_output_schema_compatibility().await {
+        return Err(ProcessingError {
+            reason: FailureReason::IncompatibleSchema(error),
+            block_range,
+            last_processed_block: None,
+        });
+    }
+
+    // Unless a start block is given, each module's sink resumes from wherever it got up to
+    let starting_block_numbers = match start_block_arg {
+        Some(_) => vec![start_block; start_positions.len()],
+        None => start_positions.iter().map(|start_position| start_position.block_number).collect(),
+    };
+    if let Err(error) = sink.set_starting_block_numbers(starting_block_numbers).await {
+        return Err(ProcessingError {
+            reason: FailureReason::Storage(error),
+            block_range,
+            last_processed_block: None,
+        });
+    }
+
+    // Unless a start block is given, the files written after each sink's cursor get written again. This only happens
+    // once everything else that could stop the run has been checked, so a run that fails to start never loses files
+    if start_block_arg.is_none() {
+        for (_, output_folder_locations) in sink.get_output_folder_locations() {
+            for output_folder_location in output_folder_locations.iter() {
+                match delete_files_after_latest_cursor(output_folder_location).await {
+                    Ok(deleted_files) => {
+                        for deleted_file in deleted_files.iter() {
+                            println!("Deleted file: {} as it's written after the cursor streaming resumes from", output_folder_location.get_storage().get_display_path(deleted_file));
+                        }
+                    },
+                    Err(error) => return Err(ProcessingError {
+                        reason: FailureReason::Storage(error),
+                        block_range,
+                        last_processed_block: None,
+                    }),
+                }
+            }
+        }
+    }
+    if live {
+        sink.enable_live_mode();
+    }
+
+    if start_cursor.is_some() {
+        println!("Resuming stream from saved cursor at block: {}", start_block);
+    }
 
     let request = Request {
         start_block_num: start_block,
-        start_cursor: "".to_string(),
+        start_cursor: start_cursor.unwrap_or_default(),
         stop_block_num: stop_block as u64,
         modules: package.modules,
-        production_mode: true,
-        final_blocks_only: true,
-        output_module: config.output_module,
+        // The outputs of the other modules only get sent outside of production mode, which means giving up on it's
+        // parallel execution of the blocks ahead of the stream
+        production_mode: output_modules.len() == 1,
+        final_blocks_only: !live,
+        output_module: streamed_module,
         debug_initial_store_snapshot_for_modules: vec![],
     };
 
-    let streamingfast_token = env::var("SUBSTREAMS_API_TOKEN").unwrap();
-    let token_metadata = MetadataValue::try_from(streamingfast_token.as_str()).unwrap();
-
-    let mut client = streamingfast_dtos::stream_client::StreamClient::with_interceptor(
-        Channel::builder(chain.get_endpoint()).connect_lazy(),
-        move |mut r: tonic::Request<()>| {
-            r.metadata_mut().insert("authorization", token_metadata.clone());
-            Ok(r)
-        },
-    );
-
-    let response_stream = client.blocks(request).await.unwrap();
-    let mut block_stream = response_stream.into_inner();
+    // Reading the stream, processing blocks in the sink and uploading files each run in their own task, connected by
+    // bounded channels so that slow uploads only hold up the stream once the buffers in between have filled up
+    let (block_sender, mut block_receiver) = mpsc::channel(config.pipeline.block_buffer_size);
+    let (file_sender, file_receiver) = mpsc::channel(config.pipeline.file_buffer_size);
+    let stream_task = tokio::spawn(stream_blocks(endpoint, request, config.retry, block_sender));
+    let upload_task = tokio::spawn(upload_files(file_receiver, config.pipeline.upload_concurrency));
 
+    let mut last_processed_block = None;
+    let mut sink_failure = None;
     let mut num_block = 1;
 
-    // TODO: Change the logic below into buffered streams in a select to prevent
-    // TODO: downloading data and writing files blocking one another
-    while let Some(block) = block_stream.next().await {
-        if let Some((output_data, block_number)) = get_output_data(block).unwrap() {
-            num_block += 1;
-            if output_data.len() > 0 {
-                println!("Num block: {}, Block process: {}, data size: {}", num_block,block_number, output_data.len());
+    'process: while let Some(block_message) = block_receiver.recv().await {
+        let files = match block_message {
+            BlockMessage::Data { module_outputs, cursor_info, block_hash, block_timestamp, final_block_height } => {
+                num_block += 1;
+                let data_size = module_outputs.iter().map(|(_, output_data)| output_data.len()).sum::<usize>();
+                if data_size > 0 {
+                    println!("Num block: {}, Block process: {}, data size: {}", num_block, cursor_info.block_number, data_size);
+                }
+
+                let block_number = cursor_info.block_number;
+                match sink.process(module_outputs, cursor_info, block_hash, block_timestamp, final_block_height) {
+                    Ok(files) => {
+                        last_processed_block = Some(block_number);
+                        files
+                    }
+                    Err(error) => {
+                        sink_failure = Some(FailureReason::Sink(error));
+                        break;
+                    }
+                }
             }
-            match sink.process(output_data, block_number) {
-                Ok(files) => {
-                    futures::future::join_all(files.into_iter().map(|file| file.save())).await;
+            BlockMessage::Undo { last_valid_block } => {
+                if let Err(error) = sink.undo(last_valid_block) {
+                    sink_failure = Some(FailureReason::Sink(error));
+                    break;
                 }
-                Err(error) => {
-                    // TODO: Flesh the error out and return it rather than panicking
-                    panic!("{}", error);
+                Vec::new()
+            }
+        };
+
+        for file in files {
+            if file_sender.send(file).await.is_err() {
+                // The upload task has failed - it's error gets picked up below
+                break 'process;
+            }
+        }
+    }
+
+    let stream_result = if sink_failure.is_some() {
+        stream_task.abort();
+        Ok(())
+    } else {
+        drop(block_receiver);
+        stream_task.await.unwrap_or_else(|error| Err(FailureReason::Pipeline(format!("Stream task stopped unexpectedly! Error: {}", error))))
+    };
+
+    // Whatever was built up before the stream failed is still valid, so it gets saved (along with it's cursor) to
+    // avoid having to stream it again. This isn't the case if the sink itself failed mid-block
+    let leftover_files = match (&stream_result, last_processed_block) {
+        _ if sink_failure.is_some() => Vec::new(),
+        (Ok(()), _) => sink.flush_leftovers(stop_block),
+        (Err(reason), Some(last_processed_block)) if reason.is_sink_intact() => sink.flush_leftovers(last_processed_block),
+        (Err(_), _) => Vec::new(),
+    };
+    for file in leftover_files {
+        if file_sender.send(file).await.is_err() {
+            break;
+        }
+    }
+
+    drop(file_sender);
+    let upload_result = upload_task.await.unwrap_or_else(|error| Err(format!("Upload task stopped unexpectedly! Error: {}", error)));
+
+    let failure_reason = sink_failure.or(upload_result.err().map(FailureReason::Storage)).or(stream_result.err());
+    match failure_reason {
+        Some(reason) => Err(ProcessingError {
+            reason,
+            block_range,
+            last_processed_block,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Reads blocks from the stream and passes them on to the sink. Transient failures get reconnected to with a backoff,
+/// resuming from the last block received (which the sink still holds in memory so nothing needs to be rolled back)
+async fn stream_blocks(endpoint: StreamingEndpoint, mut request: Request, retry_config: RetryConfig, block_sender: mpsc::Sender<BlockMessage>) -> Result<(), FailureReason> {
+    let mut client = streamingfast_dtos::stream_client::StreamClient::with_interceptor(endpoint.get_channel(), endpoint.get_interceptor());
+    let stop_block = request.stop_block_num as i64;
+    let mut retry_attempts = 0;
+
+    loop {
+        let stream_status = match client.blocks(request.clone()).await {
+            Ok(response_stream) => {
+                let mut block_stream = response_stream.into_inner();
+                loop {
+                    let response = match block_stream.next().await {
+                        Some(Ok(response)) => response,
+                        Some(Err(status)) => break Some(status),
+                        None => break None,
+                    };
+
+                    let block_message = match get_block_message(response) {
+                        Ok(Some(block_message)) => block_message,
+                        Ok(None) => continue,
+                        Err(error) => return Err(FailureReason::InvalidResponse(error)),
+                    };
+
+                    if let BlockMessage::Data { cursor_info, .. } = &block_message {
+                        retry_attempts = 0;
+                        request.start_block_num = cursor_info.block_number + 1;
+                        request.start_cursor = cursor_info.cursor.clone();
+                    }
+
+                    if block_sender.send(block_message).await.is_err() {
+                        // Processing has stopped early so the reason for it gets reported from there instead
+                        return Ok(());
+                    }
                 }
             }
+            Err(status) => Some(status),
+        };
+
+        let error_message = match stream_status {
+            None if request.start_block_num >= stop_block => return Ok(()),
+            None => format!("Stream ended with blocks: {} -> {} still left to process!", request.start_block_num, stop_block),
+            Some(status) if is_transient_status(&status) => format!("{:?} - {}", status.code(), status.message()),
+            Some(status) => return Err(FailureReason::Stream(status)),
+        };
+
+        retry_attempts += 1;
+        if retry_attempts > retry_config.max_attempts {
+            return Err(FailureReason::RetriesExhausted {
+                attempts: retry_config.max_attempts,
+                last_error: error_message,
+            });
         }
+
+        let backoff = retry_config.get_backoff(retry_attempts);
+        println!("Stream interrupted! Error: {}\nReconnecting from block: {} in {}ms (attempt {}/{})", error_message, request.start_block_num, backoff.as_millis(), retry_attempts, retry_config.max_attempts);
+        tokio::time::sleep(backoff).await;
     }
+}
+
+/// Uploads files in the order they were made with up to upload_concurrency uploads running at once. A file's cursor
+/// sidecar only gets written once every file before it has been saved, so restarting (which resumes from the latest
+/// file with a cursor) never skips over a file that didn't make it
+async fn upload_files(file_receiver: mpsc::Receiver<File>, upload_concurrency: usize) -> Result<(), String> {
+    let files = futures::stream::unfold(file_receiver, |mut file_receiver| async move {
+        file_receiver.recv().await.map(|file| (file, file_receiver))
+    });
 
-    futures::future::join_all(sink.flush_leftovers(stop_block).into_iter().map(|file| file.save())).await;
+    let mut uploads = files.map(|file| tokio::spawn(file.save())).buffered(upload_concurrency.max(1));
+    while let Some(upload) = uploads.next().await {
+        if let Some(cursor_file) = upload.map_err(|error| format!("Upload stopped unexpectedly! Error: {}", error))?? {
+            cursor_file.save().await?;
+        }
+    }
+
+    Ok(())
 }
 
-/// Returns block range info in the form -> (start_block_num, stop_block_num)
-async fn get_block_range(sink: &Sink, package: &Package, proto_type_name: &str, chain: &Chain, start_block_arg: Option<i64>, stop_block_arg: Option<u64>) -> (i64, i64) {
-    let mut stop_block= get_latest_block_number(chain).await;
+/// Returns block range info in the form -> (start_block_num, stop_block_num, start_cursor). Without a start block
+/// given, streaming starts from the earliest of the start positions
+async fn get_block_range(start_positions: &[StartPosition], endpoint: &StreamingEndpoint, start_block_arg: Option<i64>, stop_block_arg: Option<u64>) -> Result<(i64, i64, Option<String>), String> {
+    let mut stop_block= get_latest_block_number(endpoint).await?;
     if let Some(stop_block_unwrapped) = stop_block_arg {
         let stop_block_i64 = stop_block_unwrapped as i64;
         if stop_block_i64 < stop_block {
             stop_block = stop_block_i64;
         } else {
-            panic!("Stop block_num: {} specified when last block number is {} - can't specify a stop block number that's larger than the latest block number!", stop_block_i64, stop_block);
+            return Err(format!("Stop block_num: {} specified when last block number is {} - can't specify a stop block number that's larger than the latest block number!", stop_block_i64, stop_block));
         }
     }
-    let start_block = if let Some(start_block) = start_block_arg {
+    let (start_block, start_cursor) = if let Some(start_block) = start_block_arg {
         if start_block < 0 {
             let new_start_block = stop_block + start_block;
             if new_start_block < 0 {
-                panic!("Offset: {} given for start block is larger than the stop block number: {} - this would make the starting block number <0 which is invalid!", start_block.abs(), stop_block);
+                return Err(format!("Offset: {} given for start block is larger than the stop block number: {} - this would make the starting block number <0 which is invalid!", start_block.abs(), stop_block));
             }
-            new_start_block
+            (new_start_block, None)
         } else {
-            start_block
+            (start_block, None)
         }
     } else {
-        let fallback_starting_block = get_initial_block_for_module(package, proto_type_name);
-        get_start_block_num(sink.get_output_folder_locations(), fallback_starting_block).await
+        let start_position = start_positions.iter().min_by_key(|start_position| start_position.block_number).unwrap().clone();
+        (start_position.block_number, start_position.cursor)
     };
 
-    (start_block, stop_block)
+    Ok((start_block, stop_block, start_cursor))
+}
+
+fn get_output_modules_sink(package: &Package, module_names: &[String], encoding_type: EncodingType, location_type: LocationType, storage: Arc<dyn StorageBackend>, data_location_path: Option<PathBuf>, partition_scheme: Option<PartitionScheme>, block_metadata_columns: BlockMetadataColumns, chain: &Chain) -> Result<OutputModulesSink, String> {
+    let sinks = module_names.iter().map(|module_name| {
+        let (sink, proto_type_name) = get_sink_and_proto_type_name(package, module_name, encoding_type.clone(), location_type.clone(), storage.clone(), data_location_path.clone(), partition_scheme.clone(), block_metadata_columns, chain)?;
+        Ok((module_name.clone(), sink, proto_type_name))
+    }).collect::<Result<Vec<_>, String>>()?;
+
+    OutputModulesSink::new(sinks)
+}
+
+fn get_sink_and_proto_type_name(package: &Package, module_name: &str, encoding_type: EncodingType, location_type: LocationType, storage: Arc<dyn StorageBackend>, data_location_path: Option<PathBuf>, partition_scheme: Option<PartitionScheme>, block_metadata_columns: BlockMetadataColumns, chain: &Chain) -> Result<(Sink, String), String> {
+    let (sink_output_path, output_type_info, proto_type_name) = get_sink_output_path_and_type_info(package, module_name, location_type, data_location_path, chain)?;
+
+    // Checked here so that the sinks (which assume the entities don't clash with the block metadata columns) never panic
+    let (output_layout, _) = OutputLayout::new(output_type_info.clone());
+    for entity_type_info in output_layout.get_entity_type_infos() {
+        entity_type_info.check_fields_not_manually_specified(&block_metadata_columns.get_column_names())?;
+    }
+
+    let sink = Sink::new(output_type_info, encoding_type, storage, sink_output_path, partition_scheme, block_metadata_columns);
+
+    Ok((sink, proto_type_name))
 }
 
-fn get_sink_and_proto_type_name(package: &Package, module_name: &str, encoding_type: EncodingType, location_type: LocationType, data_location_path: Option<PathBuf>, bucket_name: Option<String>, chain: &Chain) -> (Sink, String) {
+/// Returns the folder that the sink of a module writes into along with the module's output type info and proto type name
+pub(crate) fn get_sink_output_path_and_type_info(package: &Package, module_name: &str, location_type: LocationType, data_location_path: Option<PathBuf>, chain: &Chain) -> Result<(PathBuf, MessageInfo, String), String> {
     let mut sink_output_path = if let Some(data_location_path) = data_location_path {
         data_location_path
     } else {
@@ -153,23 +375,21 @@ fn get_sink_and_proto_type_name(package: &Package, module_name: &str, encoding_t
 
     sink_output_path = chain.add_chain_folders_to_path(sink_output_path);
 
-    let substream_name = package.package_meta.iter().next().unwrap().name.as_str(); // Assumes the first package specified is always the main spkg rather than a sub-spkg (haven't check this though)
+    let substream_name = package.package_meta.iter().next().ok_or_else(|| "Spkg has no package meta!".to_string())?.name.as_str(); // Assumes the first package specified is always the main spkg rather than a sub-spkg (haven't check this though)
     sink_output_path = sink_output_path.join(substream_name);
 
-    let (output_type_info, proto_type_name) = get_output_type_info(package, module_name);
+    let (output_type_info, proto_type_name) = get_output_type_info(package, module_name)?;
     sink_output_path = add_package_partitions_to_output_folder_path(sink_output_path, &proto_type_name, &output_type_info.type_name);
 
-    let package_version = get_package_version(package);
+    let package_version = get_package_version(package)?;
     sink_output_path = sink_output_path.join(package_version);
 
-    let sink = Sink::new(output_type_info, encoding_type, location_type, sink_output_path, bucket_name);
-
-    (sink, proto_type_name)
+    Ok((sink_output_path, output_type_info, proto_type_name))
 }
 
 /// Gets package version and reforms it into semver form but with underscores instead of full-stops (eg. X_Y_Z)
-fn get_package_version(package: &Package) -> String {
-    let spkg_version = package.package_meta.first().unwrap().version.as_str(); // Assumes the first package specified is always the main spkg rather than a sub-spkg (haven't check this though)
+fn get_package_version(package: &Package) -> Result<String, String> {
+    let spkg_version = package.package_meta.first().ok_or_else(|| "Spkg has no package meta!".to_string())?.version.as_str(); // Assumes the first package specified is always the main spkg rather than a sub-spkg (haven't check this though)
 
     // Only expecting spkg_version to be in forms: either vX.Y.Z or X.Y.Z
     let v_semver = Regex::new(r"^v\d+.\d+.\d+$").unwrap();
@@ -179,13 +399,66 @@ fn get_package_version(package: &Package) -> String {
     } else if semver.is_match(spkg_version) {
         spkg_version.to_string()
     } else {
-        panic!("Couldn't extract proper versioning from spkg! Expecting version to be either in form: vX.Y.Z or X.Y.Z - actual version given: {}", spkg_version);
+        return Err(format!("Couldn't extract proper versioning from spkg! Expecting version to be either in form: vX.Y.Z or X.Y.Z - actual version given: {}", spkg_version));
     };
 
-    semver_str.replace(".", "_")
+    Ok(semver_str.replace(".", "_"))
 }
 
-fn get_chain_info(package: &Package) -> Chain {
+/// Only a single module can be requested from the stream, with the outputs of the modules it depends on sent along
+/// with it's own. So this returns the output module which depends on all of the other output modules, or failing that
+/// (eg. for sibling output modules) the map module depending on all of them with the fewest dependencies of it's own
+fn get_streamed_module(package: &Package, output_modules: &[String]) -> Result<String, String> {
+    for output_module in output_modules.iter() {
+        let dependencies = get_module_dependencies(package, output_module)?;
+        if output_modules.iter().all(|other_module| other_module == output_module || dependencies.contains(other_module)) {
+            return Ok(output_module.clone());
+        }
+    }
+
+    package.modules.as_ref().unwrap().modules.iter()
+        .filter(|module| matches!(module.kind, Some(Kind::KindMap(_))))
+        .map(|module| Ok((module.name.clone(), get_module_dependencies(package, &module.name)?)))
+        .collect::<Result<Vec<_>, String>>()?
+        .into_iter()
+        .filter(|(_, dependencies)| output_modules.iter().all(|output_module| dependencies.contains(output_module)))
+        .min_by_key(|(_, dependencies)| dependencies.len())
+        .map(|(module_name, _)| module_name)
+        .ok_or_else(|| format!("Output modules: {:?} can't be processed from a single stream! Either one of the output modules or another map module needs to depend on all of them.", output_modules))
+}
+
+/// Returns the names of all the modules the given module depends on, either directly or indirectly
+fn get_module_dependencies(package: &Package, module_name: &str) -> Result<HashSet<String>, String> {
+    let modules = &package.modules.as_ref().unwrap().modules;
+
+    let mut dependencies = HashSet::new();
+    let mut modules_to_check = vec![module_name.to_string()];
+    while let Some(module_name) = modules_to_check.pop() {
+        let module = modules.iter().find(|module| module.name == module_name).ok_or_else(|| format!("Couldn't find module: {} in the spkg!", module_name))?;
+        for input in module.inputs.iter() {
+            let input_module_name = match input.input.as_ref().unwrap() {
+                Input::Map(map) => &map.module_name,
+                Input::Store(store) => &store.module_name,
+                _ => continue,
+            };
+            if dependencies.insert(input_module_name.clone()) {
+                modules_to_check.push(input_module_name.clone());
+            }
+        }
+    }
+
+    Ok(dependencies)
+}
+
+/// Takes the chain from the spkg's network if it has one, otherwise from the block type it's modules take as input
+pub(crate) fn get_chain_info(package: &Package, chains: &[Chain]) -> Result<Chain, String> {
+    if !package.network.is_empty() {
+        match Chain::from_name(&package.network, chains) {
+            Some(chain) => return Ok(chain),
+            None => println!("Network: {} of the spkg isn't a known chain! Falling back to the chain of it's block input type.", package.network),
+        }
+    }
+
     let mut block_containing_inputs = HashSet::new();
     for module in package.modules.as_ref().unwrap().modules.iter() {
         for input in module.inputs.iter() {
@@ -199,23 +472,66 @@ fn get_chain_info(package: &Package) -> Chain {
 
     let block_input_types =  block_containing_inputs.into_iter().collect::<Vec<_>>();
     if block_input_types.len() == 0 {
-        panic!("Couldn't determine default chain from block type! Either specify a block input in one of your substream modules or specify a block override in your config file corresponding to this substream!");
+        return Err("Couldn't determine default chain from block type! Either specify a block input in one of your substream modules or specify a block override in your config file corresponding to this substream!".to_string());
     } else if block_input_types.len() > 1 {
-        panic!("Couldn't determine default chain from block type! More than one module input type ending in \".Block\" was specified for this substream leading to too much ambiguity for deciding which chain to pick!");
+        return Err("Couldn't determine default chain from block type! More than one module input type ending in \".Block\" was specified for this substream leading to too much ambiguity for deciding which chain to pick!".to_string());
     }
 
     let block_type = block_input_types.into_iter().next().unwrap();
 
-    Chain::default_for_block_type(&block_type)
+    Chain::default_for_block_type(&block_type, chains)
+        .ok_or_else(|| format!("Unable to identify a default chain for input block type: {}! Please specify the chain in the chains field of your config.", block_type))
 }
 
 /// Returns block range info in the form -> (start_block_num, stop_block_num)
-pub(crate) async fn get_block_range_info(spkg: Vec<u8>, module_name: &str, location_type: LocationType, data_location_path: Option<PathBuf>, bucket_name: Option<String>, start_block_override: Option<i64>, chain_override: Option<Chain>) -> (i64, i64) {
-    let package = Package::decode(spkg.as_slice()).unwrap();
-    let chain = chain_override.unwrap_or(get_chain_info(&package));
-    let (sink, proto_type_name) = get_sink_and_proto_type_name(&package, module_name, EncodingType::Parquet, location_type, data_location_path, bucket_name, &chain);
+pub(crate) async fn get_block_range_info(spkg: Vec<u8>, module_names: Vec<String>, encoding_type: EncodingType, location_type: LocationType, data_location_path: Option<PathBuf>, bucket_name: Option<String>, storage_config: &StorageConfig, partition_scheme: Option<PartitionScheme>, start_block_override: Option<i64>, chain_override: Option<Chain>, chains: &[Chain], endpoint_override: Option<String>) -> Result<(i64, i64), String> {
+    let package = Package::decode(spkg.as_slice()).map_err(|error| format!("Unable to decode spkg! Error: {}", error))?;
+    let chain = match chain_override {
+        Some(chain_override) => chain_override,
+        None => get_chain_info(&package, chains)?,
+    };
+    let storage = get_storage_backend(location_type.clone(), bucket_name, storage_config).await?;
+    let sink = get_output_modules_sink(&package, &module_names, encoding_type, location_type, storage, data_location_path, partition_scheme, BlockMetadataColumns::default(), &chain)?;
+
+    let endpoint = StreamingEndpoint::new(&chain, endpoint_override)?;
+    let (start_block, stop_block, _) = get_block_range(&sink.get_start_positions(&package).await?, &endpoint, start_block_override, None).await?;
 
-    get_block_range(&sink, &package, &proto_type_name, &chain, start_block_override, None).await
+    Ok((start_block, stop_block))
+}
+
+/// Audits the output folders of each module for gaps, overlaps, empty files and unparsable filenames in the form ->
+/// (module_name, output_folder_audits)
+pub(crate) async fn audit_output_folders(spkg: Vec<u8>, module_names: Vec<String>, encoding_type: EncodingType, location_type: LocationType, data_location_path: Option<PathBuf>, bucket_name: Option<String>, storage_config: &StorageConfig, partition_scheme: Option<PartitionScheme>, chain_override: Option<Chain>, chains: &[Chain], count_rows: bool) -> Result<Vec<(String, Vec<OutputFolderAudit>)>, String> {
+    let package = Package::decode(spkg.as_slice()).map_err(|error| format!("Unable to decode spkg! Error: {}", error))?;
+    let chain = match chain_override {
+        Some(chain_override) => chain_override,
+        None => get_chain_info(&package, chains)?,
+    };
+    let storage = get_storage_backend(location_type.clone(), bucket_name, storage_config).await?;
+    let sink = get_output_modules_sink(&package, &module_names, encoding_type, location_type, storage, data_location_path, partition_scheme, BlockMetadataColumns::default(), &chain)?;
+
+    sink.audit_output_folders(&package, count_rows).await
+}
+
+/// Merges the small parquet files in the output folders of each module in the form -> (module_name, merged_files)
+pub(crate) async fn compact_output_folders(spkg: Vec<u8>, module_names: Vec<String>, location_type: LocationType, data_location_path: Option<PathBuf>, bucket_name: Option<String>, storage_config: &StorageConfig, partition_scheme: Option<PartitionScheme>, chain_override: Option<Chain>, chains: &[Chain], parquet_config: &ParquetConfig, target_file_size: usize, dry_run: bool) -> Result<Vec<(String, Vec<MergedFiles>)>, String> {
+    let package = Package::decode(spkg.as_slice()).map_err(|error| format!("Unable to decode spkg! Error: {}", error))?;
+    let chain = match chain_override {
+        Some(chain_override) => chain_override,
+        None => get_chain_info(&package, chains)?,
+    };
+    let storage = get_storage_backend(location_type.clone(), bucket_name, storage_config).await?;
+    let sink = get_output_modules_sink(&package, &module_names, EncodingType::Parquet(parquet_config.clone()), location_type, storage, data_location_path, partition_scheme, BlockMetadataColumns::default(), &chain)?;
+
+    let mut module_merged_files = Vec::new();
+    for (module_name, output_folder_locations) in sink.get_output_folder_locations() {
+        let mut merged_files = Vec::new();
+        for output_folder_location in output_folder_locations.iter() {
+            merged_files.extend(compact_output_folder(output_folder_location, parquet_config, target_file_size, dry_run).await?);
+        }
+        module_merged_files.push((module_name, merged_files));
+    }
+    Ok(module_merged_files)
 }
 
 fn add_package_partitions_to_output_folder_path(mut sink_output_path: PathBuf, proto_type_name: &str, entity_name: &str) -> PathBuf {
@@ -230,31 +546,575 @@ fn add_package_partitions_to_output_folder_path(mut sink_output_path: PathBuf, p
     sink_output_path
 }
 
-/// Returns both the output data and also it's corresponding block_number
-fn get_output_data(block: Result<Response, Status>) -> Result<Option<(Vec<u8>, i64)>, String> {
-    match block {
-        Ok(response) => {
-            if let Some(message) = response.message {
-                match message {
-                    streamingfast_dtos::response::Message::BlockScopedData(block_scoped_data) => {
-                        let block_number = block_scoped_data.clock.unwrap().number as i64;
-                        let output = block_scoped_data.output.unwrap().map_output.unwrap();
-                        return Ok(Some((output.value, block_number)));
-                    },
-                    _ => {}
-                }
+enum BlockMessage {
+    Data {
+        /// Output of each of the modules sent for the block in the form -> (module_name, output_data)
+        module_outputs: Vec<(String, Vec<u8>)>,
+        cursor_info: CursorInfo,
+        block_hash: String,
+        /// Unix timestamp (in seconds) of the block taken from it's clock. None if the clock didn't have one
+        block_timestamp: Option<i64>,
+        final_block_height: i64,
+    },
+    Undo {
+        last_valid_block: i64,
+    },
+}
+
+/// Returns either the output data of each module along with it's corresponding block_number and cursor, or the
+/// last valid block if the response is an undo signal
+fn get_block_message(response: Response) -> Result<Option<BlockMessage>, String> {
+    match response.message {
+        Some(streamingfast_dtos::response::Message::BlockScopedData(block_scoped_data)) => {
+            let clock = block_scoped_data.clock.ok_or_else(|| "BlockScopedData received without a clock!".to_string())?;
+            let block_number = clock.number as i64;
+            let block_timestamp = clock.timestamp.map(|timestamp| timestamp.seconds);
+            let output = block_scoped_data.output.ok_or_else(|| format!("BlockScopedData received for block: {} without a map output!", block_number))?;
+            let output_data = output.map_output.ok_or_else(|| format!("BlockScopedData received for block: {} without a map output!", block_number))?.value;
+
+            // Outputs of the modules the streamed module depends on only get sent outside of production mode
+            let mut module_outputs = vec![(output.name, output_data)];
+            for debug_map_output in block_scoped_data.debug_map_outputs.into_iter() {
+                module_outputs.push((debug_map_output.name, debug_map_output.map_output.map(|map_output| map_output.value).unwrap_or_default()));
             }
 
-            Ok(None)
-        }
-        Err(error) => {
-            Err(format!("Error!: {} - TODO: Give proper error message here..", error.message()))
-        }
+            Ok(Some(BlockMessage::Data {
+                module_outputs,
+                cursor_info: CursorInfo {
+                    cursor: block_scoped_data.cursor,
+                    block_number,
+                },
+                block_hash: clock.id,
+                block_timestamp,
+                final_block_height: block_scoped_data.final_block_height as i64,
+            }))
+        },
+        Some(streamingfast_dtos::response::Message::BlockUndoSignal(block_undo_signal)) => {
+            let last_valid_block = block_undo_signal.last_valid_block.ok_or_else(|| "BlockUndoSignal received without a last valid block!".to_string())?.number as i64;
+            Ok(Some(BlockMessage::Undo {
+                last_valid_block,
+            }))
+        },
+        _ => Ok(None),
     }
 }
 
 #[derive(Clone)]
 pub(crate) enum EncodingType {
-    // JsonL,
-    Parquet
-}
\ No newline at end of file
+    /// Both csv and jsonl hold the file size threshold (in bytes) the files get rolled over at
+    Csv(RepeatedFieldPolicy, usize),
+    JsonL(usize),
+    Parquet(ParquetConfig)
+}
+
+impl EncodingType {
+    /// Used both as the file extension and as the name of the folder the files get written to
+    pub(crate) fn get_file_extension(&self) -> &'static str {
+        match self {
+            EncodingType::Csv(..) => "csv",
+            EncodingType::JsonL(_) => "jsonl",
+            EncodingType::Parquet(_) => "parquet",
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use prost::Message;
+    use tempfile::TempDir;
+    use tonic::{Code, Status};
+    use prost_types::{DescriptorProto, FieldDescriptorProto, FileDescriptorProto};
+    use prost_types::field_descriptor_proto::{Label, Type};
+
+    use crate::streaming_fast::file::{Location, LocationType};
+    use crate::streaming_fast::mock_streaming_server::{get_block_response, MockStreamingServer};
+    use crate::streaming_fast::process_substream::{EncodingType, get_streamed_module, process_substream};
+    use crate::streaming_fast::processing_error::FailureReason;
+    use crate::streaming_fast::storage::LocalStorage;
+    use crate::streaming_fast::streaming_config::{ParquetConfig, PartitionScheme, StreamingConfig};
+    use crate::streaming_fast::streamingfast_dtos::{MapModuleOutput, Module, Modules, Package, PackageMetadata};
+    use crate::streaming_fast::streamingfast_dtos::module::{Input as ModuleInput, Kind, KindMap, Output};
+    use crate::streaming_fast::streamingfast_dtos::module::input::{Input, Map, Source};
+    use crate::streaming_fast::streaming_fast_utils::audit_output_folder;
+    use crate::streaming_fast::streamingfast_dtos::response::Message as ResponseMessage;
+    use crate::test_utils::get_temp_dir;
+
+    const MODULE_NAME: &str = "map_transfers";
+    const OUTPUT_TYPE: &str = "proto:test.v1.Transfer";
+    const COUNTS_MODULE_NAME: &str = "map_transfer_counts";
+    const COUNTS_OUTPUT_TYPE: &str = "proto:test.v1.TransferCount";
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Transfer {
+        #[prost(string, tag = "1")]
+        from: String,
+        #[prost(uint64, tag = "2")]
+        amount: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct TransferCount {
+        #[prost(uint64, tag = "1")]
+        count: u64,
+    }
+
+    fn get_test_spkg() -> Vec<u8> {
+        get_test_package().encode_to_vec()
+    }
+
+    fn get_test_package() -> Package {
+        let get_field = |name: &str, number: i32, field_type: Type| FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(Label::Optional as i32),
+            r#type: Some(field_type as i32),
+            ..Default::default()
+        };
+
+        let package = Package {
+            proto_files: vec![FileDescriptorProto {
+                name: Some("transfer.proto".to_string()),
+                package: Some("test.v1".to_string()),
+                message_type: vec![DescriptorProto {
+                    name: Some("Transfer".to_string()),
+                    field: vec![get_field("from", 1, Type::String), get_field("amount", 2, Type::Uint64)],
+                    ..Default::default()
+                }],
+                syntax: Some("proto3".to_string()),
+                ..Default::default()
+            }],
+            modules: Some(Modules {
+                modules: vec![Module {
+                    name: MODULE_NAME.to_string(),
+                    kind: Some(Kind::KindMap(KindMap { output_type: OUTPUT_TYPE.to_string() })),
+                    inputs: vec![ModuleInput { input: Some(Input::Source(Source { r#type: "sf.ethereum.type.v2.Block".to_string() })) }],
+                    output: Some(Output { r#type: OUTPUT_TYPE.to_string() }),
+                    initial_block: 100,
+                    ..Default::default()
+                }],
+                binaries: vec![],
+            }),
+            package_meta: vec![PackageMetadata {
+                name: "test_substream".to_string(),
+                version: "v0.1.0".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        package
+    }
+
+    /// Test spkg with an extra module depending on the transfers module
+    fn get_multiple_modules_test_spkg() -> Vec<u8> {
+        let mut package = get_test_package();
+
+        package.proto_files[0].message_type.push(DescriptorProto {
+            name: Some("TransferCount".to_string()),
+            field: vec![FieldDescriptorProto {
+                name: Some("count".to_string()),
+                number: Some(1),
+                label: Some(Label::Optional as i32),
+                r#type: Some(Type::Uint64 as i32),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        package.modules.as_mut().unwrap().modules.push(Module {
+            name: COUNTS_MODULE_NAME.to_string(),
+            kind: Some(Kind::KindMap(KindMap { output_type: COUNTS_OUTPUT_TYPE.to_string() })),
+            inputs: vec![ModuleInput { input: Some(Input::Map(Map { module_name: MODULE_NAME.to_string() })) }],
+            output: Some(Output { r#type: COUNTS_OUTPUT_TYPE.to_string() }),
+            initial_block: 100,
+            ..Default::default()
+        });
+
+        package.encode_to_vec()
+    }
+
+    /// Records the transfer counts for each of the blocks 100 -> 119, with the transfers sent along as debug outputs
+    fn write_multiple_modules_test_recording(data_location_path: &Path) -> PathBuf {
+        let mut recording = Vec::new();
+        for block_number in 100..120 {
+            let mut response = get_block_response(block_number, COUNTS_MODULE_NAME, TransferCount { count: block_number }.encode_to_vec());
+            if let Some(ResponseMessage::BlockScopedData(block_scoped_data)) = response.message.as_mut() {
+                let transfer = Transfer { from: format!("0x{:040x}", block_number), amount: block_number };
+                block_scoped_data.debug_map_outputs.push(MapModuleOutput {
+                    name: MODULE_NAME.to_string(),
+                    map_output: Some(prost_types::Any { type_url: String::new(), value: transfer.encode_to_vec() }),
+                    ..Default::default()
+                });
+            }
+            response.encode_length_delimited(&mut recording).unwrap();
+        }
+        let recording_path = data_location_path.join("recording.bin");
+        fs::write(&recording_path, recording).unwrap();
+        recording_path
+    }
+
+    /// Records a transfer for each of the blocks 100 -> 119
+    fn write_test_recording(data_location_path: &Path) -> PathBuf {
+        let mut recording = Vec::new();
+        for block_number in 100..120 {
+            let transfer = Transfer { from: format!("0x{:040x}", block_number), amount: block_number };
+            get_block_response(block_number, MODULE_NAME, transfer.encode_to_vec()).encode_length_delimited(&mut recording).unwrap();
+        }
+        let recording_path = data_location_path.join("recording.bin");
+        fs::write(&recording_path, recording).unwrap();
+        recording_path
+    }
+
+    fn get_test_config(endpoint: &str) -> StreamingConfig {
+        // A file size threshold of 1 byte makes the sink roll over to a new file after every block
+        serde_json::from_value(serde_json::json!({
+            "output_module": MODULE_NAME,
+            "endpoint_override": endpoint,
+            "param_overrides": [],
+            "start_block_overrides": [],
+            "parquet": { "file_size_threshold": 1 },
+            "retry": { "initial_backoff_ms": 1 },
+        })).unwrap()
+    }
+
+    /// Starts a mock server replaying the recording written into a fresh temp dir, which the test then also uses as
+    /// the data location
+    fn start_test_server(write_recording: fn(&Path) -> PathBuf) -> (TempDir, MockStreamingServer, String) {
+        let data_location = get_temp_dir();
+        let server = MockStreamingServer::from_recording(&write_recording(data_location.path()), 1000);
+        let endpoint = server.start();
+        (data_location, server, endpoint)
+    }
+
+    fn get_output_folder(data_location_path: &Path, entity_name: &str) -> PathBuf {
+        data_location_path.join("ethereum").join("mainnet").join("test_substream").join("test").join("v1").join("0_1_0").join(entity_name).join("parquet")
+    }
+
+    fn get_file_names(folder: &Path, extension: &str) -> Vec<String> {
+        let mut file_names = fs::read_dir(folder).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|file_name| file_name.ends_with(extension))
+            .collect::<Vec<_>>();
+        file_names.sort();
+        file_names
+    }
+
+    #[tokio::test]
+    async fn test_process_and_restart_from_files() {
+        let (data_location, server, endpoint) = start_test_server(write_test_recording);
+        let data_location_path = data_location.path().to_path_buf();
+        let output_folder = get_output_folder(&data_location_path, "Transfer");
+
+        let config = get_test_config(&endpoint);
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(110), false).await.unwrap();
+
+        let expected_files = (100..110).map(|block_number| format!("{}_{}.parquet", block_number, block_number)).collect::<Vec<_>>();
+        assert_eq!(get_file_names(&output_folder, ".parquet"), expected_files);
+        assert_eq!(get_file_names(&output_folder, ".cursor").len(), expected_files.len());
+
+        // Without a start block given, processing should resume from the cursor saved alongside the latest file
+        let config = get_test_config(&endpoint);
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(120), false).await.unwrap();
+
+        let requests = server.get_requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!((requests[0].start_block_num, requests[0].start_cursor.as_str()), (100, ""));
+        assert_eq!((requests[1].start_block_num, requests[1].start_cursor.as_str()), (110, "cursor_109"));
+
+        let expected_files = (100..120).map(|block_number| format!("{}_{}.parquet", block_number, block_number)).collect::<Vec<_>>();
+        assert_eq!(get_file_names(&output_folder, ".parquet"), expected_files);
+    }
+
+    #[tokio::test]
+    async fn test_process_into_block_bucket_partitions() {
+        let (data_location, server, endpoint) = start_test_server(write_test_recording);
+        let data_location_path = data_location.path().to_path_buf();
+
+        // Files only get closed off when a block falls into the next partition
+        let get_partitioned_config = || {
+            let mut config = get_test_config(&endpoint);
+            config.parquet = ParquetConfig::default();
+            config.partition = Some(PartitionScheme::BlockBucket { bucket_size: 5 });
+            config
+        };
+
+        let output_folder = get_output_folder(&data_location_path, "Transfer");
+        let get_parquet_files = || {
+            let mut file_paths = Vec::new();
+            for partition_entry in fs::read_dir(&output_folder).unwrap() {
+                let partition_path = partition_entry.unwrap().path();
+                let partition_name = partition_path.file_name().unwrap().to_string_lossy().to_string();
+                file_paths.extend(get_file_names(&partition_path, ".parquet").into_iter().map(|file_name| format!("{}/{}", partition_name, file_name)));
+            }
+            file_paths.sort();
+            file_paths
+        };
+
+        let config = get_partitioned_config();
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(112), false).await.unwrap();
+
+        assert_eq!(get_parquet_files(), vec!["block_bucket=100/100_104.parquet", "block_bucket=105/105_109.parquet", "block_bucket=110/110_112.parquet"]);
+
+        // Restarting should pick up the cursor from the latest partition
+        let config = get_partitioned_config();
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(120), false).await.unwrap();
+
+        let requests = server.get_requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!((requests[1].start_block_num, requests[1].start_cursor.as_str()), (112, "cursor_111"));
+
+        assert_eq!(get_parquet_files(), vec![
+            "block_bucket=100/100_104.parquet",
+            "block_bucket=105/105_109.parquet",
+            "block_bucket=110/110_112.parquet",
+            "block_bucket=110/112_114.parquet",
+            "block_bucket=115/115_120.parquet",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_after_transient_stream_failure() {
+        let (data_location, server, endpoint) = start_test_server(write_test_recording);
+        let data_location_path = data_location.path().to_path_buf();
+        server.fail_next_stream_after(5, Status::new(Code::Unavailable, "connection reset"));
+
+        let config = get_test_config(&endpoint);
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(110), false).await.unwrap();
+
+        // The reconnect should carry on from the cursor of the last block received before the failure
+        let requests = server.get_requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!((requests[1].start_block_num, requests[1].start_cursor.as_str()), (105, "cursor_104"));
+
+        let output_folder = get_output_folder(&data_location_path, "Transfer");
+        assert_eq!(get_file_names(&output_folder, ".parquet"), (100..110).map(|block_number| format!("{}_{}.parquet", block_number, block_number)).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_incomplete_runs_return_an_error() {
+        let (data_location, server, endpoint) = start_test_server(write_test_recording);
+        let data_location_path = data_location.path().to_path_buf();
+        server.fail_next_stream_after(3, Status::new(Code::PermissionDenied, "invalid api token"));
+
+        // Non-retryable statuses fail straight away
+        let config = get_test_config(&endpoint);
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        let error = process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(110), false).await.unwrap_err();
+        assert!(matches!(error.reason, FailureReason::Stream(_)));
+        assert_eq!((error.block_range, error.last_processed_block), (Some((100, 110)), Some(102)));
+        assert_eq!(server.get_requests().len(), 1);
+
+        // The recording runs out at block 119 so the stream keeps ending early until the retries are used up
+        let mut config = get_test_config(&endpoint);
+        config.retry.max_attempts = 2;
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        let error = process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(130), false).await.unwrap_err();
+        assert!(matches!(error.reason, FailureReason::RetriesExhausted { attempts: 2, .. }));
+        assert_eq!((error.block_range.map(|(start_block, _)| start_block), error.last_processed_block), (Some(103), Some(119)));
+
+        let requests = server.get_requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!((requests[3].start_block_num, requests[3].start_cursor.as_str()), (120, "cursor_119"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_input_returns_an_error() {
+        let (data_location, server, endpoint) = start_test_server(write_test_recording);
+        let data_location_path = data_location.path().to_path_buf();
+
+        let config = get_test_config(&endpoint);
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        let error = process_substream(vec![0xFF; 8], config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(110), false).await.unwrap_err();
+        assert!(matches!(error.reason, FailureReason::InvalidSpkg(_)));
+        assert_eq!(error.block_range, None);
+
+        let mut config = get_test_config(&endpoint);
+        config.output_module = Some("map_unknown".to_string());
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        let error = process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(110), false).await.unwrap_err();
+        assert!(matches!(error.reason, FailureReason::Config(_)));
+
+        // The stop block is past the latest block of the recording
+        let config = get_test_config(&endpoint);
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        let error = process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(1_000_000), false).await.unwrap_err();
+        assert!(matches!(error.reason, FailureReason::BlockRange(_)));
+
+        let config = get_test_config(&endpoint);
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        let error = process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, Some(-1_000_000), Some(110), false).await.unwrap_err();
+        assert!(matches!(error.reason, FailureReason::BlockRange(_)));
+
+        assert!(server.get_requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_restart_from_filenames_without_any_cursors() {
+        let (data_location, server, endpoint) = start_test_server(write_test_recording);
+        let data_location_path = data_location.path().to_path_buf();
+
+        let config = get_test_config(&endpoint);
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(110), false).await.unwrap();
+
+        // Leaves the folder as if it was written before cursors were saved
+        let output_folder = get_output_folder(&data_location_path, "Transfer");
+        for cursor_file_name in get_file_names(&output_folder, ".cursor") {
+            fs::remove_file(output_folder.join(cursor_file_name)).unwrap();
+        }
+
+        let config = get_test_config(&endpoint);
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(120), false).await.unwrap();
+
+        // Without a cursor the stream restarts from the last block of the latest file
+        let requests = server.get_requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!((requests[1].start_block_num, requests[1].start_cursor.as_str()), (109, ""));
+
+        let expected_files = (100..120).map(|block_number| format!("{}_{}.parquet", block_number, block_number)).collect::<Vec<_>>();
+        assert_eq!(get_file_names(&output_folder, ".parquet"), expected_files);
+        assert_eq!(get_file_names(&output_folder, ".cursor"), (109..120).map(|block_number| format!("{}_{}.cursor", block_number, block_number)).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_restart_skips_files_without_cursors() {
+        let (data_location, server, endpoint) = start_test_server(write_test_recording);
+        let data_location_path = data_location.path().to_path_buf();
+
+        let config = get_test_config(&endpoint);
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(110), false).await.unwrap();
+
+        // Leaves the latest files as if they finished uploading before the file ahead of them, meaning their cursors
+        // were never written
+        let output_folder = get_output_folder(&data_location_path, "Transfer");
+        fs::remove_file(output_folder.join("107_107.cursor")).unwrap();
+        fs::remove_file(output_folder.join("108_108.cursor")).unwrap();
+        fs::remove_file(output_folder.join("109_109.cursor")).unwrap();
+
+        // Restarting with the default file size threshold names the rewritten file differently to the files without
+        // cursors, so they would overlap it if they were left behind
+        let mut config = get_test_config(&endpoint);
+        config.parquet = ParquetConfig::default();
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(120), false).await.unwrap();
+
+        let requests = server.get_requests();
+        assert_eq!((requests[1].start_block_num, requests[1].start_cursor.as_str()), (107, "cursor_106"));
+
+        let mut expected_files = (100..107).map(|block_number| format!("{}_{}.parquet", block_number, block_number)).collect::<Vec<_>>();
+        expected_files.push("107_120.parquet".to_string());
+        assert_eq!(get_file_names(&output_folder, ".parquet"), expected_files);
+        assert!(output_folder.join("107_120.cursor").exists());
+
+        let location = Location::new(Arc::new(LocalStorage), output_folder, None);
+        assert!(audit_output_folder(&location, 100, false).await.unwrap().overlaps.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_start_keeps_files_after_cursor() {
+        let (data_location, server, endpoint) = start_test_server(write_test_recording);
+        let data_location_path = data_location.path().to_path_buf();
+
+        let config = get_test_config(&endpoint);
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(110), false).await.unwrap();
+
+        // The latest file has no cursor and can't be read as parquet, so the schema check fails
+        let output_folder = get_output_folder(&data_location_path, "Transfer");
+        fs::remove_file(output_folder.join("108_108.cursor")).unwrap();
+        fs::remove_file(output_folder.join("109_109.cursor")).unwrap();
+        fs::write(output_folder.join("109_109.parquet"), b"not parquet").unwrap();
+
+        let config = get_test_config(&endpoint);
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        let error = process_substream(get_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(120), false).await.unwrap_err();
+        assert!(matches!(error.reason, FailureReason::IncompatibleSchema(_)));
+        assert_eq!(server.get_requests().len(), 1);
+
+        let expected_files = (100..110).map(|block_number| format!("{}_{}.parquet", block_number, block_number)).collect::<Vec<_>>();
+        assert_eq!(get_file_names(&output_folder, ".parquet"), expected_files);
+    }
+
+    #[test]
+    fn test_streamed_module_for_sibling_output_modules() {
+        let get_map_module = |name: &str, input_module_names: &[&str]| Module {
+            name: name.to_string(),
+            kind: Some(Kind::KindMap(KindMap { output_type: OUTPUT_TYPE.to_string() })),
+            inputs: input_module_names.iter().map(|module_name| ModuleInput { input: Some(Input::Map(Map { module_name: module_name.to_string() })) }).collect(),
+            output: Some(Output { r#type: OUTPUT_TYPE.to_string() }),
+            ..Default::default()
+        };
+
+        // The transfers and approvals modules are siblings, only brought together by the entity changes module
+        let mut package = get_test_package();
+        package.modules.as_mut().unwrap().modules.extend([
+            get_map_module("map_approvals", &[]),
+            get_map_module("graph_out", &["map_entity_changes"]),
+            get_map_module("map_entity_changes", &[MODULE_NAME, "map_approvals"]),
+            get_map_module("map_unrelated", &[]),
+        ]);
+
+        let get_output_modules = |module_names: &[&str]| module_names.iter().map(|module_name| module_name.to_string()).collect::<Vec<_>>();
+        assert_eq!(get_streamed_module(&package, &get_output_modules(&[MODULE_NAME, "map_approvals"])).unwrap(), "map_entity_changes");
+        assert_eq!(get_streamed_module(&package, &get_output_modules(&["map_approvals", "graph_out"])).unwrap(), "graph_out");
+        assert!(get_streamed_module(&package, &get_output_modules(&[MODULE_NAME, "map_unrelated"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_process_multiple_output_modules() {
+        let (data_location, server, endpoint) = start_test_server(write_multiple_modules_test_recording);
+        let data_location_path = data_location.path().to_path_buf();
+
+        let get_multiple_modules_config = || {
+            let mut config = get_test_config(&endpoint);
+            config.output_module = None;
+            config.output_modules = vec![MODULE_NAME.to_string(), COUNTS_MODULE_NAME.to_string()];
+            config
+        };
+
+        let transfers_folder = get_output_folder(&data_location_path, "Transfer");
+        let counts_folder = get_output_folder(&data_location_path, "TransferCount");
+
+        let config = get_multiple_modules_config();
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        process_substream(get_multiple_modules_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(110), false).await.unwrap();
+
+        // Both modules come from the one stream of the module depending on the other
+        let requests = server.get_requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].output_module, COUNTS_MODULE_NAME);
+        assert!(!requests[0].production_mode);
+
+        let expected_files = (100..110).map(|block_number| format!("{}_{}.parquet", block_number, block_number)).collect::<Vec<_>>();
+        assert_eq!(get_file_names(&transfers_folder, ".parquet"), expected_files);
+        assert_eq!(get_file_names(&counts_folder, ".parquet"), expected_files);
+
+        // Puts the transfers sink behind the counts sink. Restarting should stream from where the transfers sink got up
+        // to while the counts sink skips the blocks it has already written
+        for block_number in 105..110 {
+            fs::remove_file(transfers_folder.join(format!("{}_{}.parquet", block_number, block_number))).unwrap();
+            fs::remove_file(transfers_folder.join(format!("{}_{}.cursor", block_number, block_number))).unwrap();
+        }
+        fs::write(counts_folder.join("105_105.parquet"), b"untouched").unwrap();
+
+        let config = get_multiple_modules_config();
+        let encoding_type = EncodingType::Parquet(config.parquet.clone());
+        process_substream(get_multiple_modules_test_spkg(), config, encoding_type, LocationType::Local, Some(data_location_path.clone()), None, None, Some(120), false).await.unwrap();
+
+        let requests = server.get_requests();
+        assert_eq!((requests[1].start_block_num, requests[1].start_cursor.as_str()), (105, "cursor_104"));
+
+        let expected_files = (100..120).map(|block_number| format!("{}_{}.parquet", block_number, block_number)).collect::<Vec<_>>();
+        assert_eq!(get_file_names(&transfers_folder, ".parquet"), expected_files);
+        assert_eq!(get_file_names(&counts_folder, ".parquet"), expected_files);
+        assert_eq!(fs::read(counts_folder.join("105_105.parquet")).unwrap(), b"untouched");
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt::{Display, Formatter};
+use tonic::{Code, Status};
+
+/// Returned when processing stops before reaching the stop block. Displays as a summary of how far the run got
+#[derive(Debug)]
+pub(crate) struct ProcessingError {
+    pub(crate) reason: FailureReason,
+    /// The requested block range in the form -> (start_block, stop_block). None if processing failed before it could
+    /// be worked out
+    pub(crate) block_range: Option<(i64, i64)>,
+    pub(crate) last_processed_block: Option<i64>,
+}
+
+#[derive(Debug)]
+pub(crate) enum FailureReason {
+    /// The sink was unable to handle the output of a block or an undo signal
+    Sink(String),
+    /// A file couldn't be written to the storage backend
+    Storage(String),
+    /// The output schema doesn't match the schema of the files already in the output folder
+    IncompatibleSchema(String),
+    /// The output modules can't all be processed from a single stream
+    OutputModules(String),
+    /// A response from the stream was missing data that should always be there
+    InvalidResponse(String),
+    /// The stream failed with a status that isn't worth retrying on (eg. an invalid request or api token)
+    Stream(Status),
+    /// The stream kept failing (or ending early) until all of the retry attempts were used up
+    RetriesExhausted {
+        attempts: u32,
+        last_error: String,
+    },
+    /// The spkg couldn't be decoded
+    InvalidSpkg(String),
+    /// The spkg or config can't be processed (eg. an unknown output module, chain or package version)
+    Config(String),
+    /// The block range to process couldn't be worked out, either because the latest block couldn't be fetched or
+    /// because the requested range is invalid
+    BlockRange(String),
+    /// One of the tasks running the stream or uploads stopped unexpectedly
+    Pipeline(String),
+}
+
+impl FailureReason {
+    /// Whether the sink is still in a consistent state, in which case the data it has built up can still be saved
+    pub(crate) fn is_sink_intact(&self) -> bool {
+        match self {
+            FailureReason::Sink(_) | FailureReason::Storage(_) | FailureReason::IncompatibleSchema(_) | FailureReason::OutputModules(_) |
+            FailureReason::InvalidSpkg(_) | FailureReason::Config(_) | FailureReason::BlockRange(_) | FailureReason::Pipeline(_) => false,
+            FailureReason::InvalidResponse(_) | FailureReason::Stream(_) | FailureReason::RetriesExhausted { .. } => true,
+        }
+    }
+}
+
+impl Display for FailureReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FailureReason::Sink(message) => write!(f, "Sink error: {}", message),
+            FailureReason::Storage(message) => write!(f, "Storage error: {}", message),
+            FailureReason::IncompatibleSchema(message) => write!(f, "Incompatible schema: {}", message),
+            FailureReason::OutputModules(message) => write!(f, "Invalid output modules: {}", message),
+            FailureReason::InvalidResponse(message) => write!(f, "Invalid response received from stream: {}", message),
+            FailureReason::Stream(status) => write!(f, "Stream failed with non-retryable status: {:?} - {}", status.code(), status.message()),
+            FailureReason::RetriesExhausted { attempts, last_error } => write!(f, "Stream still failing after {} reconnect attempts! Last error: {}", attempts, last_error),
+            FailureReason::InvalidSpkg(message) => write!(f, "Invalid spkg: {}", message),
+            FailureReason::Config(message) => write!(f, "Invalid config: {}", message),
+            FailureReason::BlockRange(message) => write!(f, "Invalid block range: {}", message),
+            FailureReason::Pipeline(message) => write!(f, "Pipeline task failed: {}", message),
+        }
+    }
+}
+
+/// For failures before the block range is known
+impl From<FailureReason> for ProcessingError {
+    fn from(reason: FailureReason) -> Self {
+        ProcessingError {
+            reason,
+            block_range: None,
+            last_processed_block: None,
+        }
+    }
+}
+
+impl Display for ProcessingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Processing stopped before reaching the stop block!")?;
+        writeln!(f, "Reason: {}", self.reason)?;
+        let (start_block, stop_block) = match self.block_range {
+            Some(block_range) => block_range,
+            None => return write!(f, "Failed before the block range was worked out, no blocks were processed"),
+        };
+        writeln!(f, "Requested block range: {} -> {} (stop block exclusive)", start_block, stop_block)?;
+        match self.last_processed_block {
+            Some(last_processed_block) => write!(f, "Last processed block: {}, blocks remaining: {}", last_processed_block, stop_block - last_processed_block - 1),
+            None => write!(f, "No blocks were processed"),
+        }
+    }
+}
+
+/// Status codes that are expected to clear up by themselves, meaning the stream is worth reconnecting to
+pub(crate) fn is_transient_status(status: &Status) -> bool {
+    matches!(status.code(), Code::Unavailable | Code::Unknown | Code::Internal | Code::DeadlineExceeded | Code::ResourceExhausted | Code::Aborted)
+}
@@ -2,22 +2,95 @@ use derives::proto_structure_info::{FieldSpecification, MessageInfo};
 
 use crate::streaming_fast::streamingfast_dtos::Package;
 
-pub(crate) fn get_output_type_info(package: &Package, module_name: &str) -> (MessageInfo, String) {
+pub(crate) fn get_output_type_info(package: &Package, module_name: &str) -> Result<(MessageInfo, String), String> {
     for module in package.modules.as_ref().unwrap().modules.iter() {
         if &module.name == module_name {
-            let output_type = module.output.as_ref().expect(&format!("Module you are trying to process: {}, is not a map module!", module_name)).r#type.to_string();
+            let output_type = module.output.as_ref().ok_or_else(|| format!("Module you are trying to process: {}, is not a map module!", module_name))?.r#type.to_string();
 
             if !output_type.starts_with("proto:") {
-                panic!("TODO!");
+                return Err(format!("Output type: {} of module: {} is not supported! Only modules outputting proto messages can be processed.", output_type, module_name));
             }
 
-            let message_info = MessageInfo::new(&package.proto_files, &output_type, FieldSpecification::Required, None);
+            let message_info = MessageInfo::new(&package.proto_files, &output_type, FieldSpecification::Required)
+                .map_err(|error| format!("Unable to get the output type of module: {}! Error: {}", module_name, error))?;
 
-            message_info.assert_block_number_field_not_manually_specified();
+            message_info.check_fields_not_manually_specified(&["block_number"])?;
 
-            return (message_info, output_type);
+            return Ok((message_info, output_type));
         }
     }
 
-    panic!("Couldn't find output type!!")
+    Err(format!("Couldn't find module: {} in the spkg!", module_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use derives::proto_structure_info::{FieldSpecification, FieldType, MessageInfo};
+    use prost_types::field_descriptor_proto::{Label, Type};
+    use prost_types::{DescriptorProto, FieldDescriptorProto, FileDescriptorProto};
+
+    fn get_field(name: &str, number: i32, label: Label, field_type: Type, type_name: Option<&str>) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(label as i32),
+            r#type: Some(field_type as i32),
+            type_name: type_name.map(|type_name| type_name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn get_message(name: &str, field: Vec<FieldDescriptorProto>, nested_type: Vec<DescriptorProto>) -> DescriptorProto {
+        DescriptorProto {
+            name: Some(name.to_string()),
+            field,
+            nested_type,
+            ..Default::default()
+        }
+    }
+
+    fn get_proto_files() -> Vec<FileDescriptorProto> {
+        vec![
+            FileDescriptorProto {
+                package: Some("messari.other.v1".to_string()),
+                message_type: vec![get_message("Token", vec![get_field("symbol", 1, Label::Optional, Type::String, None)], vec![])],
+                ..Default::default()
+            },
+            FileDescriptorProto {
+                package: Some("messari.test.v1".to_string()),
+                message_type: vec![
+                    get_message("Token", vec![get_field("address", 1, Label::Optional, Type::Bytes, None)], vec![]),
+                    get_message("Call", vec![
+                        get_field("token", 1, Label::Optional, Type::Message, Some(".messari.test.v1.Token")),
+                        get_field("trace", 2, Label::Optional, Type::Message, Some(".messari.test.v1.Call.Trace")),
+                    ], vec![
+                        get_message("Trace", vec![
+                            get_field("depth", 1, Label::Optional, Type::Uint32, None),
+                            get_field("sub_calls", 2, Label::Repeated, Type::Message, Some(".messari.test.v1.Call")),
+                        ], vec![]),
+                    ]),
+                ],
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_same_named_and_recursive_messages() {
+        let message_info = MessageInfo::new(&get_proto_files(), "proto:messari.test.v1.Call", FieldSpecification::Required).unwrap();
+        assert_eq!(message_info.full_type_name, "messari.test.v1.Call");
+
+        // Token of the same package is picked over the Token declared in another package
+        let (token_info, _) = message_info.fields[0].clone().get_struct_info();
+        assert_eq!(token_info.full_type_name, "messari.test.v1.Token");
+        assert_eq!(token_info.fields[0].field_name, "address");
+
+        // Call is recursive through the nested Trace message so the sub calls get kept as raw proto bytes
+        let (trace_info, _) = message_info.fields[1].clone().get_struct_info();
+        assert_eq!(trace_info.full_type_name, "messari.test.v1.Call.Trace");
+        assert_eq!(trace_info.fields[1].field_type, FieldType::Bytes);
+        assert_eq!(trace_info.fields[1].field_specification, FieldSpecification::Repeated);
+
+        assert!(MessageInfo::new(&get_proto_files(), "proto:messari.missing.v1.Call", FieldSpecification::Required).is_err());
+    }
 }
@@ -1,53 +1,83 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use derives::proto_structure_info::{FieldSpecification, MessageInfo};
 use async_trait::async_trait;
+use parquet::schema::types::TypePtr;
 
-use crate::streaming_fast::file::{File, Location, LocationType};
-use crate::streaming_fast::file_sinks::file_sink::FileSink;
+use crate::streaming_fast::file::{File, Location};
+use crate::streaming_fast::file_sinks::file_sink::{BlockMetadata, FileSink};
+use crate::streaming_fast::file_sinks::csv::CsvFileSink;
+use crate::streaming_fast::file_sinks::jsonl::JsonLFileSink;
 use crate::streaming_fast::file_sinks::parquet::ParquetFileSink;
 use crate::streaming_fast::multiple_files_sink::MultipleFilesSink;
+use crate::streaming_fast::output_schema::get_breaking_changes_from_file;
 use crate::streaming_fast::process_substream::EncodingType;
+use crate::streaming_fast::storage::StorageBackend;
+use crate::streaming_fast::streaming_config::{BlockMetadataColumns, PartitionScheme};
+use crate::streaming_fast::streaming_fast_utils::get_latest_file_path;
 
 pub(crate) struct SingleFileSink {
     file_sink: Box<dyn FileSink>,
     sink_output_location: Location,
     starting_block_number: i64,
     encoding_type: EncodingType,
+    /// Schema of the files written when encoding to parquet. Used to check that existing files in the output folder
+    /// share the same schema before appending to it
+    parquet_schema: Option<TypePtr>,
+    /// Partition of the most recently seen block which is where the file currently being built will be written to
+    partition_folder_name: Option<String>,
 }
 
 impl SingleFileSink {
-    pub(crate) fn new(mut output_type_info: MessageInfo, encoding_type: EncodingType, location_type: LocationType, mut sink_output_path: PathBuf, bucket_name: Option<String>) -> Self {
-        sink_output_path = sink_output_path.join(&output_type_info.type_name);
+    pub(crate) fn new(mut output_type_info: MessageInfo, encoding_type: EncodingType, storage: Arc<dyn StorageBackend>, sink_output_path: PathBuf, partition_scheme: Option<PartitionScheme>, block_metadata_columns: BlockMetadataColumns) -> Self {
+        let sink_output_path = SingleFileSink::get_output_folder(&sink_output_path, &output_type_info.type_name, &encoding_type);
         output_type_info.field_specification = FieldSpecification::Required;
+        output_type_info.assert_fields_not_manually_specified(&block_metadata_columns.get_column_names());
 
-        let file_sink = match encoding_type {
-            EncodingType::Parquet => {
-                sink_output_path = sink_output_path.join("parquet");
-                Box::new(ParquetFileSink::new(output_type_info))
-            }
+        let mut parquet_schema = None;
+        let file_sink: Box<dyn FileSink> = match &encoding_type {
+            EncodingType::Csv(repeated_field_policy, file_size_threshold) => Box::new(CsvFileSink::new_with_config(output_type_info, *repeated_field_policy, *file_size_threshold, block_metadata_columns)),
+            EncodingType::JsonL(file_size_threshold) => Box::new(JsonLFileSink::new_with_config(output_type_info, *file_size_threshold, block_metadata_columns)),
+            EncodingType::Parquet(parquet_config) => {
+                let parquet_file_sink = ParquetFileSink::new_with_config(output_type_info, parquet_config, block_metadata_columns);
+                parquet_schema = Some(parquet_file_sink.get_parquet_schema());
+                Box::new(parquet_file_sink)
+            },
         };
 
-        let sink_output_location = Location::new(location_type, sink_output_path, bucket_name);
+        let sink_output_location = Location::new(storage, sink_output_path, partition_scheme);
 
         SingleFileSink {
             file_sink,
             sink_output_location,
             starting_block_number: 0, // 0 set initially as a dummy value - will be overwritten later on
             encoding_type,
+            parquet_schema,
+            partition_folder_name: None,
         }
     }
+
+    /// Folder the entity's files get written to (before any partition folders)
+    pub(crate) fn get_output_folder(sink_output_path: &Path, entity_name: &str, encoding_type: &EncodingType) -> PathBuf {
+        sink_output_path.join(entity_name).join(encoding_type.get_file_extension())
+    }
 }
 
 #[async_trait]
 impl MultipleFilesSink for SingleFileSink {
-    fn process(&mut self, proto_data: &mut &[u8], block_number: i64) -> Result<Vec<File>, String> {
-        if let Some(file_data) = self.file_sink.process(proto_data, block_number)? {
-            let output_files = vec![File::new(file_data, self.sink_output_location.get_file_location(self.starting_block_number, block_number, &self.encoding_type))];
-            self.starting_block_number = block_number + 1;
-            Ok(output_files)
-        } else {
-            Ok(Vec::new())
+    fn process(&mut self, proto_data: &mut &[u8], block_metadata: &BlockMetadata) -> Result<(), String> {
+        self.file_sink.process(proto_data, block_metadata)
+    }
+
+    fn notify_block_end(&mut self, block_number: i64) -> Vec<File> {
+        if !self.file_sink.is_full() {
+            return Vec::new();
         }
+
+        let file_data = self.file_sink.make_file();
+        let output_files = vec![File::new(file_data, self.sink_output_location.get_file_location(self.starting_block_number, block_number, self.partition_folder_name.as_deref(), &self.encoding_type))];
+        self.starting_block_number = block_number + 1;
+        output_files
     }
 
     fn flush_leftovers(&mut self, block_number: i64) -> Vec<File> {
@@ -57,7 +87,7 @@ impl MultipleFilesSink for SingleFileSink {
 
         let mut output = Vec::new();
         if !file_data.is_empty() {
-            output.push(File::new(file_data, self.sink_output_location.get_file_location(self.starting_block_number, block_number, &self.encoding_type)));
+            output.push(File::new(file_data, self.sink_output_location.get_file_location(self.starting_block_number, block_number, self.partition_folder_name.as_deref(), &self.encoding_type)));
         }
 
         self.starting_block_number = block_number + 1;
@@ -68,16 +98,54 @@ impl MultipleFilesSink for SingleFileSink {
         vec![self.sink_output_location.clone()]
     }
 
-    fn get_bucket_name(&self) -> Option<String> {
-        match &self.sink_output_location {
-            Location::DataWarehouse(_, bucket_name) => Some(bucket_name.to_string()),
-            _ => None,
+    fn notify_new_block(&mut self, block_number: i64, block_timestamp: Option<i64>) -> Result<Vec<File>, String> {
+        let partition_folder_name = self.sink_output_location.get_partition_folder_name(block_number, block_timestamp)?;
+
+        let mut output_files = Vec::new();
+        if self.partition_folder_name.is_some() && self.partition_folder_name != partition_folder_name {
+            // Files can't span partitions so anything built up so far gets written to the previous partition. Either way
+            // the next file starts at this block so that it's name doesn't cover blocks from the previous partition
+            let file_data = self.file_sink.make_file();
+            if !file_data.is_empty() {
+                output_files.push(File::new(file_data, self.sink_output_location.get_file_location(self.starting_block_number, block_number - 1, self.partition_folder_name.as_deref(), &self.encoding_type)));
+            }
+            self.starting_block_number = block_number;
         }
-    }
 
-    fn notify_new_block(&mut self, _block_number: i64) {}
+        self.partition_folder_name = partition_folder_name;
+        Ok(output_files)
+    }
 
-    async fn set_starting_block_number(&mut self, starting_block_number: i64) {
+    async fn set_starting_block_number(&mut self, starting_block_number: i64) -> Result<(), String> {
         self.starting_block_number = starting_block_number;
+        Ok(())
     }
-}
\ No newline at end of file
+
+    /// Only the latest file gets checked, as any files before it would have been checked when it was appended
+    async fn check_output_schema_compatibility(&self) -> Result<(), String> {
+        let parquet_schema = if let Some(parquet_schema) = self.parquet_schema.as_ref() {
+            parquet_schema
+        } else {
+            return Ok(());
+        };
+
+        let latest_file_path = if let Some(latest_file_path) = get_latest_file_path(&self.sink_output_location).await? {
+            latest_file_path
+        } else {
+            return Ok(());
+        };
+
+        let storage = self.sink_output_location.get_storage();
+        let latest_file_data = storage.get(&latest_file_path).await?.ok_or_else(|| format!("Unable to read existing file: {}!", storage.get_display_path(&latest_file_path)))?;
+
+        let entity_name = parquet_schema.name();
+        let breaking_changes = get_breaking_changes_from_file(entity_name, latest_file_data, parquet_schema)
+            .map_err(|error| format!("Unable to check schema of existing file: {}! {}", storage.get_display_path(&latest_file_path), error))?;
+        if !breaking_changes.is_empty() {
+            let breaking_changes = breaking_changes.iter().map(|schema_change| format!("  {}", schema_change)).collect::<Vec<_>>().join("\n");
+            return Err(format!("Output schema of: {} is incompatible with existing file: {}! Bump the package version to write to a new version folder instead. Schema changes:\n{}", entity_name, storage.get_display_path(&latest_file_path), breaking_changes));
+        }
+
+        Ok(())
+    }
+}
@@ -1,9 +1,14 @@
+use std::collections::VecDeque;
 use std::path::PathBuf;
-use derives::proto_structure_info::MessageInfo;
+use std::sync::Arc;
+use derives::proto_structure_info::{FieldInfo, MessageInfo};
 
-use crate::streaming_fast::file::{File, Location, LocationType};
+use crate::streaming_fast::file::{CursorInfo, File, Location};
+use crate::streaming_fast::file_sinks::file_sink::BlockMetadata;
 use crate::streaming_fast::multiple_files_sink::MultipleFilesSink;
 use crate::streaming_fast::process_substream::EncodingType;
+use crate::streaming_fast::storage::StorageBackend;
+use crate::streaming_fast::streaming_config::{BlockMetadataColumns, PartitionScheme};
 use crate::streaming_fast::streaming_fast_utils::FromUnsignedVarint;
 use crate::streaming_fast::single_file_sink::SingleFileSink;
 use crate::streaming_fast::split_files_sink::SplitFilesSink;
@@ -11,47 +16,162 @@ use crate::streaming_fast::split_files_sink::SplitFilesSink;
 pub(crate) struct Sink {
     items_field_number: Option<u64>,
     multiple_files_sink: Box<dyn MultipleFilesSink>,
+    /// Only used in live mode. Holds the output of blocks that aren't final yet so that their rows
+    /// can still be rolled back if a BlockUndoSignal arrives before they get written to a file
+    undo_buffer: Option<VecDeque<BufferedBlock>>,
+    last_cursor_info: Option<CursorInfo>,
 }
 
-impl Sink {
-    pub(crate) fn new(output_type_info: MessageInfo, encoding_type: EncodingType, location_type: LocationType, sink_output_path: PathBuf, bucket_name: Option<String>) -> Self {
+/// How the output of a module gets laid out into entities. A module outputs a single entity unless it's output (or the
+/// items of it's output) is a oneof, in which case each oneof field gets written to it's own folder by SplitFilesSink
+pub(crate) enum OutputLayout {
+    SingleFile(MessageInfo),
+    SplitFiles(Vec<FieldInfo>),
+}
+
+impl OutputLayout {
+    /// Also returns the field number of the items field when the output is a collection of items
+    pub(crate) fn new(output_type_info: MessageInfo) -> (Self, Option<u64>) {
         if output_type_info.is_collection_of_items() {
             let (inner_type_info, items_field_number) = output_type_info.get_item_type_info();
 
             if inner_type_info.is_oneof_type() {
-                Sink {
-                    items_field_number: Some(items_field_number),
-                    multiple_files_sink: Box::new(SplitFilesSink::new(inner_type_info.fields, encoding_type, location_type, sink_output_path, bucket_name)),
-                }
+                (OutputLayout::SplitFiles(inner_type_info.fields), Some(items_field_number))
             } else {
-                Sink {
-                    items_field_number: Some(items_field_number),
-                    multiple_files_sink: Box::new(SingleFileSink::new(inner_type_info, encoding_type, location_type, sink_output_path, bucket_name)),
-                }
+                (OutputLayout::SingleFile(inner_type_info), Some(items_field_number))
             }
         } else if output_type_info.is_oneof_type() {
-            Sink {
-                items_field_number: None,
-                multiple_files_sink: Box::new(SplitFilesSink::new(output_type_info.fields, encoding_type, location_type, sink_output_path, bucket_name)),
-            }
+            (OutputLayout::SplitFiles(output_type_info.fields), None)
         } else {
-            Sink {
-                items_field_number: None,
-                multiple_files_sink: Box::new(SingleFileSink::new(output_type_info, encoding_type, location_type, sink_output_path, bucket_name)),
+            (OutputLayout::SingleFile(output_type_info), None)
+        }
+    }
+
+    /// Type info of each of the entities, each of which gets written out by it's own SingleFileSink
+    pub(crate) fn get_entity_type_infos(self) -> Vec<MessageInfo> {
+        match self {
+            OutputLayout::SingleFile(entity_type_info) => vec![entity_type_info],
+            OutputLayout::SplitFiles(oneof_fields) => oneof_fields.into_iter().map(|field| field.get_struct_info().0).collect(),
+        }
+    }
+}
+
+struct BufferedBlock {
+    proto_data: Vec<u8>,
+    cursor_info: CursorInfo,
+    block_hash: String,
+    block_timestamp: Option<i64>,
+}
+
+impl Sink {
+    pub(crate) fn new(output_type_info: MessageInfo, encoding_type: EncodingType, storage: Arc<dyn StorageBackend>, sink_output_path: PathBuf, partition_scheme: Option<PartitionScheme>, block_metadata_columns: BlockMetadataColumns) -> Self {
+        let (output_layout, items_field_number) = OutputLayout::new(output_type_info);
+
+        let multiple_files_sink: Box<dyn MultipleFilesSink> = match output_layout {
+            OutputLayout::SingleFile(entity_type_info) => Box::new(SingleFileSink::new(entity_type_info, encoding_type, storage, sink_output_path, partition_scheme, block_metadata_columns)),
+            OutputLayout::SplitFiles(oneof_fields) => Box::new(SplitFilesSink::new(oneof_fields, encoding_type, storage, sink_output_path, partition_scheme, block_metadata_columns)),
+        };
+
+        Sink {
+            items_field_number,
+            multiple_files_sink,
+            undo_buffer: None,
+            last_cursor_info: None,
+        }
+    }
+
+    pub(crate) async fn set_starting_block_number(&mut self, starting_block_number: i64) -> Result<(), String> {
+        self.multiple_files_sink.set_starting_block_number(starting_block_number).await
+    }
+
+    pub(crate) async fn check_output_schema_compatibility(&self) -> Result<(), String> {
+        self.multiple_files_sink.check_output_schema_compatibility().await
+    }
+
+    /// Enables live mode where non-final blocks are buffered until they become final (or get undone)
+    pub(crate) fn enable_live_mode(&mut self) {
+        self.undo_buffer = Some(VecDeque::new());
+    }
+
+    /// Processes the output for a block. In live mode the output is held back until the block is final
+    /// which means the files returned can contain data from previously received blocks.
+    pub(crate) fn process(&mut self, proto_data: Vec<u8>, cursor_info: CursorInfo, block_hash: String, block_timestamp: Option<i64>, final_block_height: i64) -> Result<Vec<File>, String> {
+        if let Some(undo_buffer) = self.undo_buffer.as_mut() {
+            undo_buffer.push_back(BufferedBlock {
+                proto_data,
+                cursor_info,
+                block_hash,
+                block_timestamp,
+            });
+
+            let mut output_files = Vec::new();
+            while let Some(buffered_block) = self.undo_buffer.as_mut().unwrap().pop_front() {
+                if buffered_block.cursor_info.block_number > final_block_height {
+                    self.undo_buffer.as_mut().unwrap().push_front(buffered_block);
+                    break;
+                }
+                output_files.extend(self.process_final_block(buffered_block.proto_data, buffered_block.cursor_info, buffered_block.block_hash, buffered_block.block_timestamp)?);
             }
+            Ok(output_files)
+        } else {
+            self.process_final_block(proto_data, cursor_info, block_hash, block_timestamp)
         }
     }
 
-    pub(crate) async fn set_starting_block_number(&mut self, starting_block_number: i64) {
-        self.multiple_files_sink.set_starting_block_number(starting_block_number).await;
+    /// Drops the buffered output of every block above last_valid_block. Returns an error if any of
+    /// those blocks have already been written out, as that data can no longer be rolled back.
+    pub(crate) fn undo(&mut self, last_valid_block: i64) -> Result<(), String> {
+        if let Some(last_cursor_info) = self.last_cursor_info.as_ref() {
+            if last_cursor_info.block_number > last_valid_block {
+                return Err(format!("Received undo signal back to block: {}, although block: {} has already been passed on to the file sinks as final!", last_valid_block, last_cursor_info.block_number));
+            }
+        }
+
+        let undo_buffer = if let Some(undo_buffer) = self.undo_buffer.as_mut() {
+            undo_buffer
+        } else {
+            return Err(format!("Received undo signal back to block: {}, although the sink isn't running in live mode!", last_valid_block));
+        };
+
+        let num_blocks_before_undo = undo_buffer.len();
+        undo_buffer.retain(|buffered_block| buffered_block.cursor_info.block_number <= last_valid_block);
+        println!("Undo signal received! Rolled back {} buffered blocks above block: {}", num_blocks_before_undo - undo_buffer.len(), last_valid_block);
+
+        Ok(())
     }
 
-    pub(crate) fn process(&mut self, proto_data: Vec<u8>, block_number: i64) -> Result<Vec<File>, String> {
-        self.multiple_files_sink.notify_new_block(block_number);
+    fn process_final_block(&mut self, proto_data: Vec<u8>, cursor_info: CursorInfo, block_hash: String, block_timestamp: Option<i64>) -> Result<Vec<File>, String> {
+        // Files closed off because this block starts a new partition only hold data up to the previous block
+        let mut output_files = self.multiple_files_sink.notify_new_block(cursor_info.block_number, block_timestamp)?;
+        if let Some(last_cursor_info) = self.last_cursor_info.as_ref() {
+            for file in output_files.iter_mut() {
+                file.set_cursor_info(last_cursor_info.clone());
+            }
+        }
+
+        let mut block_metadata = BlockMetadata {
+            block_number: cursor_info.block_number,
+            block_hash,
+            block_timestamp,
+            row_index: 0,
+        };
+        self.process_block_data(proto_data, &mut block_metadata)?;
+        // Files are only closed off once all of the block's rows are in, so the block's cursor covers everything in them
+        let mut block_output_files = self.multiple_files_sink.notify_block_end(cursor_info.block_number);
+        for file in block_output_files.iter_mut() {
+            file.set_cursor_info(cursor_info.clone());
+        }
+        output_files.extend(block_output_files);
 
+        self.last_cursor_info = Some(cursor_info);
+        Ok(output_files)
+    }
+
+    /// Passes each of the rows in the block's output on to the file sinks. row_index is bumped for each item so that
+    /// (block_number, row_index) uniquely identifies a row
+    fn process_block_data(&mut self, proto_data: Vec<u8>, block_metadata: &mut BlockMetadata) -> Result<(), String> {
         if let Some(items_field_number) = self.items_field_number {
             let mut data_slice = proto_data.as_slice();
-            let mut output_files = Vec::new();
             loop {
                 if data_slice.is_empty() {
                     break;
@@ -82,11 +202,12 @@ impl Sink {
                 let (mut consumed, remainder) = data_slice.split_at(struct_data_length);
                 data_slice = remainder;
 
-                output_files.extend(self.multiple_files_sink.process(&mut consumed, block_number)?);
+                self.multiple_files_sink.process(&mut consumed, block_metadata)?;
+                block_metadata.row_index += 1;
             }
-            Ok(output_files)
+            Ok(())
         } else {
-            self.multiple_files_sink.process(&mut proto_data.as_slice(), block_number)
+            self.multiple_files_sink.process(&mut proto_data.as_slice(), block_metadata)
         }
     }
 
@@ -96,7 +217,80 @@ impl Sink {
 
     /// Instead of waiting for file to "fill" to required size, instead you can call this method to
     /// make a file out of the data you have parsed so far
-    pub(crate) fn flush_leftovers(&mut self, block_number: i64) -> Vec<File> {
-        self.multiple_files_sink.flush_leftovers(block_number)
+    pub(crate) fn flush_leftovers(&mut self, mut block_number: i64) -> Vec<File> {
+        if let Some(undo_buffer) = self.undo_buffer.as_mut() {
+            if !undo_buffer.is_empty() {
+                // Blocks that never became final are dropped - they will be streamed again on the next run
+                println!("Discarding {} non-final blocks from the undo buffer!", undo_buffer.len());
+                undo_buffer.clear();
+                if let Some(last_cursor_info) = self.last_cursor_info.as_ref() {
+                    block_number = last_cursor_info.block_number + 1;
+                }
+            }
+        }
+
+        let mut output_files = self.multiple_files_sink.flush_leftovers(block_number);
+        if let Some(last_cursor_info) = self.last_cursor_info.as_ref() {
+            for file in output_files.iter_mut() {
+                file.set_cursor_info(last_cursor_info.clone());
+            }
+        }
+        output_files
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::Arc;
+    use derives::{ProtoInfo, TestData};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    use crate::streaming_fast::file::CursorInfo;
+    use crate::streaming_fast::process_substream::EncodingType;
+    use crate::streaming_fast::sink::Sink;
+    use crate::streaming_fast::storage::LocalStorage;
+    use crate::streaming_fast::streaming_config::{BlockMetadataColumns, ParquetConfig};
+    use crate::test_utils::get_temp_dir;
+
+    #[derive(TestData)]
+    pub struct Transfer {
+        amount: u64,
     }
-}
\ No newline at end of file
+
+    #[derive(TestData)]
+    pub struct Transfers {
+        items: Vec<Transfer>,
+    }
+
+    #[tokio::test]
+    async fn test_files_only_rotate_at_block_boundaries() {
+        let temp_dir = get_temp_dir();
+        // A file size threshold of 1 byte means the file is full as soon as the first item of a block is in
+        let encoding_type = EncodingType::Parquet(ParquetConfig { file_size_threshold: 1, ..ParquetConfig::default() });
+        let mut sink = Sink::new(Transfers::get_proto_structure_info(), encoding_type, Arc::new(LocalStorage), temp_dir.path().to_path_buf(), None, BlockMetadataColumns::default());
+        sink.set_starting_block_number(1).await.unwrap();
+
+        for block_number in 1..3 {
+            let transfers = Transfers { items: (0..3).map(|amount| Transfer { amount }).collect() };
+            let cursor_info = CursorInfo { cursor: format!("cursor{}", block_number), block_number };
+            let output_files = sink.process(transfers.to_proto_bytes(), cursor_info, String::new(), None, block_number).unwrap();
+            assert_eq!(output_files.len(), 1);
+
+            for output_file in output_files {
+                output_file.save().await.unwrap().unwrap().save().await.unwrap();
+            }
+        }
+
+        // Every file holds all of the items of it's block along with that block's cursor
+        let output_folder = temp_dir.path().join("Transfer").join("parquet");
+        for block_number in 1..3 {
+            let file_data = fs::read(output_folder.join(format!("{}_{}.parquet", block_number, block_number))).unwrap();
+            let reader = SerializedFileReader::new(bytes::Bytes::from(file_data)).unwrap();
+            assert_eq!(reader.metadata().file_metadata().num_rows(), 3);
+
+            let cursor_data = fs::read(output_folder.join(format!("{}_{}.cursor", block_number, block_number))).unwrap();
+            let cursor_info: CursorInfo = serde_json::from_slice(&cursor_data).unwrap();
+            assert_eq!((cursor_info.cursor, cursor_info.block_number), (format!("cursor{}", block_number), block_number));
+        }
+    }
+}
@@ -1,36 +1,37 @@
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::Arc;
 use derives::proto_structure_info::FieldInfo;
 use async_trait::async_trait;
 
-use crate::streaming_fast::file::{File, Location, LocationType};
+use crate::streaming_fast::file::{File, Location};
+use crate::streaming_fast::file_sinks::file_sink::BlockMetadata;
 use crate::streaming_fast::multiple_files_sink::MultipleFilesSink;
 use crate::streaming_fast::process_substream::EncodingType;
-use crate::streaming_fast::streaming_fast_utils::{FromUnsignedVarint, get_start_block_numbers};
+use crate::streaming_fast::storage::StorageBackend;
+use crate::streaming_fast::streaming_config::{BlockMetadataColumns, PartitionScheme};
+use crate::streaming_fast::streaming_fast_utils::{FromUnsignedVarint, get_start_positions};
 use crate::streaming_fast::single_file_sink::SingleFileSink;
 
 pub(crate) struct SplitFilesSink {
     file_sinks: HashMap<u64, SingleFileSink>,
     pending_sinks: HashMap<u64, i64>, // i64 is the starting block corresponding to the sink
-    bucket_name: Option<String>,
 }
 
 impl SplitFilesSink {
-    pub(crate) fn new(oneof_fields: Vec<FieldInfo>, encoding_type: EncodingType, location_type: LocationType, sink_output_path: PathBuf, bucket_name: Option<String>) -> Self {
+    pub(crate) fn new(oneof_fields: Vec<FieldInfo>, encoding_type: EncodingType, storage: Arc<dyn StorageBackend>, sink_output_path: PathBuf, partition_scheme: Option<PartitionScheme>, block_metadata_columns: BlockMetadataColumns) -> Self {
         SplitFilesSink {
             file_sinks: oneof_fields.into_iter().map(|field| {
-                (field.field_number, SingleFileSink::new(field.get_struct_info().0, encoding_type.clone(), location_type.clone(), sink_output_path.clone(), bucket_name.clone()))
+                (field.field_number, SingleFileSink::new(field.get_struct_info().0, encoding_type.clone(), storage.clone(), sink_output_path.clone(), partition_scheme.clone(), block_metadata_columns))
             }).collect(),
             pending_sinks: Default::default(),
-            bucket_name: bucket_name,
         }
     }
 }
 
 #[async_trait]
 impl MultipleFilesSink for SplitFilesSink {
-    fn process(&mut self, proto_data: &mut &[u8], block_number: i64) -> Result<Vec<File>, String> {
-        let mut output_files = Vec::new();
+    fn process(&mut self, proto_data: &mut &[u8], block_metadata: &BlockMetadata) -> Result<(), String> {
         let mut field_seen = None;
         loop {
             if proto_data.is_empty() {
@@ -71,17 +72,21 @@ impl MultipleFilesSink for SplitFilesSink {
             *proto_data = remainder;
 
             if !self.pending_sinks.contains_key(&field_number) {
-                output_files.extend(self.file_sinks.get_mut(&field_number).unwrap().process(&mut consumed, block_number)?);
+                self.file_sinks.get_mut(&field_number).unwrap().process(&mut consumed, block_metadata)?;
             }
         }
 
         if field_seen.is_none() {
             Err("No fields seen when deserializing proto response!".to_string())
         } else {
-            Ok(output_files)
+            Ok(())
         }
     }
 
+    fn notify_block_end(&mut self, block_number: i64) -> Vec<File> {
+        self.file_sinks.values_mut().flat_map(|file_sink| file_sink.notify_block_end(block_number)).collect()
+    }
+
     fn flush_leftovers(&mut self, block_number: i64) -> Vec<File> {
         let mut output_files = Vec::new();
 
@@ -96,11 +101,7 @@ impl MultipleFilesSink for SplitFilesSink {
         self.file_sinks.values().flat_map(|file_sink| file_sink.get_output_folder_locations()).collect()
     }
 
-    fn get_bucket_name(&self) -> Option<String> {
-        self.bucket_name.clone()
-    }
-
-    fn notify_new_block(&mut self, block_number: i64) {
+    fn notify_new_block(&mut self, block_number: i64, block_timestamp: Option<i64>) -> Result<Vec<File>, String> {
         let mut keys_to_remove = HashSet::new();
         for (key, val) in self.pending_sinks.clone() {
             if block_number >= val {
@@ -111,17 +112,31 @@ impl MultipleFilesSink for SplitFilesSink {
         for key in keys_to_remove.into_iter() {
             self.pending_sinks.remove(&key);
         }
+
+        let mut output_files = Vec::new();
+        for file_sink in self.file_sinks.values_mut() {
+            output_files.extend(file_sink.notify_new_block(block_number, block_timestamp)?);
+        }
+        Ok(output_files)
     }
 
-    async fn set_starting_block_number(&mut self, starting_block_number: i64) {
-        let starting_block_numbers = get_start_block_numbers(self.get_output_folder_locations(), starting_block_number).await;
+    async fn set_starting_block_number(&mut self, starting_block_number: i64) -> Result<(), String> {
+        let starting_block_numbers = get_start_positions(self.get_output_folder_locations(), starting_block_number).await?.into_iter().map(|start_position| start_position.block_number).collect::<Vec<_>>();
         let min_starting_block = starting_block_numbers.iter().min().unwrap().clone();
 
         for ((sink_id, sink), starting_block_number) in self.file_sinks.iter_mut().zip(starting_block_numbers.into_iter()) {
-            sink.set_starting_block_number(starting_block_number).await;
+            sink.set_starting_block_number(starting_block_number).await?;
             if starting_block_number > min_starting_block {
                 self.pending_sinks.insert(sink_id.clone(), starting_block_number);
             }
         }
+        Ok(())
+    }
+
+    async fn check_output_schema_compatibility(&self) -> Result<(), String> {
+        for file_sink in self.file_sinks.values() {
+            file_sink.check_output_schema_compatibility().await?;
+        }
+        Ok(())
     }
-}
\ No newline at end of file
+}
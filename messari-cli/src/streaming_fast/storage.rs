@@ -0,0 +1,273 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use async_trait::async_trait;
+use aws_sdk_s3::config::Region;
+use aws_sdk_s3::primitives::ByteStream;
+use tokio::fs;
+
+use crate::streaming_fast::file::LocationType;
+use crate::streaming_fast::streaming_config::StorageConfig;
+
+const GCS_ENDPOINT: &str = "https://storage.googleapis.com";
+
+/// Where the sink files (and their cursors) get written to and read back from when restarting
+#[async_trait]
+pub(crate) trait StorageBackend: Send + Sync {
+    /// The file only shows up at the given path once all of it's data has been written
+    async fn put(&self, path: &Path, data: Vec<u8>) -> Result<(), String>;
+
+    /// Returns None if there is no file at the given path
+    async fn get(&self, path: &Path) -> Result<Option<Vec<u8>>, String>;
+
+    /// Deleting a file that doesn't exist is not treated as an error
+    async fn delete(&self, path: &Path) -> Result<(), String>;
+
+    /// Returns the paths of all files directly within the given folder along with their sizes (in bytes). A folder that
+    /// doesn't exist is treated as empty
+    async fn list_with_sizes(&self, folder_path: &Path) -> Result<Vec<(PathBuf, usize)>, String>;
+
+    /// Returns the paths of all files directly within the given folder. A folder that doesn't exist is treated as empty
+    async fn list(&self, folder_path: &Path) -> Result<Vec<PathBuf>, String> {
+        Ok(self.list_with_sizes(folder_path).await?.into_iter().map(|(file_path, _)| file_path).collect())
+    }
+
+    /// Returns the paths of all folders directly within the given folder
+    async fn list_folders(&self, folder_path: &Path) -> Result<Vec<PathBuf>, String>;
+
+    /// Full path of a file as it should be shown to the user (eg. s3://bucket/path)
+    fn get_display_path(&self, path: &Path) -> String;
+}
+
+pub(crate) async fn get_storage_backend(location_type: LocationType, bucket_name: Option<String>, storage_config: &StorageConfig) -> Result<Arc<dyn StorageBackend>, String> {
+    match location_type {
+        LocationType::Local => Ok(Arc::new(LocalStorage)),
+        LocationType::DataWarehouse => {
+            let bucket_name = bucket_name.ok_or_else(|| "Bucket is mandatory if location type is DWH".to_string())?;
+            Ok(Arc::new(S3Storage::new(bucket_name, storage_config).await))
+        }
+    }
+}
+
+pub(crate) struct LocalStorage;
+
+#[async_trait]
+impl StorageBackend for LocalStorage {
+    async fn put(&self, path: &Path, data: Vec<u8>) -> Result<(), String> {
+        if let Some(parent_folder) = path.parent() {
+            fs::create_dir_all(parent_folder).await.map_err(|error| format!("Unable to create folder: {}! Error: {}", parent_folder.display(), error))?;
+        }
+
+        // Written to a hidden file first and then moved into place so that a half written file is never listed
+        let temp_path = path.with_file_name(format!(".{}.tmp", path.file_name().unwrap().to_string_lossy()));
+        fs::write(&temp_path, data).await.map_err(|error| format!("Unable to write file: {}! Error: {}", path.display(), error))?;
+        fs::rename(&temp_path, path).await.map_err(|error| format!("Unable to write file: {}! Error: {}", path.display(), error))
+    }
+
+    async fn get(&self, path: &Path) -> Result<Option<Vec<u8>>, String> {
+        match fs::read(path).await {
+            Ok(data) => Ok(Some(data)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(format!("Unable to read file: {}! Error: {}", path.display(), error)),
+        }
+    }
+
+    async fn delete(&self, path: &Path) -> Result<(), String> {
+        match fs::remove_file(path).await {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(format!("Unable to delete file: {}! Error: {}", path.display(), error)),
+        }
+    }
+
+    async fn list_with_sizes(&self, folder_path: &Path) -> Result<Vec<(PathBuf, usize)>, String> {
+        let mut read_dir = match fs::read_dir(folder_path).await {
+            Ok(read_dir) => read_dir,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(format!("Unable to list folder: {}! Error: {}", folder_path.display(), error)),
+        };
+
+        let mut files = Vec::new();
+        while let Some(entry) = read_dir.next_entry().await.map_err(|error| format!("Unable to list folder: {}! Error: {}", folder_path.display(), error))? {
+            if let Ok(metadata) = entry.metadata().await {
+                if metadata.is_file() && !entry.file_name().to_string_lossy().starts_with('.') {
+                    files.push((entry.path(), metadata.len() as usize));
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    async fn list_folders(&self, folder_path: &Path) -> Result<Vec<PathBuf>, String> {
+        let mut read_dir = match fs::read_dir(folder_path).await {
+            Ok(read_dir) => read_dir,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(format!("Unable to list folder: {}! Error: {}", folder_path.display(), error)),
+        };
+
+        let mut folder_paths = Vec::new();
+        while let Some(entry) = read_dir.next_entry().await.map_err(|error| format!("Unable to list folder: {}! Error: {}", folder_path.display(), error))? {
+            if entry.file_type().await.map(|file_type| file_type.is_dir()).unwrap_or(false) {
+                folder_paths.push(entry.path());
+            }
+        }
+        Ok(folder_paths)
+    }
+
+    fn get_display_path(&self, path: &Path) -> String {
+        path.display().to_string()
+    }
+}
+
+/// Any S3 compatible object store. This covers AWS itself, local emulators such as MinIO (by setting the endpoint and
+/// path style addressing) and GCS through it's interoperability api using HMAC keys as the aws credentials
+pub(crate) struct S3Storage {
+    client: aws_sdk_s3::Client,
+    bucket_name: String,
+}
+
+impl S3Storage {
+    pub(crate) async fn new(bucket_name: String, storage_config: &StorageConfig) -> Self {
+        let (region, endpoint, force_path_style) = match storage_config {
+            StorageConfig::S3 { region, endpoint, force_path_style } => (region.clone(), endpoint.clone(), *force_path_style),
+            StorageConfig::Gcs { endpoint } => ("auto".to_string(), Some(endpoint.clone().unwrap_or(GCS_ENDPOINT.to_string())), false),
+        };
+
+        let sdk_config = aws_config::from_env().region(Region::new(region)).load().await;
+        let mut s3_config_builder = aws_sdk_s3::config::Builder::from(&sdk_config).force_path_style(force_path_style);
+        if let Some(endpoint) = endpoint {
+            s3_config_builder = s3_config_builder.endpoint_url(endpoint);
+        }
+
+        S3Storage {
+            client: aws_sdk_s3::Client::from_conf(s3_config_builder.build()),
+            bucket_name,
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for S3Storage {
+    async fn put(&self, path: &Path, data: Vec<u8>) -> Result<(), String> {
+        self.client.put_object()
+            .bucket(&self.bucket_name)
+            .key(path.to_string_lossy())
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(|error| format!("Unable to upload file: {}! Error: {}", self.get_display_path(path), error))?;
+        Ok(())
+    }
+
+    async fn get(&self, path: &Path) -> Result<Option<Vec<u8>>, String> {
+        let response = match self.client.get_object().bucket(&self.bucket_name).key(path.to_string_lossy()).send().await {
+            Ok(response) => response,
+            Err(error) => {
+                if error.as_service_error().map(|service_error| service_error.is_no_such_key()).unwrap_or(false) {
+                    return Ok(None);
+                }
+                return Err(format!("Unable to download file: {}! Error: {}", self.get_display_path(path), error));
+            }
+        };
+
+        let data = response.body.collect().await.map_err(|error| format!("Unable to download file: {}! Error: {}", self.get_display_path(path), error))?;
+        Ok(Some(data.into_bytes().to_vec()))
+    }
+
+    async fn delete(&self, path: &Path) -> Result<(), String> {
+        self.client.delete_object()
+            .bucket(&self.bucket_name)
+            .key(path.to_string_lossy())
+            .send()
+            .await
+            .map_err(|error| format!("Unable to delete file: {}! Error: {}", self.get_display_path(path), error))?;
+        Ok(())
+    }
+
+    async fn list_with_sizes(&self, folder_path: &Path) -> Result<Vec<(PathBuf, usize)>, String> {
+        let prefix = format!("{}/", folder_path.to_string_lossy());
+
+        let mut files = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let response = self.client.list_objects_v2()
+                .bucket(&self.bucket_name)
+                .prefix(&prefix)
+                .delimiter("/")
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(|error| format!("Unable to list folder: {}! Error: {}", self.get_display_path(folder_path), error))?;
+
+            files.extend(response.contents().unwrap_or_default().iter().filter_map(|object| {
+                object.key().map(|key| (PathBuf::from(key), object.size().max(0) as usize))
+            }));
+
+            continuation_token = response.next_continuation_token().map(|token| token.to_string());
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(files)
+    }
+
+    async fn list_folders(&self, folder_path: &Path) -> Result<Vec<PathBuf>, String> {
+        let prefix = format!("{}/", folder_path.to_string_lossy());
+
+        let mut folder_paths = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let response = self.client.list_objects_v2()
+                .bucket(&self.bucket_name)
+                .prefix(&prefix)
+                .delimiter("/")
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(|error| format!("Unable to list folder: {}! Error: {}", self.get_display_path(folder_path), error))?;
+
+            // Folders only exist in S3 as the common prefixes of the keys beneath them (eg. path/dt=2023-05-01/)
+            folder_paths.extend(response.common_prefixes().unwrap_or_default().iter().filter_map(|common_prefix| {
+                common_prefix.prefix().map(|folder_prefix| PathBuf::from(folder_prefix.trim_end_matches('/')))
+            }));
+
+            continuation_token = response.next_continuation_token().map(|token| token.to_string());
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(folder_paths)
+    }
+
+    fn get_display_path(&self, path: &Path) -> String {
+        format!("s3://{}/{}", self.bucket_name, path.to_string_lossy())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::streaming_fast::storage::{LocalStorage, StorageBackend};
+    use crate::test_utils::get_temp_dir;
+
+    #[tokio::test]
+    async fn test_local_storage_round_trip() {
+        let temp_dir = get_temp_dir();
+        let folder_path = temp_dir.path().join("nested");
+        let storage = LocalStorage;
+
+        // Nothing has been written yet so the folder shouldn't exist
+        assert!(storage.list(&folder_path).await.unwrap().is_empty());
+        assert_eq!(storage.get(&folder_path.join("0_9.parquet")).await.unwrap(), None);
+
+        storage.put(&folder_path.join("0_9.parquet"), vec![1, 2, 3]).await.unwrap();
+        storage.put(&folder_path.join("0_9.cursor"), vec![4]).await.unwrap();
+
+        assert_eq!(storage.get(&folder_path.join("0_9.parquet")).await.unwrap(), Some(vec![1, 2, 3]));
+
+        let mut file_paths = storage.list(&folder_path).await.unwrap();
+        file_paths.sort();
+        assert_eq!(file_paths, vec![folder_path.join("0_9.cursor"), folder_path.join("0_9.parquet")]);
+    }
+}
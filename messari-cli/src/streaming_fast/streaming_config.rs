@@ -1,7 +1,12 @@
+use chrono::NaiveDateTime;
 use clap::Parser;
 use serde::{Deserialize, Serialize};
+use std::env;
 use std::path::PathBuf;
-use tonic::transport::Uri;
+use std::time::Duration;
+use tonic::metadata::MetadataValue;
+use tonic::Status;
+use tonic::transport::{Channel, Uri};
 
 #[derive(Parser)]
 pub(crate) struct ConfigArg {
@@ -11,30 +16,92 @@ pub(crate) struct ConfigArg {
 
 impl ConfigArg {
     pub(crate) fn parse(&self) -> StreamingConfig {
-        serde_json::from_str(self.config.as_str()).unwrap()
+        StreamingConfig::from_json(self.config.as_str()).unwrap_or_else(|error| panic!("Invalid config! {}", error))
     }
 }
 
 #[derive(Serialize, Deserialize)]
 pub(crate) struct StreamingConfig {
     pub(crate) name: Option<String>,
-    pub(crate) output_module: String,
+    /// Kept for configs written before output_modules was added. Gets processed along with the output_modules
+    #[serde(default)]
+    pub(crate) output_module: Option<String>,
+    /// All of these modules are processed from a single stream, each into it's own sink. Either one of the modules has
+    /// to depend on all of the others, or another map module has to depend on all of them (that module then gets
+    /// streamed and it's own output dropped). The streaming endpoint only sends the outputs of the modules a streamed
+    /// module depends on outside of production mode, which gives up on it's parallel execution of the blocks ahead of
+    /// the stream. So for backfilling large block ranges, giving each module it's own run is a lot faster
+    #[serde(default)]
+    pub(crate) output_modules: Vec<String>,
     pub(crate) substream_name_override: Option<String>,
-    pub(crate) chain_override: Option<Chain>,
+    /// Name of the chain to stream from. Without it the chain is taken from the spkg's network, or failing that it's
+    /// block input type
+    pub(crate) chain_override: Option<String>,
+    /// Chains to add to (or replace) the built in chains
+    #[serde(default)]
+    pub(crate) chains: Vec<Chain>,
+    pub(crate) endpoint_override: Option<String>,
     pub(crate) param_overrides: Vec<ParamOverride>,
     pub(crate) start_block_overrides: Vec<StartBlockOverride>,
+    #[serde(default)]
+    pub(crate) parquet: ParquetConfig,
+    #[serde(default)]
+    pub(crate) storage: StorageConfig,
+    #[serde(default)]
+    pub(crate) partition: Option<PartitionScheme>,
+    #[serde(default)]
+    pub(crate) retry: RetryConfig,
+    #[serde(default)]
+    pub(crate) pipeline: PipelineConfig,
+    #[serde(default)]
+    pub(crate) block_metadata_columns: BlockMetadataColumns,
 }
 
 impl StreamingConfig {
-    pub(crate) fn get_start_block_override(&self) -> Option<i64> {
-        let mut start_block_override = None;
-        for start_override in self.start_block_overrides.iter() {
-            if start_override.module == self.output_module {
-                start_block_override = Some(start_override.block_number as i64);
-                break;
+    /// Values that would otherwise only blow up part way through a run get rejected here
+    pub(crate) fn from_json(config: &str) -> Result<StreamingConfig, String> {
+        let config: StreamingConfig = serde_json::from_str(config).map_err(|error| error.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        self.parquet.validate()?;
+        if let Some(partition) = self.partition.as_ref() {
+            partition.validate()?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn get_output_modules(&self) -> Vec<String> {
+        let mut output_modules = self.output_module.iter().cloned().collect::<Vec<_>>();
+        for output_module in self.output_modules.iter() {
+            if !output_modules.contains(output_module) {
+                output_modules.push(output_module.clone());
             }
         }
-        start_block_override
+
+        if output_modules.is_empty() {
+            panic!("No output modules specified in config! Please specify the modules to process with output_modules!");
+        }
+
+        output_modules
+    }
+
+    pub(crate) fn get_chain_override(&self) -> Option<Chain> {
+        self.chain_override.as_ref().map(|chain_name| {
+            Chain::from_name(chain_name, &self.chains).unwrap_or_else(|| panic!("Chain override: {} is neither a built in chain nor one given in the chains field of the config!", chain_name))
+        })
+    }
+
+    /// Earliest start block override given for any of the output modules
+    pub(crate) fn get_start_block_override(&self) -> Option<i64> {
+        let output_modules = self.get_output_modules();
+        self.start_block_overrides.iter()
+            .filter(|start_override| output_modules.contains(&start_override.module))
+            .map(|start_override| start_override.block_number as i64)
+            .min()
     }
 }
 
@@ -50,49 +117,389 @@ pub(crate) struct ParamOverride {
     pub(crate) value: String,
 }
 
-#[derive(Serialize, Deserialize)]
-pub(crate) enum Chain {
-    #[serde(rename = "mainnet")]
-    // We will revert to ethereum-mainnet once other chains are added, but for now it's nicer just to have as mainnet
-    EthereumMainnet,
-    Polygon,
+/// Extra primary key columns that get written after block_number (which is always written) and before the module
+/// output columns. The row index is the position of a row's output within it's block
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub(crate) struct BlockMetadataColumns {
+    pub(crate) block_hash: bool,
+    pub(crate) block_timestamp: bool,
+    pub(crate) row_index: bool,
+}
+
+impl BlockMetadataColumns {
+    pub(crate) fn get_column_names(&self) -> Vec<&'static str> {
+        let mut column_names = vec!["block_number"];
+        if self.block_hash {
+            column_names.push("block_hash");
+        }
+        if self.block_timestamp {
+            column_names.push("block_timestamp");
+        }
+        if self.row_index {
+            column_names.push("row_index");
+        }
+        column_names
+    }
 }
 
-impl Default for Chain {
+/// Writer properties used for parquet encoding. Any field left out of the config falls back to it's default
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct ParquetConfig {
+    pub(crate) compression: ParquetCompression,
+    /// A file gets written out once it's size reaches this threshold (in bytes). Rows that haven't yet been written
+    /// to a row group are counted at their uncompressed size so files will tend to come out a bit smaller than this.
+    /// Also used as the threshold for jsonl and csv files
+    pub(crate) file_size_threshold: usize,
+    pub(crate) max_rows_per_row_group: usize,
+    pub(crate) dictionary_enabled: bool,
+    pub(crate) statistics_enabled: bool,
+    /// How Messari BigInt/BigDecimal messages get stored
+    pub(crate) big_numbers: BigNumberType,
+    /// Stores string/bytes fields named *address or *hash (and Messari Address messages) as fixed length binary
+    pub(crate) fixed_length_addresses_and_hashes: bool,
+}
+
+impl Default for ParquetConfig {
     fn default() -> Self {
-        Chain::EthereumMainnet
+        ParquetConfig {
+            compression: ParquetCompression::default(),
+            file_size_threshold: 10 * 1024 * 1024, // 10MB
+            // Small enough that a file of rows a few hundred bytes in size gets split into a handful of row groups
+            max_rows_per_row_group: 16 * 1024,
+            dictionary_enabled: true,
+            statistics_enabled: true,
+            big_numbers: BigNumberType::default(),
+            fixed_length_addresses_and_hashes: false,
+        }
     }
 }
 
-impl Chain {
-    pub(crate) fn get_proto_block_type(&self) -> String {
-        match self {
-            Chain::EthereumMainnet => "sf.ethereum.type.v2.Block".to_string(),
-            Chain::Polygon => "sf.ethereum.type.v2.Block".to_string(),
+impl ParquetConfig {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.max_rows_per_row_group == 0 {
+            return Err("parquet.max_rows_per_row_group has to be greater than 0!".to_string());
+        }
+        if let BigNumberType::Decimal { precision, big_decimal_scale } = &self.big_numbers {
+            if !(1..=38).contains(precision) {
+                return Err(format!("parquet.big_numbers.precision has to be between 1 and 38! Got: {}", precision));
+            }
+            if big_decimal_scale > precision {
+                return Err(format!("parquet.big_numbers.big_decimal_scale: {} can't be larger than it's precision: {}!", big_decimal_scale, precision));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ParquetCompression {
+    Snappy,
+    Zstd,
+    Gzip,
+    None,
+}
+
+impl Default for ParquetCompression {
+    fn default() -> Self {
+        ParquetCompression::Snappy
+    }
+}
+
+/// Column type for Messari BigInt/BigDecimal messages. Strings (the default) can hold any value, such as raw uint256
+/// amounts. Decimals can be queried as numbers, but any value that doesn't fit their precision fails the run
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum BigNumberType {
+    /// BigInts get a scale of 0 and BigDecimals get big_decimal_scale (extra decimal places are truncated)
+    Decimal {
+        #[serde(default = "get_default_decimal_precision")]
+        precision: u32,
+        #[serde(default = "get_default_big_decimal_scale")]
+        big_decimal_scale: u32,
+    },
+    String,
+}
+
+impl Default for BigNumberType {
+    fn default() -> Self {
+        BigNumberType::String
+    }
+}
+
+/// Largest precision supported by most query engines (and that fits in an i128)
+fn get_default_decimal_precision() -> u32 {
+    38
+}
+
+fn get_default_big_decimal_scale() -> u32 {
+    18
+}
+
+/// Object store that gets written to when the location type is DWH. Defaults to AWS S3 in us-west-2
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum StorageConfig {
+    S3 {
+        #[serde(default = "get_default_s3_region")]
+        region: String,
+        /// Custom endpoint for S3 compatible stores (eg. MinIO running locally)
+        #[serde(default)]
+        endpoint: Option<String>,
+        #[serde(default)]
+        force_path_style: bool,
+    },
+    /// GCS through it's S3 interoperability api. The HMAC keys get picked up from the usual aws credential env vars
+    Gcs {
+        #[serde(default)]
+        endpoint: Option<String>,
+    },
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig::S3 {
+            region: get_default_s3_region(),
+            endpoint: None,
+            force_path_style: false,
+        }
+    }
+}
+
+fn get_default_s3_region() -> String {
+    "us-west-2".to_string()
+}
+
+/// Reconnect behaviour for when the block stream fails with a transient error (or ends before the stop block).
+/// The backoff doubles with each attempt up to max_backoff_ms and resets once a block is received again
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct RetryConfig {
+    pub(crate) max_attempts: u32,
+    pub(crate) initial_backoff_ms: u64,
+    pub(crate) max_backoff_ms: u64,
+}
+
+impl RetryConfig {
+    /// Attempts are counted from 1
+    pub(crate) fn get_backoff(&self, attempt: u32) -> Duration {
+        let backoff_ms = self.initial_backoff_ms.saturating_mul(2_u64.saturating_pow(attempt.saturating_sub(1)));
+        Duration::from_millis(backoff_ms.min(self.max_backoff_ms))
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_attempts: 10,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 60 * 1000,
+        }
+    }
+}
+
+/// Buffer sizes of the channels between reading the stream, processing blocks in the sink and uploading files, along
+/// with how many files can be uploaded at once. Once a buffer fills up the stage before it waits for it to drain
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct PipelineConfig {
+    pub(crate) block_buffer_size: usize,
+    pub(crate) file_buffer_size: usize,
+    pub(crate) upload_concurrency: usize,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        PipelineConfig {
+            block_buffer_size: 1000,
+            file_buffer_size: 16,
+            upload_concurrency: 4,
         }
     }
+}
+
+/// Hive style partition folders (eg. dt=2023-05-01/) placed between the output folder and the files so that
+/// query engines such as Athena, Spark or DuckDB can prune them. A single file never spans more than one partition
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum PartitionScheme {
+    /// Partitions by the UTC date of the block timestamp -> dt=YYYY-MM-DD
+    Date,
+    /// Partitions by ranges of bucket_size blocks -> block_bucket=N where N is the first block number of the range
+    BlockBucket {
+        bucket_size: i64,
+    },
+}
 
-    pub(crate) fn get_endpoint(&self) -> Uri {
+impl PartitionScheme {
+    /// Blocks without a timestamp can't be placed in a date partition, so they return an error rather than being
+    /// written to the wrong date
+    pub(crate) fn get_partition_folder_name(&self, block_number: i64, block_timestamp: Option<i64>) -> Result<String, String> {
         match self {
-            Chain::EthereumMainnet => Uri::from_static("https://mainnet.eth.streamingfast.io:443"),
-            Chain::Polygon => Uri::from_static("https://polygon.streamingfast.io:443"),
+            PartitionScheme::Date => {
+                let block_timestamp = block_timestamp.ok_or_else(|| format!("Block: {} has no timestamp to partition it by date!", block_number))?;
+                let block_datetime = NaiveDateTime::from_timestamp_opt(block_timestamp, 0)
+                    .ok_or_else(|| format!("Block: {} has an out of range timestamp: {}!", block_number, block_timestamp))?;
+                Ok(format!("dt={}", block_datetime.format("%Y-%m-%d")))
+            }
+            PartitionScheme::BlockBucket { bucket_size } => {
+                Ok(format!("block_bucket={}", block_number - block_number.rem_euclid(*bucket_size)))
+            }
         }
     }
 
-    pub(crate) fn default_for_block_type(block_type_str: &str) -> Self {
-        match block_type_str {
-            "sf.ethereum.type.v2.Block" => Chain::EthereumMainnet,
-            _ => panic!(
-                "Unable to identify a default chain for input block type: {}!",
-                block_type_str
-            ),
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        match self {
+            PartitionScheme::BlockBucket { bucket_size } if *bucket_size <= 0 => {
+                Err(format!("partition.bucket_size has to be greater than 0! Got: {}", bucket_size))
+            }
+            _ => Ok(()),
         }
     }
 
-    pub(crate) fn add_chain_folders_to_path(&self, path: PathBuf) -> PathBuf {
+    /// Sorts the partition folders from the one holding the most recent blocks to the one holding the oldest. Any folders
+    /// not belonging to the partition scheme are left out
+    pub(crate) fn sort_partition_folders(&self, folder_paths: Vec<PathBuf>) -> Vec<PathBuf> {
+        let partition_folders = folder_paths.into_iter().filter_map(|folder_path| {
+            let folder_name = folder_path.file_name()?.to_string_lossy().to_string();
+            let (partition_key, partition_value) = folder_name.split_once('=')?;
+            Some((partition_key.to_string(), partition_value.to_string(), folder_path))
+        });
+
         match self {
-            Chain::EthereumMainnet => path.join("ethereum").join("mainnet"),
-            Chain::Polygon => path.join("ethereum").join("polygon"),
+            // Dates in the form YYYY-MM-DD already sort chronologically as strings
+            PartitionScheme::Date => {
+                let mut date_folders = partition_folders.filter(|(partition_key, _, _)| partition_key == "dt").map(|(_, date, folder_path)| (date, folder_path)).collect::<Vec<_>>();
+                date_folders.sort_by(|(date1, _), (date2, _)| date2.cmp(date1));
+                date_folders.into_iter().map(|(_, folder_path)| folder_path).collect()
+            }
+            PartitionScheme::BlockBucket { .. } => {
+                let mut bucket_folders = partition_folders
+                    .filter(|(partition_key, _, _)| partition_key == "block_bucket")
+                    .filter_map(|(_, partition_value, folder_path)| Some((partition_value.parse::<i64>().ok()?, folder_path)))
+                    .collect::<Vec<_>>();
+                bucket_folders.sort_by_key(|(bucket_start_block, _)| -bucket_start_block);
+                bucket_folders.into_iter().map(|(_, folder_path)| folder_path).collect()
+            }
+        }
+    }
+}
+
+/// A network that substreams can be streamed from. Chains not built in to the cli (or built in chains with a different
+/// endpoint etc.) can be given in the chains field of the config
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct Chain {
+    /// Name the chain is referred to by in configs (the same as the networks in config/params.json)
+    pub(crate) name: String,
+    pub(crate) endpoint: String,
+    /// Proto type of the chain's blocks (eg. sf.ethereum.type.v2.Block)
+    pub(crate) block_type: String,
+    /// Folders the output gets written under (eg. ethereum/mainnet)
+    pub(crate) output_path_prefix: String,
+    /// Tag of the block number (or slot) field in the chain's block proto. Used for fetching the latest block number
+    /// without needing the full block proto for every chain
+    #[serde(default = "get_default_block_number_field_tag")]
+    pub(crate) block_number_field_tag: u32,
+}
+
+/// Tag of the number field in sf.ethereum.type.v2.Block
+fn get_default_block_number_field_tag() -> u32 {
+    3
+}
+
+impl Chain {
+    fn new(name: &str, endpoint: &str, block_type: &str, output_path_prefix: &str, block_number_field_tag: u32) -> Self {
+        Chain {
+            name: name.to_string(),
+            endpoint: endpoint.to_string(),
+            block_type: block_type.to_string(),
+            output_path_prefix: output_path_prefix.to_string(),
+            block_number_field_tag,
+        }
+    }
+
+    /// When a block type is shared by more than one chain, the first of those chains is the one used by default
+    pub(crate) fn get_builtin_chains() -> Vec<Chain> {
+        const ETHEREUM_BLOCK_TYPE: &str = "sf.ethereum.type.v2.Block";
+        const SOLANA_BLOCK_TYPE: &str = "sf.solana.type.v1.Block";
+
+        vec![
+            // We will revert to ethereum-mainnet once other chains are added, but for now it's nicer just to have as mainnet
+            Chain::new("mainnet", "https://mainnet.eth.streamingfast.io:443", ETHEREUM_BLOCK_TYPE, "ethereum/mainnet", 3),
+            Chain::new("polygon", "https://polygon.streamingfast.io:443", ETHEREUM_BLOCK_TYPE, "ethereum/polygon", 3),
+            Chain::new("arbitrum", "https://arb-one.streamingfast.io:443", ETHEREUM_BLOCK_TYPE, "ethereum/arbitrum", 3),
+            Chain::new("optimism", "https://opt-mainnet.streamingfast.io:443", ETHEREUM_BLOCK_TYPE, "ethereum/optimism", 3),
+            Chain::new("bsc", "https://bnb.streamingfast.io:443", ETHEREUM_BLOCK_TYPE, "ethereum/bsc", 3),
+            Chain::new("goerli", "https://goerli.eth.streamingfast.io:443", ETHEREUM_BLOCK_TYPE, "ethereum/goerli", 3),
+            Chain::new("solana", "https://mainnet.sol.streamingfast.io:443", SOLANA_BLOCK_TYPE, "solana/mainnet", 20),
+        ]
+    }
+
+    /// Looks up a chain by name (ignoring case). The chains given take precedence over the built in chains
+    pub(crate) fn from_name(name: &str, chains: &[Chain]) -> Option<Chain> {
+        chains.iter().find(|chain| chain.name.eq_ignore_ascii_case(name)).cloned()
+            .or_else(|| Chain::get_builtin_chains().into_iter().find(|chain| chain.name.eq_ignore_ascii_case(name)))
+    }
+
+    pub(crate) fn default_for_block_type(block_type_str: &str, chains: &[Chain]) -> Option<Self> {
+        chains.iter().find(|chain| chain.block_type == block_type_str).cloned()
+            .or_else(|| Chain::get_builtin_chains().into_iter().find(|chain| chain.block_type == block_type_str))
+    }
+
+    pub(crate) fn get_endpoint(&self) -> Result<Uri, String> {
+        self.endpoint.parse::<Uri>().map_err(|_| format!("Invalid endpoint given for chain: {} - {}!", self.name, self.endpoint))
+    }
+
+    pub(crate) fn add_chain_folders_to_path(&self, mut path: PathBuf) -> PathBuf {
+        for folder_name in self.output_path_prefix.split('/').filter(|folder_name| !folder_name.is_empty()) {
+            path = path.join(folder_name);
+        }
+        path
+    }
+}
+
+/// Where substreams data gets requested from. The endpoint can be given in the config or injected through the
+/// SUBSTREAMS_ENDPOINT env var (in that order of precedence) otherwise it falls back to the chain's default endpoint
+#[derive(Clone)]
+pub(crate) struct StreamingEndpoint {
+    uri: Uri,
+    api_token: Option<String>,
+    block_number_field_tag: u32,
+}
+
+impl StreamingEndpoint {
+    pub(crate) fn new(chain: &Chain, endpoint_override: Option<String>) -> Result<Self, String> {
+        let uri = match endpoint_override.or_else(|| env::var("SUBSTREAMS_ENDPOINT").ok()) {
+            Some(endpoint) => endpoint.parse::<Uri>().map_err(|_| format!("Invalid substreams endpoint given: {}!", endpoint))?,
+            None => chain.get_endpoint()?,
+        };
+
+        Ok(StreamingEndpoint {
+            uri,
+            api_token: env::var("SUBSTREAMS_API_TOKEN").ok(),
+            block_number_field_tag: chain.block_number_field_tag,
+        })
+    }
+
+    pub(crate) fn get_block_number_field_tag(&self) -> u32 {
+        self.block_number_field_tag
+    }
+
+    pub(crate) fn get_channel(&self) -> Channel {
+        Channel::builder(self.uri.clone()).connect_lazy()
+    }
+
+    /// Adds the api token to the metadata of each request. No token is needed when streaming from a local endpoint
+    pub(crate) fn get_interceptor(&self) -> impl FnMut(tonic::Request<()>) -> Result<tonic::Request<()>, Status> + Clone {
+        let token_metadata = self.api_token.as_ref().map(|api_token| MetadataValue::try_from(api_token.as_str()).unwrap());
+        move |mut r: tonic::Request<()>| {
+            if let Some(token_metadata) = token_metadata.as_ref() {
+                r.metadata_mut().insert("authorization", token_metadata.clone());
+            }
+            Ok(r)
         }
     }
 }
@@ -110,3 +517,89 @@ impl ToJsonL for Vec<StreamingConfig> {
         json_lines.join("\n")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use crate::streaming_fast::streaming_config::{Chain, PartitionScheme, RetryConfig, StreamingConfig};
+
+    #[test]
+    fn test_partition_folder_names() {
+        // 2023-05-01T23:59:59Z and 2023-05-02T00:00:00Z
+        assert_eq!(PartitionScheme::Date.get_partition_folder_name(17170000, Some(1682985599)).unwrap(), "dt=2023-05-01");
+        assert_eq!(PartitionScheme::Date.get_partition_folder_name(17170001, Some(1682985600)).unwrap(), "dt=2023-05-02");
+        assert!(PartitionScheme::Date.get_partition_folder_name(17170002, None).is_err());
+
+        let block_bucket = PartitionScheme::BlockBucket { bucket_size: 1000 };
+        assert_eq!(block_bucket.get_partition_folder_name(17170999, None).unwrap(), "block_bucket=17170000");
+        assert_eq!(block_bucket.get_partition_folder_name(17171000, None).unwrap(), "block_bucket=17171000");
+    }
+
+    #[test]
+    fn test_sort_partition_folders() {
+        let folder_paths = ["dt=2022-12-31", "unrelated", "dt=2023-01-01"].into_iter().map(|folder_name| PathBuf::from("data").join(folder_name)).collect();
+        assert_eq!(PartitionScheme::Date.sort_partition_folders(folder_paths), vec![PathBuf::from("data/dt=2023-01-01"), PathBuf::from("data/dt=2022-12-31")]);
+
+        // Bucket numbers have to be compared numerically rather than as strings
+        let folder_paths = ["block_bucket=900", "block_bucket=1000"].into_iter().map(|folder_name| PathBuf::from("data").join(folder_name)).collect();
+        assert_eq!(PartitionScheme::BlockBucket { bucket_size: 100 }.sort_partition_folders(folder_paths), vec![PathBuf::from("data/block_bucket=1000"), PathBuf::from("data/block_bucket=900")]);
+
+        assert!(PartitionScheme::Date.sort_partition_folders(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let retry_config = RetryConfig {
+            max_attempts: 10,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1000,
+        };
+
+        let backoffs = (1..=6).map(|attempt| retry_config.get_backoff(attempt)).collect::<Vec<_>>();
+        assert_eq!(backoffs, [100, 200, 400, 800, 1000, 1000].into_iter().map(Duration::from_millis).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_chain_lookup() {
+        // Networks used in config/params.json along with the other chains substreams get deployed to
+        for network in ["mainnet", "polygon", "arbitrum", "optimism", "bsc", "goerli", "solana"] {
+            assert!(Chain::from_name(network, &[]).is_some(), "No built in chain for network: {}", network);
+        }
+
+        let polygon = Chain::from_name("Polygon", &[]).unwrap();
+        assert_eq!(polygon.add_chain_folders_to_path(PathBuf::from("data")), PathBuf::from("data/ethereum/polygon"));
+        assert_eq!(Chain::default_for_block_type("sf.ethereum.type.v2.Block", &[]).unwrap().name, "mainnet");
+        assert_eq!(Chain::default_for_block_type("sf.solana.type.v1.Block", &[]).unwrap().block_number_field_tag, 20);
+
+        // Chains given in the config take precedence over the built in ones
+        let chains: Vec<Chain> = serde_json::from_value(serde_json::json!([
+            { "name": "polygon", "endpoint": "http://localhost:9000", "block_type": "sf.ethereum.type.v2.Block", "output_path_prefix": "ethereum/polygon-archive" },
+        ])).unwrap();
+        let polygon = Chain::from_name("polygon", &chains).unwrap();
+        assert_eq!((polygon.endpoint.as_str(), polygon.block_number_field_tag), ("http://localhost:9000", 3));
+        assert_eq!(Chain::default_for_block_type("sf.ethereum.type.v2.Block", &chains).unwrap().name, "polygon");
+        assert!(Chain::from_name("unknown", &chains).is_none());
+        assert!(Chain::default_for_block_type("sf.unknown.type.v1.Block", &chains).is_none());
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let get_config = |extra_fields: &str| format!("{{\"output_module\": \"map_output\", \"param_overrides\": [], \"start_block_overrides\": []{}}}", extra_fields);
+
+        assert!(StreamingConfig::from_json(&get_config("")).is_ok());
+        assert!(StreamingConfig::from_json("{}").is_err());
+
+        assert_eq!(StreamingConfig::from_json(&get_config(", \"parquet\": {\"max_rows_per_row_group\": 0}")).err().unwrap(), "parquet.max_rows_per_row_group has to be greater than 0!");
+
+        assert!(StreamingConfig::from_json(&get_config(", \"parquet\": {\"big_numbers\": {\"type\": \"decimal\"}}")).is_ok());
+        assert_eq!(StreamingConfig::from_json(&get_config(", \"parquet\": {\"big_numbers\": {\"type\": \"decimal\", \"precision\": 39}}")).err().unwrap(), "parquet.big_numbers.precision has to be between 1 and 38! Got: 39");
+        assert!(StreamingConfig::from_json(&get_config(", \"parquet\": {\"big_numbers\": {\"type\": \"decimal\", \"precision\": 0, \"big_decimal_scale\": 0}}")).is_err());
+        assert_eq!(StreamingConfig::from_json(&get_config(", \"parquet\": {\"big_numbers\": {\"type\": \"decimal\", \"precision\": 10}}")).err().unwrap(), "parquet.big_numbers.big_decimal_scale: 18 can't be larger than it's precision: 10!");
+
+        assert!(StreamingConfig::from_json(&get_config(", \"partition\": {\"type\": \"block_bucket\", \"bucket_size\": 1000}")).is_ok());
+        assert_eq!(StreamingConfig::from_json(&get_config(", \"partition\": {\"type\": \"block_bucket\", \"bucket_size\": 0}")).err().unwrap(), "partition.bucket_size has to be greater than 0! Got: 0");
+        assert!(StreamingConfig::from_json(&get_config(", \"partition\": {\"type\": \"block_bucket\", \"bucket_size\": -5}")).is_err());
+    }
+}
@@ -1,15 +1,14 @@
 use std::fmt::Debug;
-use std::fs;
+use std::path::{Path, PathBuf};
 #[cfg(test)]
 use derives::TestData;
-use s3::Bucket;
-use s3::creds::Credentials;
-use crate::streaming_fast::file::Location;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use crate::streaming_fast::file::{CursorInfo, get_cursor_file_path, Location};
 use crate::streaming_fast::streamingfast_dtos::Package;
 
-/// Considers all output folder paths and takes the earliest start block from all output folders as the global start block
-pub(crate) async fn get_start_block_num(output_folder_paths: Vec<Location>, fallback_starting_block: i64) -> i64 {
-    get_start_block_numbers(output_folder_paths, fallback_starting_block).await.into_iter().min().unwrap()
+/// Considers all output folder paths and takes the earliest start position from all output folders as the global start position
+pub(crate) async fn get_start_position(output_folder_paths: Vec<Location>, fallback_starting_block: i64) -> Result<StartPosition, String> {
+    Ok(get_start_positions(output_folder_paths, fallback_starting_block).await?.into_iter().min_by_key(|start_position| start_position.block_number).unwrap())
 }
 
 pub(crate) fn get_initial_block_for_module(package: &Package, proto_type_name: &str) -> i64 {
@@ -22,51 +21,298 @@ pub(crate) fn get_initial_block_for_module(package: &Package, proto_type_name: &
     panic!("Unable to match the module output: {} to a given module!", proto_type_name);
 }
 
-/// Returns a list of block number where each block number is the starting block for the
-/// corresponding output folder path in the input list for the same element number
-pub(crate) async fn get_start_block_numbers(output_folder_paths: Vec<Location>, fallback_starting_block: i64) -> Vec<i64> {
-    let mut starting_block_numbers = Vec::new();
+/// Where streaming should resume from for a given output folder. If the latest file in the folder has a cursor
+/// sidecar then the block number will be the block following that cursor, otherwise it is derived from the filenames
+#[derive(Clone)]
+pub(crate) struct StartPosition {
+    pub(crate) block_number: i64,
+    pub(crate) cursor: Option<String>,
+}
+
+/// Returns a list of start positions where each start position corresponds to the
+/// output folder path in the input list for the same element number
+pub(crate) async fn get_start_positions(output_folder_paths: Vec<Location>, fallback_starting_block: i64) -> Result<Vec<StartPosition>, String> {
+    let mut start_positions = Vec::new();
 
     for output_folder_path in output_folder_paths.into_iter() {
-        let processed_block_files = match output_folder_path {
-            Location::DataWarehouse(path, bucket_name) => {
-                let region = "us-west-2".parse().unwrap();
-                let credentials = Credentials::default().unwrap();
-                let bucket = Bucket::new(&bucket_name, region, credentials).unwrap();
-                let list_response = bucket.list(path.to_string_lossy().to_string() + "/", Some("/".to_string())).await.unwrap();
-                list_response[0].contents.clone().into_iter().map(|x| x.key).collect::<Vec<_>>()
+        start_positions.push(get_start_position_for_location(&output_folder_path, fallback_starting_block).await?);
+    }
+
+    Ok(start_positions)
+}
+
+/// Files can finish uploading out of order, but a cursor sidecar is only written once every file before it has been
+/// saved. So streaming resumes from the latest file with a cursor, only falling back to the latest filename if
+/// none of the files have cursors (eg. for folders written before cursors were saved)
+async fn get_start_position_for_location(output_folder_path: &Location, fallback_starting_block: i64) -> Result<StartPosition, String> {
+    let latest_cursor = get_latest_cursor(output_folder_path).await?;
+
+    Ok(match latest_cursor.cursor_info {
+        Some(cursor_info) => StartPosition {
+            block_number: cursor_info.block_number + 1,
+            cursor: Some(cursor_info.cursor),
+        },
+        None => StartPosition {
+            block_number: latest_cursor.latest_file_block_num.unwrap_or(fallback_starting_block),
+            cursor: None,
+        },
+    })
+}
+
+/// Files after the latest cursor get written again once streaming resumes from that cursor, and not necessarily
+/// under the same names (eg. if the file size threshold has changed). So they get deleted beforehand to stop them
+/// overlapping the new files. Nothing gets deleted if none of the files have cursors, as streaming then resumes from
+/// the latest filename instead. Returns the deleted files
+pub(crate) async fn delete_files_after_latest_cursor(output_folder_path: &Location) -> Result<Vec<PathBuf>, String> {
+    let latest_cursor = get_latest_cursor(output_folder_path).await?;
+    if latest_cursor.cursor_info.is_none() {
+        return Ok(Vec::new());
+    }
+
+    for file_path in latest_cursor.files_after_cursor.iter() {
+        output_folder_path.get_storage().delete(file_path).await?;
+    }
+
+    Ok(latest_cursor.files_after_cursor)
+}
+
+/// The cursor of the latest file in an output folder that has one
+struct LatestCursor {
+    cursor_info: Option<CursorInfo>,
+    /// Files holding later blocks than the cursor, latest first. These have no cursor yet as some file before them
+    /// never finished uploading
+    files_after_cursor: Vec<PathBuf>,
+    /// Last block number of the latest file in the folder whether it has a cursor or not
+    latest_file_block_num: Option<i64>,
+}
+
+async fn get_latest_cursor(output_folder_path: &Location) -> Result<LatestCursor, String> {
+    let mut latest_file_block_num = None;
+    let mut files_after_cursor = Vec::new();
+
+    for files_folder_path in output_folder_path.get_files_folder_paths().await? {
+        let processed_block_files = output_folder_path.get_storage().list(&files_folder_path).await?;
+        let (cursor_files, block_files): (Vec<_>, Vec<_>) = processed_block_files.into_iter().partition(|file| file.extension().map(|extension| extension == "cursor").unwrap_or(false));
+
+        // Each encoding type gets written to it's own folder so the only other files expected here are cursor files
+        let mut block_files = block_files.into_iter().filter_map(|file| {
+            get_last_block_num(&file).map(|last_block_num| (last_block_num, file))
+        }).collect::<Vec<_>>();
+        block_files.sort_by_key(|(last_block_num, _)| -last_block_num);
+
+        if latest_file_block_num.is_none() {
+            latest_file_block_num = block_files.first().map(|(last_block_num, _)| *last_block_num);
+        }
+
+        for (_, block_file) in block_files.into_iter() {
+            if cursor_files.contains(&get_cursor_file_path(&block_file)) {
+                if let Some(cursor_info) = get_cursor_info(output_folder_path, &block_file).await? {
+                    return Ok(LatestCursor {
+                        cursor_info: Some(cursor_info),
+                        files_after_cursor,
+                        latest_file_block_num,
+                    });
+                }
+            }
+            files_after_cursor.push(block_file);
+        }
+    }
+
+    Ok(LatestCursor {
+        cursor_info: None,
+        files_after_cursor,
+        latest_file_block_num,
+    })
+}
+
+/// Returns the path of the file holding the most recent blocks in the output folder, if there are any files yet
+pub(crate) async fn get_latest_file_path(output_folder_path: &Location) -> Result<Option<PathBuf>, String> {
+    for files_folder_path in output_folder_path.get_files_folder_paths().await? {
+        let processed_block_files = output_folder_path.get_storage().list(&files_folder_path).await?;
+
+        let latest_file = processed_block_files.into_iter()
+            .filter(|file| file.extension().map(|extension| extension != "cursor").unwrap_or(true))
+            .filter_map(|file| get_last_block_num(&file).map(|last_block_num| (last_block_num, file)))
+            .max_by_key(|(last_block_num, _)| *last_block_num);
+        if let Some((_, latest_file)) = latest_file {
+            return Ok(Some(latest_file));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Files are expected to be in the form -> startBlock_stopBlock.fileExtension. Anything else in the folder (eg. a file
+/// copied in by hand) is skipped with a warning rather than stopping the run
+fn get_last_block_num(file_path: &Path) -> Option<i64> {
+    let block_range = get_file_block_range(file_path);
+    if block_range.is_none() {
+        eprintln!("Skipping file: {}! It's name isn't in the form startBlock_stopBlock", file_path.display());
+    }
+    block_range.map(|(_, stop_block)| stop_block)
+}
+
+/// A file in an output folder along with the block range taken from it's filename -> startBlock_stopBlock.fileExtension
+pub(crate) struct BlockRangeFile {
+    pub(crate) path: PathBuf,
+    pub(crate) start_block: i64,
+    pub(crate) stop_block: i64,
+}
+
+/// Everything found to be wrong with the files in an output folder
+pub(crate) struct OutputFolderAudit {
+    pub(crate) folder_display_path: String,
+    /// Sorted by start block
+    pub(crate) files: Vec<BlockRangeFile>,
+    /// Block ranges not covered by any of the files in the form -> (first_missing_block, last_missing_block)
+    pub(crate) gaps: Vec<(i64, i64)>,
+    /// Pairs of files covering some of the same blocks
+    pub(crate) overlaps: Vec<(PathBuf, PathBuf)>,
+    /// Only filled in when rows are counted
+    pub(crate) empty_files: Vec<PathBuf>,
+    pub(crate) unparsable_files: Vec<PathBuf>,
+}
+
+impl OutputFolderAudit {
+    pub(crate) fn has_issues(&self) -> bool {
+        !(self.gaps.is_empty() && self.overlaps.is_empty() && self.empty_files.is_empty() && self.unparsable_files.is_empty())
+    }
+}
+
+/// Lists every file in the output folder (across all of it's partitions) and checks that together they cover every
+/// block from the initial block up to the latest file. Counting rows means downloading every file, so it's optional
+pub(crate) async fn audit_output_folder(output_folder_path: &Location, initial_block: i64, count_rows: bool) -> Result<OutputFolderAudit, String> {
+    let storage = output_folder_path.get_storage();
+
+    let mut files = Vec::new();
+    let mut unparsable_files = Vec::new();
+    for files_folder_path in output_folder_path.get_files_folder_paths().await? {
+        for file_path in storage.list(&files_folder_path).await? {
+            if file_path.extension().map(|extension| extension == "cursor").unwrap_or(false) {
+                continue;
             }
-            Location::Local(path) => {
-                fs::read_dir(path).unwrap().into_iter().map(|path| path.unwrap().path().display().to_string()).collect::<Vec<_>>()
+
+            match get_file_block_range(&file_path) {
+                Some((start_block, stop_block)) => files.push(BlockRangeFile {
+                    path: file_path,
+                    start_block,
+                    stop_block,
+                }),
+                None => unparsable_files.push(file_path),
             }
-        };
-
-        if processed_block_files.len() > 0 {
-            // For now we will just assume all files will be in form -> startBlock_stopBlock.fileExtension
-            let mut last_block_num_iterator = processed_block_files.into_iter().filter(|file| file.ends_with(".parquet")).map(|file| {
-                file.split('.').next().unwrap().split('_').last().unwrap().parse::<i64>().unwrap()
-            });
-            if let Some(mut latest_block_num) = last_block_num_iterator.next() {
-                for block_num in last_block_num_iterator {
-                    if block_num > latest_block_num {
-                        latest_block_num = block_num;
-                    }
-                }
-                starting_block_numbers.push(latest_block_num);
-            } else {
-                starting_block_numbers.push(fallback_starting_block);
+        }
+    }
+    files.sort_by_key(|file| (file.start_block, file.stop_block));
+
+    let mut empty_files = Vec::new();
+    if count_rows {
+        for file in files.iter() {
+            let file_data = storage.get(&file.path).await?.unwrap_or_default();
+            if get_num_rows(&file.path, file_data)? == 0 {
+                empty_files.push(file.path.clone());
+            }
+        }
+    }
+
+    let (gaps, overlaps) = get_gaps_and_overlaps(&files, initial_block);
+
+    Ok(OutputFolderAudit {
+        folder_display_path: storage.get_display_path(output_folder_path.get_path()),
+        files,
+        gaps,
+        overlaps,
+        empty_files,
+        unparsable_files,
+    })
+}
+
+fn get_file_block_range(file_path: &Path) -> Option<(i64, i64)> {
+    let file_stem = file_path.file_stem()?.to_string_lossy().to_string();
+    let (start_block, stop_block) = file_stem.split_once('_')?;
+    let (start_block, stop_block) = (start_block.parse::<i64>().ok()?, stop_block.parse::<i64>().ok()?);
+
+    if start_block > stop_block {
+        return None;
+    }
+    Some((start_block, stop_block))
+}
+
+/// Files written mid-stream are named after the last block they hold, while files flushed at the end of a run are
+/// named after the (exclusive) stop block of the run. So a file starting at either the stop block of the file before
+/// it or the block after is treated as contiguous
+fn get_gaps_and_overlaps(files: &[BlockRangeFile], initial_block: i64) -> (Vec<(i64, i64)>, Vec<(PathBuf, PathBuf)>) {
+    let mut gaps = Vec::new();
+    let mut overlaps = Vec::new();
+
+    if let Some(first_file) = files.first() {
+        if first_file.start_block > initial_block {
+            gaps.push((initial_block, first_file.start_block - 1));
+        }
+    }
+
+    // Compared against the file reaching the furthest so far so that files fully covered by an earlier file are caught too
+    let mut furthest_file: Option<&BlockRangeFile> = None;
+    for file in files.iter() {
+        if let Some(previous_file) = furthest_file {
+            if file.start_block > previous_file.stop_block + 1 {
+                gaps.push((previous_file.stop_block + 1, file.start_block - 1));
+            } else if file.start_block < previous_file.stop_block {
+                overlaps.push((previous_file.path.clone(), file.path.clone()));
+            }
+        }
+
+        if furthest_file.map(|previous_file| file.stop_block > previous_file.stop_block).unwrap_or(true) {
+            furthest_file = Some(file);
+        }
+    }
+
+    (gaps, overlaps)
+}
+
+fn get_num_rows(file_path: &Path, file_data: Vec<u8>) -> Result<i64, String> {
+    let num_lines = || String::from_utf8_lossy(&file_data).lines().filter(|line| !line.trim().is_empty()).count() as i64;
+
+    match file_path.extension().map(|extension| extension.to_string_lossy().to_string()).as_deref() {
+        Some("parquet") => {
+            if file_data.is_empty() {
+                return Ok(0);
             }
-        } else {
-            starting_block_numbers.push(fallback_starting_block);
+            let reader = SerializedFileReader::new(bytes::Bytes::from(file_data)).map_err(|error| format!("Unable to read parquet file: {}! Error: {}", file_path.display(), error))?;
+            Ok(reader.metadata().file_metadata().num_rows())
         }
+        // The first line of a csv file is it's header
+        Some("csv") => Ok((num_lines() - 1).max(0)),
+        _ => Ok(num_lines()),
     }
+}
+
+/// Reads the cursor sidecar for the given file if one was saved alongside it
+async fn get_cursor_info(output_folder_path: &Location, file_path: &PathBuf) -> Result<Option<CursorInfo>, String> {
+    let cursor_file_path = get_cursor_file_path(file_path);
+    let cursor_data = match output_folder_path.get_storage().get(&cursor_file_path).await? {
+        Some(cursor_data) => cursor_data,
+        None => return Ok(None),
+    };
 
-    starting_block_numbers
+    match serde_json::from_slice(&cursor_data) {
+        Ok(cursor_info) => Ok(Some(cursor_info)),
+        Err(error) => {
+            println!("Unable to parse cursor file: {}, falling back to filename based restart! Error: {}", cursor_file_path.to_string_lossy(), error);
+            Ok(None)
+        }
+    }
 }
 
 #[cfg(test)]
 pub(crate) fn assert_data_sinks_to_parquet_correctly<T: TestData + Debug>() {
-    use crate::streaming_fast::file_sinks::file_sink::FileSink;
+    assert_data_sinks_to_parquet_correctly_with_config::<T>(&crate::streaming_fast::streaming_config::ParquetConfig::default());
+}
+
+#[cfg(test)]
+pub(crate) fn assert_data_sinks_to_parquet_correctly_with_config<T: TestData + Debug>(parquet_config: &crate::streaming_fast::streaming_config::ParquetConfig) {
+    use crate::streaming_fast::file_sinks::file_sink::{BlockMetadata, FileSink};
+    use crate::streaming_fast::file_sinks::parquet::ParquetFileSink;
+    use crate::streaming_fast::streaming_config::BlockMetadataColumns;
     use parquet::file::reader::{FileReader, SerializedFileReader};
     use rand::rngs::StdRng;
     use rand::SeedableRng;
@@ -78,15 +324,17 @@ pub(crate) fn assert_data_sinks_to_parquet_correctly<T: TestData + Debug>() {
     let test_data = T::get_samples(NUM_SAMPLES, &mut rng);
     let test_block_numbers = (0..NUM_SAMPLES).into_iter().map(|_| rng.gen()).collect::<Vec<i64>>();
 
-    let mut sink = ParquetFileSink::new(T::get_proto_structure_info());
+    let mut sink = ParquetFileSink::new_with_config(T::get_proto_structure_info(), parquet_config, BlockMetadataColumns::default());
     for (test_datum, test_block_number) in test_data.iter().zip(test_block_numbers.iter()) {
         let bytes: Vec<u8> = test_datum.to_proto_bytes();
 
-        sink.process(&mut bytes.as_slice(), *test_block_number).unwrap();
+        sink.process(&mut bytes.as_slice(), &BlockMetadata::from_block_number(*test_block_number)).unwrap();
     }
 
     let parquet_file_data = sink.make_file();
     let reader = SerializedFileReader::new(bytes::Bytes::from(parquet_file_data)).unwrap();
+    let expected_num_row_groups = (NUM_SAMPLES + parquet_config.max_rows_per_row_group - 1) / parquet_config.max_rows_per_row_group;
+    assert_eq!(reader.metadata().num_row_groups(), expected_num_row_groups);
 
     for ((parquet_row, test_datum), test_block_number) in reader.get_row_iter(None).unwrap().zip(test_data).zip(test_block_numbers.into_iter()) {
         let (parsed_data, block_number_result) = T::get_from_parquet_row(parquet_row.get_column_iter());
@@ -166,4 +414,71 @@ impl<T: Default + TryFrom<u64>> FromUnsignedVarint for T
         *data = &data[idx..];
         Some(result)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::streaming_fast::file::Location;
+    use crate::streaming_fast::storage::{LocalStorage, StorageBackend};
+    use crate::streaming_fast::streaming_fast_utils::{audit_output_folder, get_latest_file_path, get_start_positions};
+    use crate::test_utils::get_temp_dir;
+
+    #[tokio::test]
+    async fn test_audit_output_folder() {
+        let temp_dir = get_temp_dir();
+        let folder_path = temp_dir.path().to_path_buf();
+        let storage = LocalStorage;
+
+        // 0-99 and 100-199 are contiguous, 200 is the exclusive stop block of the file before it, 250-299 are missing,
+        // 300-399 is overlapped by 350-449 and 360-370 is fully covered by 350-449
+        for file_name in ["0_99.jsonl", "100_200.jsonl", "200_249.jsonl", "300_399.jsonl", "350_449.jsonl", "360_370.jsonl"] {
+            storage.put(&folder_path.join(file_name), b"{}\n".to_vec()).await.unwrap();
+        }
+        storage.put(&folder_path.join("200_249.cursor"), b"{}".to_vec()).await.unwrap();
+        storage.put(&folder_path.join("450_499.jsonl"), Vec::new()).await.unwrap();
+        storage.put(&folder_path.join("latest.jsonl"), b"{}\n".to_vec()).await.unwrap();
+
+        let location = Location::new(Arc::new(LocalStorage), folder_path.clone(), None);
+        let audit = audit_output_folder(&location, 0, true).await.unwrap();
+        assert_eq!(audit.files.len(), 7);
+        assert_eq!(audit.gaps, vec![(250, 299)]);
+        assert_eq!(audit.overlaps, vec![
+            (folder_path.join("300_399.jsonl"), folder_path.join("350_449.jsonl")),
+            (folder_path.join("350_449.jsonl"), folder_path.join("360_370.jsonl")),
+        ]);
+        assert_eq!(audit.empty_files, vec![folder_path.join("450_499.jsonl")]);
+        assert_eq!(audit.unparsable_files, vec![folder_path.join("latest.jsonl")]);
+        assert!(audit.has_issues());
+
+        // Blocks between the module's initial block and the first file are missing too
+        std::fs::remove_file(folder_path.join("0_99.jsonl")).unwrap();
+        let audit = audit_output_folder(&location, 0, false).await.unwrap();
+        assert_eq!(audit.gaps, vec![(0, 99), (250, 299)]);
+        assert!(audit.empty_files.is_empty());
+
+        let empty_location = Location::new(Arc::new(LocalStorage), folder_path.join("empty"), None);
+        assert!(!audit_output_folder(&empty_location, 0, true).await.unwrap().has_issues());
+    }
+
+    #[tokio::test]
+    async fn test_unparsable_file_names_are_skipped() {
+        let temp_dir = get_temp_dir();
+        let folder_path = temp_dir.path().to_path_buf();
+        let storage = LocalStorage;
+
+        for file_name in ["100_109.parquet", "110_119.parquet", "latest.parquet", "110_119_copy.parquet"] {
+            storage.put(&folder_path.join(file_name), Vec::new()).await.unwrap();
+        }
+
+        let location = Location::new(Arc::new(LocalStorage), folder_path.clone(), None);
+        assert_eq!(get_latest_file_path(&location).await.unwrap(), Some(folder_path.join("110_119.parquet")));
+        let start_positions = get_start_positions(vec![location.clone()], 0).await.unwrap();
+        assert_eq!((start_positions[0].block_number, start_positions[0].cursor.clone()), (119, None));
+
+        storage.put(&folder_path.join("100_109.cursor"), b"{\"cursor\": \"abc\", \"block_number\": 109}".to_vec()).await.unwrap();
+        let start_positions = get_start_positions(vec![location], 0).await.unwrap();
+        assert_eq!((start_positions[0].block_number, start_positions[0].cursor.clone()), (110, Some("abc".to_string())));
+    }
+}
@@ -0,0 +1,7 @@
+use tempfile::TempDir;
+
+/// Fresh folder for the files written by a test. The folder (and everything in it) gets deleted when the TempDir is
+/// dropped, so it's cleaned up even when an assertion fails part way through the test
+pub(crate) fn get_temp_dir() -> TempDir {
+    tempfile::Builder::new().prefix("messari_cli_test_").tempdir().unwrap()
+}
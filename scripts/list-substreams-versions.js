@@ -1,9 +0,0 @@
-
-const { listCurrentVersions } = require("./versions/versions");
-
-
-(() => {
-    const versions = listCurrentVersions();
-    console.log(JSON.stringify(versions));
-})();
-